@@ -182,7 +182,7 @@ fn main() {
     let weak_children = children.iter().map(|e| **e).collect::<Vec<_>>();
 
     world
-        .query_one_mut::<&mut Parent>(&parent)
+        .query_one_mut::<&mut Parent, _>(&parent)
         .unwrap()
         .children = children;
 
@@ -0,0 +1,412 @@
+//! This module defines [`Allocator`] trait used by [`World`]
+//! to allocate memory for components and entities.
+//!
+//! `Allocator` is modelled after unstable `core::alloc::Allocator`
+//! and may be replaced by it once stabilized.
+//!
+//! Storage of components and entities is allocated with `Allocator`.
+//! `World` still needs global allocator for its bookkeeping collections.
+//!
+//! [`World`]: `edict::world::World`
+
+use core::{
+    alloc::Layout,
+    fmt, mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+    sync::atomic::{fence, AtomicUsize, Ordering},
+};
+
+/// Allocator of memory blocks.
+///
+/// `World` uses allocator for component columns and entity lists of archetypes,
+/// entity table, shared data of entities and drop queue.
+/// Bookkeeping collections of the `World` use global allocator.
+///
+/// # Safety
+///
+/// Memory blocks returned from `allocate` and `allocate_zeroed`
+/// must stay valid until passed to `deallocate`
+/// and must fit the layout requested.
+///
+/// Blocks of zero size may be dangling, but must be aligned.
+pub unsafe trait Allocator: Send + Sync + 'static {
+    /// Allocates memory block that fits the layout.
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError>;
+
+    /// Allocates zero-initialized memory block that fits the layout.
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        let ptr = self.allocate(layout)?;
+        unsafe { ptr::write_bytes(ptr.as_ptr(), 0, layout.size()) };
+        Ok(ptr)
+    }
+
+    /// Deallocates memory block.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be allocated by this allocator with the same `layout`.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+/// Global memory allocator.
+/// Default allocator of the `World`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+        NonNull::new(unsafe { alloc::alloc::alloc(layout) }).ok_or(AllocError)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+        NonNull::new(unsafe { alloc::alloc::alloc_zeroed(layout) }).ok_or(AllocError)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            alloc::alloc::dealloc(ptr.as_ptr(), layout)
+        }
+    }
+}
+
+/// Error returned by [`Allocator`] when memory block cannot be allocated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Memory allocation failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AllocError {}
+
+/// Type-erased allocator shared between storages of one `World`.
+///
+/// Allocator is moved into memory block allocated by itself,
+/// so sharing it does not need global allocator.
+pub(crate) struct SharedAllocator {
+    inner: NonNull<SharedAllocatorInner<dyn Allocator>>,
+    release: unsafe fn(NonNull<SharedAllocatorInner<dyn Allocator>>),
+}
+
+struct SharedAllocatorInner<A: ?Sized> {
+    refs: AtomicUsize,
+    alloc: A,
+}
+
+/// # Safety
+///
+/// This is basically `Arc<dyn Allocator>`
+/// and `Allocator` is `Send` and `Sync`.
+unsafe impl Send for SharedAllocator {}
+
+/// # Safety
+///
+/// This is basically `Arc<dyn Allocator>`
+/// and `Allocator` is `Send` and `Sync`.
+unsafe impl Sync for SharedAllocator {}
+
+impl fmt::Debug for SharedAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedAllocator")
+    }
+}
+
+impl Deref for SharedAllocator {
+    type Target = dyn Allocator;
+
+    #[inline]
+    fn deref(&self) -> &dyn Allocator {
+        unsafe { &(*self.inner.as_ptr()).alloc }
+    }
+}
+
+impl Clone for SharedAllocator {
+    #[inline]
+    fn clone(&self) -> Self {
+        unsafe { &*self.inner.as_ptr() }
+            .refs
+            .fetch_add(1, Ordering::Relaxed);
+
+        SharedAllocator {
+            inner: self.inner,
+            release: self.release,
+        }
+    }
+}
+
+impl Drop for SharedAllocator {
+    fn drop(&mut self) {
+        let old = unsafe { &*self.inner.as_ptr() }
+            .refs
+            .fetch_sub(1, Ordering::Release);
+
+        if old == 1 {
+            fence(Ordering::Acquire);
+            unsafe { (self.release)(self.inner) }
+        }
+    }
+}
+
+impl SharedAllocator {
+    pub fn new<A>(alloc: A) -> Self
+    where
+        A: Allocator,
+    {
+        let layout = Layout::new::<SharedAllocatorInner<A>>();
+        let ptr = match alloc.allocate(layout) {
+            Ok(ptr) => ptr.cast::<SharedAllocatorInner<A>>(),
+            Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+        };
+
+        unsafe {
+            ptr::write(
+                ptr.as_ptr(),
+                SharedAllocatorInner {
+                    refs: AtomicUsize::new(1),
+                    alloc,
+                },
+            );
+        }
+
+        SharedAllocator {
+            inner: ptr,
+            release: release::<A>,
+        }
+    }
+
+    /// Allocates memory block that fits the layout.
+    /// Aborts on allocation failure.
+    #[inline]
+    pub fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        match self.allocate(layout) {
+            Ok(ptr) => ptr,
+            Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+        }
+    }
+
+    /// Allocates zero-initialized memory block that fits the layout.
+    /// Aborts on allocation failure.
+    #[inline]
+    pub fn alloc_zeroed(&self, layout: Layout) -> NonNull<u8> {
+        match self.allocate_zeroed(layout) {
+            Ok(ptr) => ptr,
+            Err(AllocError) => alloc::alloc::handle_alloc_error(layout),
+        }
+    }
+}
+
+/// Moves allocator out of its block and deallocates the block with it.
+unsafe fn release<A>(inner: NonNull<SharedAllocatorInner<dyn Allocator>>)
+where
+    A: Allocator,
+{
+    let inner = inner.cast::<SharedAllocatorInner<A>>();
+    let alloc = ptr::read(ptr::addr_of!((*inner.as_ptr()).alloc));
+    alloc.deallocate(inner.cast(), Layout::new::<SharedAllocatorInner<A>>());
+}
+
+/// Growable array of `Copy` values allocated with [`SharedAllocator`].
+pub(crate) struct AllocVec<T> {
+    ptr: NonNull<T>,
+    cap: usize,
+    len: usize,
+    alloc: SharedAllocator,
+}
+
+unsafe impl<T: Send> Send for AllocVec<T> {}
+unsafe impl<T: Sync> Sync for AllocVec<T> {}
+
+impl<T> Drop for AllocVec<T> {
+    fn drop(&mut self) {
+        if self.cap != 0 {
+            unsafe {
+                self.alloc
+                    .deallocate(self.ptr.cast(), Layout::array::<T>(self.cap).unwrap());
+            }
+        }
+    }
+}
+
+impl<T> Deref for AllocVec<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for AllocVec<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> fmt::Debug for AllocVec<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Clone for AllocVec<T>
+where
+    T: Copy,
+{
+    fn clone(&self) -> Self {
+        let mut vec = AllocVec::new(self.alloc.clone());
+        vec.extend_from_slice(self);
+        vec
+    }
+}
+
+impl<T> Extend<T> for AllocVec<T>
+where
+    T: Copy,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T> AllocVec<T>
+where
+    T: Copy,
+{
+    /// Returns empty array. Does not allocate.
+    #[inline]
+    pub fn new(alloc: SharedAllocator) -> Self {
+        assert_ne!(
+            mem::size_of::<T>(),
+            0,
+            "Zero-sized values are not supported"
+        );
+
+        AllocVec {
+            ptr: NonNull::dangling(),
+            cap: 0,
+            len: 0,
+            alloc,
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Reserves space for at least `additional` more values.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        if self.cap - self.len < additional {
+            let required = self.len.checked_add(additional).expect("Capacity overflow");
+            self.grow(required.max(self.cap * 2).max(4));
+        }
+    }
+
+    /// Reserves space for exactly `additional` more values.
+    #[inline]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if self.cap - self.len < additional {
+            let required = self.len.checked_add(additional).expect("Capacity overflow");
+            self.grow(required);
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+        unsafe { ptr::write(self.ptr.as_ptr().add(self.len), value) };
+        self.len += 1;
+    }
+
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { ptr::read(self.ptr.as_ptr().add(self.len)) })
+        }
+    }
+
+    /// Removes value at `idx` replacing it with the last one.
+    #[inline]
+    pub fn swap_remove(&mut self, idx: usize) -> T {
+        let value = self[idx];
+        let last = self.len - 1;
+        self[idx] = self[last];
+        self.len = last;
+        value
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        self.reserve(values.len());
+        unsafe {
+            ptr::copy_nonoverlapping(
+                values.as_ptr(),
+                self.ptr.as_ptr().add(self.len),
+                values.len(),
+            );
+        }
+        self.len += values.len();
+    }
+
+    #[cold]
+    fn grow(&mut self, new_cap: usize) {
+        let new_layout = Layout::array::<T>(new_cap).expect("Capacity overflow");
+        let ptr = self.alloc.alloc(new_layout).cast::<T>();
+
+        if self.cap != 0 {
+            unsafe {
+                ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len);
+                self.alloc
+                    .deallocate(self.ptr.cast(), Layout::array::<T>(self.cap).unwrap());
+            }
+        }
+
+        self.ptr = ptr;
+        self.cap = new_cap;
+    }
+}
+
+#[inline]
+fn dangling(layout: Layout) -> NonNull<u8> {
+    unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+}
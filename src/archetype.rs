@@ -1,24 +1,30 @@
+//! This module implements [`Archetype`] storage of components.
+//!
+//! Archetypes and their [`Column`]s are exposed
+//! for custom [`Query`] implementations.
+//!
+//! [`Query`]: `edict::query::Query`
+
 use core::{
     alloc::Layout,
-    any::TypeId,
+    any::{type_name, Any, TypeId},
     cell::UnsafeCell,
+    fmt,
     hint::unreachable_unchecked,
     intrinsics::copy_nonoverlapping,
+    marker::PhantomData,
     mem::{self, MaybeUninit},
-    ops::Deref,
+    ops::{Deref, Range},
     ptr::{self, NonNull},
 };
 
-use alloc::{
-    alloc::{alloc, alloc_zeroed, dealloc},
-    boxed::Box,
-    vec::Vec,
-};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 
 use crate::{
+    allocator::{AllocVec, SharedAllocator},
     bundle::DynamicBundle,
-    component::{Component, ComponentInfo},
-    entity::EntityId,
+    component::{Component, ComponentInfo, SharedComponent},
+    entity::{EntityId, EntityMap},
     idx::MAX_IDX_USIZE,
     typeidset::TypeIdSet,
 };
@@ -260,7 +266,13 @@ impl ComponentData {
         Self::new(&ComponentInfo::of::<Dummy>())
     }
 
-    pub unsafe fn grow(&mut self, len: usize, old_cap: usize, new_cap: usize) {
+    pub unsafe fn grow(
+        &mut self,
+        alloc: &SharedAllocator,
+        len: usize,
+        old_cap: usize,
+        new_cap: usize,
+    ) {
         let old_layout = Layout::from_size_align_unchecked(
             self.info.layout.size() * old_cap,
             self.info.layout.align(),
@@ -272,7 +284,7 @@ impl ComponentData {
         );
 
         if self.info.layout.size() != 0 {
-            let mut ptr = NonNull::new_unchecked(alloc(new_layout));
+            let mut ptr = alloc.alloc(new_layout);
             if len != 0 {
                 copy_nonoverlapping(
                     self.ptr.as_ptr(),
@@ -283,21 +295,22 @@ impl ComponentData {
 
             if old_cap != 0 {
                 mem::swap(&mut self.ptr, &mut ptr);
-                dealloc(ptr.as_ptr(), old_layout);
+                alloc.deallocate(ptr, old_layout);
             } else {
                 self.ptr = ptr;
             }
         }
 
-        let mut ptr =
-            NonNull::new_unchecked(alloc_zeroed(Layout::array::<u64>(new_cap).unwrap())).cast();
+        let mut ptr = alloc
+            .alloc_zeroed(Layout::array::<u64>(new_cap).unwrap())
+            .cast();
         if len != 0 {
             copy_nonoverlapping(self.entity_versions.as_ptr(), ptr.as_ptr(), len);
         }
 
         if old_cap != 0 {
             mem::swap(&mut self.entity_versions, &mut ptr);
-            dealloc(ptr.cast().as_ptr(), Layout::array::<u64>(old_cap).unwrap());
+            alloc.deallocate(ptr.cast(), Layout::array::<u64>(old_cap).unwrap());
         } else {
             self.entity_versions = ptr;
         }
@@ -306,45 +319,209 @@ impl ComponentData {
             let old_cap = chunks_count(old_cap);
             let new_cap = chunks_count(new_cap);
 
-            let mut ptr =
-                NonNull::new_unchecked(alloc_zeroed(Layout::array::<u64>(new_cap).unwrap())).cast();
+            let mut ptr = alloc
+                .alloc_zeroed(Layout::array::<u64>(new_cap).unwrap())
+                .cast();
 
-            copy_nonoverlapping(self.chunk_versions.as_ptr(), ptr.as_ptr(), len);
+            copy_nonoverlapping(self.chunk_versions.as_ptr(), ptr.as_ptr(), old_cap);
 
             if old_cap != 0 {
                 mem::swap(&mut self.chunk_versions, &mut ptr);
-                dealloc(ptr.cast().as_ptr(), Layout::array::<u64>(old_cap).unwrap());
+                alloc.deallocate(ptr.cast(), Layout::array::<u64>(old_cap).unwrap());
             } else {
                 self.chunk_versions = ptr;
             }
         }
     }
+
+    /// Deallocates memory of the column with specified capacity.
+    /// Values must be dropped or moved out beforehand.
+    pub unsafe fn free(&mut self, alloc: &SharedAllocator, cap: usize) {
+        if cap == 0 {
+            return;
+        }
+
+        if self.info.layout.size() != 0 {
+            alloc.deallocate(
+                self.ptr,
+                Layout::from_size_align_unchecked(
+                    self.info.layout.size() * cap,
+                    self.info.layout.align(),
+                ),
+            );
+        }
+
+        alloc.deallocate(
+            self.entity_versions.cast(),
+            Layout::array::<u64>(cap).unwrap(),
+        );
+
+        alloc.deallocate(
+            self.chunk_versions.cast(),
+            Layout::array::<u64>(chunks_count(cap)).unwrap(),
+        );
+    }
+}
+
+/// Configuration of component storage shared by archetypes of one `World`.
+#[derive(Clone, Debug)]
+pub(crate) struct StorageConfig {
+    /// Allocator of component columns.
+    pub alloc: SharedAllocator,
+
+    /// Capacity of component columns allocated up front.
+    /// Columns never grow beyond it.
+    /// `None` if columns grow on demand.
+    pub fixed_capacity: Option<usize>,
+
+    /// Keeps order of entities when entities are removed.
+    pub deterministic: bool,
+}
+
+/// Type-erased value of a shared component.
+trait AnyShared {
+    fn as_any(&self) -> &dyn Any;
+    fn eq_any(&self, other: &dyn Any) -> bool;
+}
+
+impl<T> AnyShared for T
+where
+    T: SharedComponent,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_any(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<T>() == Some(self)
+    }
+}
+
+#[derive(Clone)]
+struct SharedValue {
+    id: TypeId,
+    name: &'static str,
+    value: Arc<dyn AnyShared>,
+}
+
+/// Values of shared components of an archetype.
+/// Sorted by type id.
+#[derive(Clone, Default)]
+pub(crate) struct SharedValues {
+    values: Vec<SharedValue>,
+}
+
+impl fmt::Debug for SharedValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.values.iter().map(|value| value.name))
+            .finish()
+    }
+}
+
+impl PartialEq for SharedValues {
+    fn eq(&self, other: &Self) -> bool {
+        self.values.len() == other.values.len()
+            && self
+                .values
+                .iter()
+                .zip(&other.values)
+                .all(|(lhs, rhs)| lhs.id == rhs.id && lhs.value.eq_any(rhs.value.as_any()))
+    }
+}
+
+impl SharedValues {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns value of shared component of type `T`.
+    #[inline]
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: 'static,
+    {
+        let idx = self
+            .values
+            .binary_search_by_key(&TypeId::of::<T>(), |value| value.id)
+            .ok()?;
+        self.values[idx].value.as_any().downcast_ref()
+    }
+
+    /// Returns type ids, type names and pointers to values of shared components.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (TypeId, &'static str, *const u8)> + '_ {
+        self.values.iter().map(|value| {
+            let ptr = value.value.as_any() as *const dyn Any as *const u8;
+            (value.id, value.name, ptr)
+        })
+    }
+
+    /// Returns copy of these values with value of type `T` replaced or added.
+    pub fn with<T>(&self, value: T) -> Self
+    where
+        T: SharedComponent,
+    {
+        let value = SharedValue {
+            id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+            value: Arc::new(value),
+        };
+
+        let mut values = self.values.clone();
+        match values.binary_search_by_key(&value.id, |value| value.id) {
+            Ok(idx) => values[idx] = value,
+            Err(idx) => values.insert(idx, value),
+        }
+        SharedValues { values }
+    }
+
+    /// Returns copy of these values without value of type `T`.
+    pub fn without<T>(&self) -> Self
+    where
+        T: 'static,
+    {
+        let mut values = self.values.clone();
+        values.retain(|value| value.id != TypeId::of::<T>());
+        SharedValues { values }
+    }
 }
 
 /// Collection of all entities with same set of components.
 /// Archetypes are typically managed by the `World` instance.
 ///
 /// This type is exposed for `Query` implementations.
+/// Components are accessed through [`Archetype::column`].
 #[derive(Debug)]
 pub struct Archetype {
     set: TypeIdSet,
     indices: Box<[usize]>,
-    entities: Vec<EntityId>,
+    entities: AllocVec<EntityId>,
     components: Box<[ComponentData]>,
+    config: StorageConfig,
+    shared: SharedValues,
 }
 
 impl Drop for Archetype {
     fn drop(&mut self) {
         for &idx in &*self.indices {
-            let component = &self.components[idx];
-            unsafe { (component.drop)(component.ptr.as_ptr(), self.entities.len()) }
+            let component = &mut self.components[idx];
+            unsafe {
+                (component.drop)(component.ptr.as_ptr(), self.entities.len());
+                component.free(&self.config.alloc, self.entities.capacity());
+            }
         }
     }
 }
 
 impl Archetype {
     /// Creates new archetype with the given set of components.
-    pub fn new<'a>(components: impl Iterator<Item = &'a ComponentInfo> + Clone) -> Self {
+    /// Components are stored according to `config`.
+    pub(crate) fn new<'a>(
+        components: impl Iterator<Item = &'a ComponentInfo> + Clone,
+        config: StorageConfig,
+    ) -> Self {
         let set = TypeIdSet::new(components.clone().map(|c| c.id));
 
         let mut component_data: Box<[_]> = (0..set.upper_bound())
@@ -360,12 +537,69 @@ impl Archetype {
             component_data[idx] = ComponentData::new(c);
         }
 
-        Archetype {
+        let mut archetype = Archetype {
             set,
             indices,
-            entities: Vec::new(),
+            entities: AllocVec::new(config.alloc.clone()),
             components: component_data,
+            config,
+            shared: SharedValues::default(),
+        };
+
+        if let Some(cap) = archetype.config.fixed_capacity {
+            archetype.grow_exact(cap);
         }
+
+        archetype
+    }
+
+    /// Sets values of shared components of the archetype.
+    #[inline]
+    pub(crate) fn with_shared(mut self, shared: SharedValues) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// Returns values of shared components of the archetype.
+    #[inline]
+    pub(crate) fn shared_values(&self) -> &SharedValues {
+        &self.shared
+    }
+
+    /// Returns value of shared component of type `T`
+    /// for all entities in this archetype.
+    #[inline]
+    pub fn shared<T>(&self) -> Option<&T>
+    where
+        T: 'static,
+    {
+        self.shared.get()
+    }
+
+    /// Returns configuration of component storage.
+    #[inline]
+    pub(crate) fn config(&self) -> &StorageConfig {
+        &self.config
+    }
+
+    /// Enables or disables keeping order of entities on removal.
+    #[inline]
+    pub(crate) fn set_deterministic(&mut self, deterministic: bool) {
+        self.config.deterministic = deterministic;
+    }
+
+    /// Returns `true` if archetype has fixed capacity
+    /// and cannot accept more entities.
+    #[inline]
+    pub(crate) fn is_full(&self) -> bool {
+        !self.has_space(1)
+    }
+
+    /// Returns `true` if archetype can accept `additional` more entities.
+    #[inline]
+    pub(crate) fn has_space(&self, additional: usize) -> bool {
+        self.config.fixed_capacity.is_none()
+            || self.entities.capacity() - self.entities.len() >= additional
     }
 
     /// Returns `true` if archetype contains compoment with specified id.
@@ -421,10 +655,20 @@ impl Archetype {
             .map(move |&idx| &self.components[idx].info)
     }
 
+    /// Replaces info of the component type with the same id.
+    /// Used to update optional glue functions of registered components.
+    #[inline]
+    pub(crate) fn update_info(&mut self, info: &ComponentInfo) {
+        if let Some(idx) = self.set.get(info.id) {
+            debug_assert_eq!(self.components[idx].layout, info.layout);
+            self.components[idx].info = *info;
+        }
+    }
+
     /// Spawns new entity in the archetype.
     ///
     /// Returns index of the newly created entity in the archetype.
-    pub fn spawn<B>(&mut self, entity: EntityId, bundle: B, epoch: u64) -> u32
+    pub(crate) fn spawn<B>(&mut self, entity: EntityId, bundle: B, epoch: u64) -> u32
     where
         B: DynamicBundle,
     {
@@ -444,14 +688,529 @@ impl Archetype {
         entity_idx as u32
     }
 
-    /// Despawns specified entity in the archetype.
+    /// Spawns new entity in the archetype with clones of components of another entity.
+    /// Id of the new entity is requested from `spawn` after all components are cloned,
+    /// so panicking `clone_one` leaves neither cloned components nor id behind.
     ///
-    /// Returns id of the entity that took the place of despawned.
-    #[inline]
-    pub fn despawn(&mut self, idx: u32) -> Option<u32> {
-        assert!(idx < self.entities.len() as u32);
+    /// Returns id of the new entity and its index in the archetype.
+    ///
+    /// # Safety
+    ///
+    /// `src_idx` must be in bounds of the archetype entities array.
+    /// All components of the archetype must have `clone_one` function.
+    pub(crate) unsafe fn clone_entity(
+        &mut self,
+        src_idx: u32,
+        spawn: impl FnOnce() -> EntityId,
+        epoch: u64,
+    ) -> (EntityId, u32) {
+        debug_assert!((src_idx as usize) < self.entities.len());
+        debug_assert!(self.entities.len() < MAX_IDX_USIZE);
+
+        let src_entity_idx = src_idx as usize;
+        let dst_entity_idx = self.entities.len();
+        let chunk_idx = chunk_idx(dst_entity_idx);
+
+        self.reserve(1);
+
+        /// Drops components cloned so far if cloning panics.
+        struct Guard<'a> {
+            archetype: &'a Archetype,
+            entity_idx: usize,
+            cloned: usize,
+        }
+
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                for &idx in &self.archetype.indices[..self.cloned] {
+                    let component = &self.archetype.components[idx];
+                    let size = component.layout.size();
+                    unsafe {
+                        (component.drop_one)(component.ptr.as_ptr().add(self.entity_idx * size));
+                    }
+                }
+            }
+        }
+
+        let mut guard = Guard {
+            archetype: self,
+            entity_idx: dst_entity_idx,
+            cloned: 0,
+        };
+
+        for &idx in self.indices.iter() {
+            let component = &self.components[idx];
+            let size = component.layout.size();
+
+            let clone_one = component
+                .clone_one
+                .unwrap_or_else(|| unreachable_unchecked());
+            clone_one(
+                component.ptr.as_ptr().add(src_entity_idx * size),
+                component.ptr.as_ptr().add(dst_entity_idx * size),
+            );
+            guard.cloned += 1;
+        }
+
+        mem::forget(guard);
+
+        for &idx in self.indices.iter() {
+            let component = &self.components[idx];
+
+            debug_assert!(*component.version.get() <= epoch);
+            *component.version.get() = epoch;
+
+            let chunk_version = &mut *component.chunk_versions.as_ptr().add(chunk_idx);
+            debug_assert!(*chunk_version <= epoch);
+            *chunk_version = epoch;
+
+            let entity_version = &mut *component.entity_versions.as_ptr().add(dst_entity_idx);
+            debug_assert_eq!(*entity_version, 0);
+            *entity_version = epoch;
+        }
 
-        unsafe { self.despawn_unchecked(idx) }
+        let entity = spawn();
+        self.entities.push(entity);
+        (entity, dst_entity_idx as u32)
+    }
+
+    /// Spawns new entity in the archetype with clones of components at specified pointers
+    /// and components from the bundle.
+    ///
+    /// Returns index of the newly created entity in the archetype.
+    ///
+    /// # Safety
+    ///
+    /// Pointers must reference valid components of types specified by infos.
+    /// All specified infos must have `clone_one` function.
+    /// Cloned and bundle components together must match components of this archetype exactly.
+    pub(crate) unsafe fn spawn_cloned<'a, B>(
+        &mut self,
+        entity: EntityId,
+        cloned: impl Iterator<Item = (&'a ComponentInfo, NonNull<u8>)>,
+        bundle: B,
+        epoch: u64,
+    ) -> u32
+    where
+        B: DynamicBundle,
+    {
+        debug_assert!(self.entities.len() < MAX_IDX_USIZE);
+
+        let entity_idx = self.entities.len();
+        let chunk_idx = chunk_idx(entity_idx);
+
+        self.reserve(1);
+
+        for (info, src) in cloned {
+            debug_assert!(!bundle.contains_id(info.id));
+
+            let component = &self.components[self.set.get(info.id).unwrap_unchecked()];
+            let size = component.layout.size();
+
+            let clone_one = info.clone_one.unwrap_or_else(|| unreachable_unchecked());
+            clone_one(src.as_ptr(), component.ptr.as_ptr().add(entity_idx * size));
+
+            debug_assert!(*component.version.get() <= epoch);
+            *component.version.get() = epoch;
+
+            let chunk_version = &mut *component.chunk_versions.as_ptr().add(chunk_idx);
+            debug_assert!(*chunk_version <= epoch);
+            *chunk_version = epoch;
+
+            let entity_version = &mut *component.entity_versions.as_ptr().add(entity_idx);
+            debug_assert_eq!(*entity_version, 0);
+            *entity_version = epoch;
+        }
+
+        self.write_bundle(entity_idx, bundle, epoch, |_| false);
+
+        self.entities.push(entity);
+        entity_idx as u32
+    }
+
+    /// Returns new archetype with clones of all entities and their components.
+    /// Entity and chunk versions are copied as well.
+    ///
+    /// # Safety
+    ///
+    /// All components of the archetype must have `clone_one` function.
+    pub(crate) unsafe fn clone_all(&self) -> Archetype {
+        let mut archetype =
+            Archetype::new(self.infos(), self.config.clone()).with_shared(self.shared.clone());
+        archetype.reserve(self.entities.len());
+
+        let len = self.entities.len();
+
+        for &idx in self.indices.iter() {
+            let src = &self.components[idx];
+            let dst = &archetype.components[idx];
+            debug_assert_eq!(src.id, dst.id);
+
+            let size = src.layout.size();
+            let clone_one = src.clone_one.unwrap_or_else(|| unreachable_unchecked());
+
+            for entity_idx in 0..len {
+                clone_one(
+                    src.ptr.as_ptr().add(entity_idx * size),
+                    dst.ptr.as_ptr().add(entity_idx * size),
+                );
+            }
+
+            *dst.version.get() = *src.version.get();
+            ptr::copy_nonoverlapping(
+                src.entity_versions.as_ptr(),
+                dst.entity_versions.as_ptr(),
+                len,
+            );
+            ptr::copy_nonoverlapping(
+                src.chunk_versions.as_ptr(),
+                dst.chunk_versions.as_ptr(),
+                chunks_count(len),
+            );
+        }
+
+        archetype.entities.extend_from_slice(&self.entities);
+        archetype
+    }
+
+    /// Moves entity with all its components into archetype of another `World`
+    /// with the same set of components, where it gets new id.
+    /// Moved components are marked as modified at specified epoch of the destination.
+    ///
+    /// Returns index of the entity in destination archetype
+    /// and id of the entity that took the place of moved one.
+    ///
+    /// # Safety
+    ///
+    /// `src_idx` must be in bounds of this archetype.
+    /// `dst` archetype must contain exactly same component types as this archetype.
+    pub(crate) unsafe fn transfer(
+        &mut self,
+        dst: &mut Archetype,
+        src_idx: u32,
+        entity: EntityId,
+        epoch: u64,
+    ) -> (u32, Option<u32>) {
+        debug_assert!(dst.matches(self.ids()));
+
+        let src_entity_idx = src_idx as usize;
+        debug_assert!(src_entity_idx < self.entities.len());
+
+        let dst_entity_idx = dst.entities.len();
+        debug_assert!(dst_entity_idx < MAX_IDX_USIZE);
+
+        dst.reserve(1);
+        debug_assert_ne!(dst.entities.len(), dst.entities.capacity());
+
+        let dst_chunk_idx = chunk_idx(dst_entity_idx);
+        let last_entity_idx = self.entities.len() - 1;
+
+        for &src_type_idx in self.indices.iter() {
+            let src_component = &self.components[src_type_idx];
+            let size = src_component.layout.size();
+            let src_ptr = src_component.ptr.as_ptr().add(src_entity_idx * size);
+
+            let dst_component = &dst.components[dst.set.get(src_component.id).unwrap_unchecked()];
+
+            debug_assert!(*dst_component.version.get() <= epoch);
+            *dst_component.version.get() = epoch;
+
+            let dst_chunk_version = &mut *dst_component.chunk_versions.as_ptr().add(dst_chunk_idx);
+            debug_assert!(*dst_chunk_version <= epoch);
+            *dst_chunk_version = epoch;
+
+            let dst_entity_version =
+                &mut *dst_component.entity_versions.as_ptr().add(dst_entity_idx);
+            debug_assert_eq!(*dst_entity_version, 0);
+            *dst_entity_version = epoch;
+
+            let dst_ptr = dst_component.ptr.as_ptr().add(dst_entity_idx * size);
+            ptr::copy_nonoverlapping(src_ptr, dst_ptr, size);
+
+            if src_entity_idx != last_entity_idx {
+                let src_chunk_idx = chunk_idx(src_entity_idx);
+
+                let last_epoch = *src_component.entity_versions.as_ptr().add(last_entity_idx);
+
+                let src_chunk_version =
+                    &mut *src_component.chunk_versions.as_ptr().add(src_chunk_idx);
+
+                let src_entity_version =
+                    &mut *src_component.entity_versions.as_ptr().add(src_entity_idx);
+
+                if *src_chunk_version < last_epoch {
+                    *src_chunk_version = last_epoch;
+                }
+
+                *src_entity_version = last_epoch;
+
+                let last_ptr = src_component.ptr.as_ptr().add(last_entity_idx * size);
+                ptr::copy_nonoverlapping(last_ptr, src_ptr, size);
+            }
+
+            #[cfg(debug_assertions)]
+            {
+                *src_component.entity_versions.as_ptr().add(last_entity_idx) = 0;
+            }
+        }
+
+        self.entities.swap_remove(src_entity_idx);
+        dst.entities.push(entity);
+
+        if src_entity_idx != self.entities.len() {
+            (
+                dst_entity_idx as u32,
+                Some(self.entities[src_entity_idx].idx),
+            )
+        } else {
+            (dst_entity_idx as u32, None)
+        }
+    }
+
+    /// Moves all entities with their components into archetype of another `World`
+    /// with the same set of components, column by column.
+    /// Entities get new ids from the iterator in order.
+    /// Moved components are marked as modified at specified epoch of the destination.
+    ///
+    /// Returns index of the first moved entity in destination archetype.
+    ///
+    /// # Safety
+    ///
+    /// `dst` archetype must contain exactly same component types as this archetype.
+    /// `entities` must yield exactly as many ids as there are entities in this archetype.
+    pub(crate) unsafe fn transfer_all(
+        &mut self,
+        dst: &mut Archetype,
+        entities: impl Iterator<Item = EntityId>,
+        epoch: u64,
+    ) -> u32 {
+        debug_assert!(dst.matches(self.ids()));
+
+        let len = self.entities.len();
+        let dst_start = dst.entities.len();
+        debug_assert!(dst_start + len <= MAX_IDX_USIZE);
+
+        if len == 0 {
+            return dst_start as u32;
+        }
+
+        dst.reserve(len);
+
+        for &src_type_idx in self.indices.iter() {
+            let src_component = &self.components[src_type_idx];
+            let size = src_component.layout.size();
+
+            let dst_component = &dst.components[dst.set.get(src_component.id).unwrap_unchecked()];
+
+            ptr::copy_nonoverlapping(
+                src_component.ptr.as_ptr(),
+                dst_component.ptr.as_ptr().add(dst_start * size),
+                len * size,
+            );
+
+            debug_assert!(*dst_component.version.get() <= epoch);
+            *dst_component.version.get() = epoch;
+
+            for chunk_idx in chunk_idx(dst_start)..=chunk_idx(dst_start + len - 1) {
+                let chunk_version = &mut *dst_component.chunk_versions.as_ptr().add(chunk_idx);
+                debug_assert!(*chunk_version <= epoch);
+                *chunk_version = epoch;
+            }
+
+            for entity_idx in dst_start..dst_start + len {
+                let entity_version = &mut *dst_component.entity_versions.as_ptr().add(entity_idx);
+                debug_assert_eq!(*entity_version, 0);
+                *entity_version = epoch;
+            }
+
+            #[cfg(debug_assertions)]
+            {
+                ptr::write_bytes(src_component.entity_versions.as_ptr(), 0, len);
+            }
+        }
+
+        // Components are moved, so entities are forgotten without dropping.
+        self.entities.clear();
+        dst.entities.extend(entities);
+        debug_assert_eq!(dst.entities.len(), dst_start + len);
+
+        dst_start as u32
+    }
+
+    /// Rewrites entity ids in components of entities in specified range
+    /// that have `map_entities` function.
+    /// Rewritten components are marked as modified at specified epoch.
+    pub(crate) fn map_entities(&mut self, range: Range<usize>, map: &EntityMap, epoch: u64) {
+        debug_assert!(range.end <= self.entities.len());
+
+        if range.start == range.end {
+            return;
+        }
+
+        for &idx in self.indices.iter() {
+            let component = &self.components[idx];
+            let map_entities = match component.map_entities {
+                None => continue,
+                Some(map_entities) => map_entities,
+            };
+            let size = component.layout.size();
+
+            unsafe {
+                debug_assert!(*component.version.get() <= epoch);
+                *component.version.get() = epoch;
+
+                for chunk_idx in chunk_idx(range.start)..=chunk_idx(range.end - 1) {
+                    let chunk_version = &mut *component.chunk_versions.as_ptr().add(chunk_idx);
+                    debug_assert!(*chunk_version <= epoch);
+                    *chunk_version = epoch;
+                }
+
+                for entity_idx in range.clone() {
+                    map_entities(component.ptr.as_ptr().add(entity_idx * size), map);
+
+                    let entity_version = &mut *component.entity_versions.as_ptr().add(entity_idx);
+                    debug_assert!(*entity_version <= epoch);
+                    *entity_version = epoch;
+                }
+            }
+        }
+    }
+
+    /// Restores order of entities after entity at `idx` was swap-removed,
+    /// moving entity that took its place back to the end.
+    ///
+    /// Returns `true` if any entity was moved.
+    pub(crate) fn restore_order(&mut self, idx: u32) -> bool {
+        let idx = idx as usize;
+        let len = self.entities.len();
+        if idx + 1 >= len {
+            return false;
+        }
+
+        for &type_idx in self.indices.iter() {
+            let component = &self.components[type_idx];
+            let size = component.layout.size();
+
+            unsafe {
+                let bytes = core::slice::from_raw_parts_mut(
+                    component
+                        .ptr
+                        .as_ptr()
+                        .add(idx * size)
+                        .cast::<MaybeUninit<u8>>(),
+                    (len - idx) * size,
+                );
+                bytes.rotate_left(size);
+
+                let versions = core::slice::from_raw_parts_mut(
+                    component.entity_versions.as_ptr().add(idx),
+                    len - idx,
+                );
+                versions.rotate_left(1);
+
+                for (offset, &version) in versions.iter().enumerate() {
+                    let chunk_version = &mut *component
+                        .chunk_versions
+                        .as_ptr()
+                        .add(chunk_idx(idx + offset));
+
+                    if *chunk_version < version {
+                        *chunk_version = version;
+                    }
+                }
+            }
+        }
+
+        self.entities[idx..].rotate_left(1);
+        true
+    }
+
+    /// Sorts entities in the archetype by key extracted from component `T`.
+    /// Sort is stable. Archetype must contain component `T`.
+    ///
+    /// Returns `true` if entities were reordered.
+    pub(crate) fn sort_by_key<T, K>(&mut self, key_fn: &mut impl FnMut(&T) -> K) -> bool
+    where
+        T: Component,
+        K: Ord,
+    {
+        let idx = self.set.get(TypeId::of::<T>()).unwrap();
+        let ptr = self.components[idx].ptr.cast::<T>();
+
+        let keys: Vec<K> = (0..self.entities.len())
+            .map(|entity_idx| key_fn(unsafe { &*ptr.as_ptr().add(entity_idx) }))
+            .collect();
+
+        let mut order: Vec<usize> = (0..self.entities.len()).collect();
+        order.sort_by(|&lhs, &rhs| keys[lhs].cmp(&keys[rhs]));
+
+        if order.iter().enumerate().all(|(idx, &src)| idx == src) {
+            return false;
+        }
+
+        unsafe { self.permute(&order) };
+        true
+    }
+
+    /// Reorders entities in the archetype.
+    /// Entity at index `order[i]` is moved to index `i`.
+    /// Chunk versions are recalculated from entity versions.
+    ///
+    /// # Safety
+    ///
+    /// `order` must be a permutation of indices of entities in the archetype.
+    unsafe fn permute(&mut self, order: &[usize]) {
+        let len = self.entities.len();
+        debug_assert_eq!(order.len(), len);
+
+        let mut bytes = Vec::new();
+        let mut versions = Vec::with_capacity(len);
+
+        for &idx in self.indices.iter() {
+            let component = &self.components[idx];
+            let size = component.layout.size();
+
+            bytes.clear();
+            bytes.reserve(len * size);
+            ptr::copy_nonoverlapping(component.ptr.as_ptr(), bytes.as_mut_ptr(), len * size);
+
+            versions.clear();
+            versions.extend_from_slice(core::slice::from_raw_parts(
+                component.entity_versions.as_ptr(),
+                len,
+            ));
+
+            for (dst_idx, &src_idx) in order.iter().enumerate() {
+                ptr::copy_nonoverlapping(
+                    bytes.as_ptr().add(src_idx * size),
+                    component.ptr.as_ptr().add(dst_idx * size),
+                    size,
+                );
+
+                let version = versions[src_idx];
+                *component.entity_versions.as_ptr().add(dst_idx) = version;
+            }
+
+            for chunk_idx in 0..chunks_count(len) {
+                *component.chunk_versions.as_ptr().add(chunk_idx) = 0;
+            }
+
+            for entity_idx in 0..len {
+                let version = *component.entity_versions.as_ptr().add(entity_idx);
+                let chunk_version =
+                    &mut *component.chunk_versions.as_ptr().add(chunk_idx(entity_idx));
+
+                if *chunk_version < version {
+                    *chunk_version = version;
+                }
+            }
+        }
+
+        // Entities list capacity must stay in sync with component columns.
+        let entities = self.entities.clone();
+        for (dst_idx, &src_idx) in order.iter().enumerate() {
+            self.entities[dst_idx] = entities[src_idx];
+        }
     }
 
     /// Despawns specified entity in the archetype.
@@ -461,7 +1220,7 @@ impl Archetype {
     /// # Safety
     ///
     /// idx must be in bounds of the archetype entities array.
-    pub unsafe fn despawn_unchecked(&mut self, idx: u32) -> Option<u32> {
+    pub(crate) unsafe fn despawn_unchecked(&mut self, idx: u32) -> Option<u32> {
         let entity_idx = idx as usize;
         debug_assert!(entity_idx < self.entities.len());
 
@@ -512,7 +1271,7 @@ impl Archetype {
     /// # Safety
     ///
     /// Bundle must not contain components that are absent in this archetype.
-    pub unsafe fn set_bundle<B>(&mut self, idx: u32, bundle: B, epoch: u64)
+    pub(crate) unsafe fn set_bundle<B>(&mut self, idx: u32, bundle: B, epoch: u64)
     where
         B: DynamicBundle,
     {
@@ -528,7 +1287,7 @@ impl Archetype {
     /// # Safety
     ///
     /// Archetype must contain that component type.
-    pub unsafe fn set<T>(&mut self, idx: u32, value: T, epoch: u64)
+    pub(crate) unsafe fn set<T>(&mut self, idx: u32, value: T, epoch: u64)
     where
         T: Component,
     {
@@ -547,7 +1306,7 @@ impl Archetype {
     /// `src_idx` must be in bounds of this archetype.
     /// This archetype must not contain at least one component type from the bundle.
     /// `dst` archetype must contain all component types from this archetype and the bundle.
-    pub unsafe fn insert_bundle<B>(
+    pub(crate) unsafe fn insert_bundle<B>(
         &mut self,
         dst: &mut Archetype,
         src_idx: u32,
@@ -654,7 +1413,11 @@ impl Archetype {
     /// `src_idx` must be in bounds of this archetype.
     /// This archetype must contain specified type.
     /// `dst` archetype must contain all component types from this archetype except specified type.
-    pub unsafe fn remove<T>(&mut self, dst: &mut Archetype, src_idx: u32) -> (u32, Option<u32>, T)
+    pub(crate) unsafe fn remove<T>(
+        &mut self,
+        dst: &mut Archetype,
+        src_idx: u32,
+    ) -> (u32, Option<u32>, T)
     where
         T: Component,
     {
@@ -703,7 +1466,11 @@ impl Archetype {
     ///
     /// `src_idx` must be in bounds of this archetype.
     /// `dst` archetype must contain all component types from this archetype except types from bundle.
-    pub unsafe fn drop_bundle(&mut self, dst: &mut Archetype, src_idx: u32) -> (u32, Option<u32>) {
+    pub(crate) unsafe fn drop_bundle(
+        &mut self,
+        dst: &mut Archetype,
+        src_idx: u32,
+    ) -> (u32, Option<u32>) {
         debug_assert!(dst.ids().all(|id| self.set.get(id).is_some()));
 
         let src_entity_idx = src_idx as usize;
@@ -732,11 +1499,282 @@ impl Archetype {
         }
     }
 
+    /// Despawns entities at specified indices.
+    /// Calls `moved` with id and new index of each entity that was moved
+    /// to fill vacated slots.
+    ///
+    /// # Safety
+    ///
+    /// `indices` must be sorted, unique and in bounds of the archetype entities array.
+    pub(crate) unsafe fn despawn_batch(&mut self, indices: &[u32], moved: impl FnMut(u32, u32)) {
+        for &type_idx in self.indices.iter() {
+            let component = &self.components[type_idx];
+            let size = component.layout.size();
+
+            for &idx in indices {
+                (component.drop_one)(component.ptr.as_ptr().add(idx as usize * size));
+            }
+        }
+
+        self.compact(indices, moved);
+    }
+
+    /// Moves entities at specified indices with their components into `dst` archetype,
+    /// appending them in order of `indices`.
+    /// Components that are absent in `dst` are dropped.
+    /// Calls `moved` with id and new index of each entity that was moved
+    /// to fill vacated slots of this archetype.
+    ///
+    /// Returns index of the first moved entity in `dst` archetype.
+    ///
+    /// # Safety
+    ///
+    /// `indices` must be sorted, unique and in bounds of the archetype entities array.
+    /// `dst` archetype must contain only component types from this archetype.
+    pub(crate) unsafe fn remove_batch(
+        &mut self,
+        dst: &mut Archetype,
+        indices: &[u32],
+        moved: impl FnMut(u32, u32),
+    ) -> u32 {
+        debug_assert!(dst.ids().all(|id| self.set.get(id).is_some()));
+
+        let dst_start = self.relocate_batch(dst, indices, |info, ptr| (info.drop_one)(ptr));
+        self.compact(indices, moved);
+        dst_start
+    }
+
+    /// Moves entities at specified indices with their components into `dst` archetype,
+    /// appending them in order of `values` and adding component from `values` to each.
+    /// Calls `moved` with id and new index of each entity that was moved
+    /// to fill vacated slots of this archetype.
+    ///
+    /// Returns index of the first moved entity in `dst` archetype.
+    ///
+    /// # Safety
+    ///
+    /// Indices must be sorted, unique and in bounds of the archetype entities array.
+    /// This archetype must not contain specified type.
+    /// `dst` archetype must contain all component types from this archetype and specified type.
+    pub(crate) unsafe fn insert_batch<T>(
+        &mut self,
+        dst: &mut Archetype,
+        values: Vec<(u32, T)>,
+        epoch: u64,
+        moved: impl FnMut(u32, u32),
+    ) -> u32
+    where
+        T: Component,
+    {
+        debug_assert!(self.ids().all(|id| dst.set.get(id).is_some()));
+        debug_assert!(self.set.get(TypeId::of::<T>()).is_none());
+        debug_assert!(dst.set.get(TypeId::of::<T>()).is_some());
+        debug_assert_eq!(self.set.len() + 1, dst.set.len());
+
+        let indices: Vec<u32> = values.iter().map(|&(idx, _)| idx).collect();
+
+        let dst_start = self.relocate_batch(dst, &indices, |_, _| unreachable_unchecked());
+        for (offset, (_, value)) in values.into_iter().enumerate() {
+            dst.write_one(dst_start as usize + offset, value, epoch, false);
+        }
+
+        self.compact(&indices, moved);
+        dst_start
+    }
+
+    /// Copies components of entities at specified indices to the end of `dst` archetype
+    /// and pushes their ids to it.
+    /// Components that are absent in `dst` are passed to `missing`.
+    /// Slots in this archetype are left to be vacated with `compact`.
+    ///
+    /// Returns index of the first copied entity in `dst` archetype.
+    unsafe fn relocate_batch<F>(
+        &mut self,
+        dst: &mut Archetype,
+        indices: &[u32],
+        mut missing: F,
+    ) -> u32
+    where
+        F: FnMut(&ComponentInfo, *mut u8),
+    {
+        let dst_start = dst.entities.len();
+        debug_assert!(dst_start + indices.len() <= MAX_IDX_USIZE);
+
+        dst.reserve(indices.len());
+
+        for &src_type_idx in self.indices.iter() {
+            let src_component = &self.components[src_type_idx];
+            let size = src_component.layout.size();
+
+            match dst.set.get(src_component.id) {
+                Some(dst_type_idx) => {
+                    let dst_component = &dst.components[dst_type_idx];
+
+                    for (offset, &idx) in indices.iter().enumerate() {
+                        let src_idx = idx as usize;
+                        let dst_idx = dst_start + offset;
+
+                        let epoch = *src_component.entity_versions.as_ptr().add(src_idx);
+
+                        if *dst_component.version.get() < epoch {
+                            *dst_component.version.get() = epoch;
+                        }
+
+                        let dst_chunk_version = &mut *dst_component
+                            .chunk_versions
+                            .as_ptr()
+                            .add(chunk_idx(dst_idx));
+                        if *dst_chunk_version < epoch {
+                            *dst_chunk_version = epoch;
+                        }
+
+                        let dst_entity_version =
+                            &mut *dst_component.entity_versions.as_ptr().add(dst_idx);
+                        debug_assert_eq!(*dst_entity_version, 0);
+                        *dst_entity_version = epoch;
+
+                        ptr::copy_nonoverlapping(
+                            src_component.ptr.as_ptr().add(src_idx * size),
+                            dst_component.ptr.as_ptr().add(dst_idx * size),
+                            size,
+                        );
+                    }
+                }
+                None => {
+                    for &idx in indices {
+                        missing(
+                            src_component,
+                            src_component.ptr.as_ptr().add(idx as usize * size),
+                        );
+                    }
+                }
+            }
+        }
+
+        dst.entities
+            .extend(indices.iter().map(|&idx| self.entities[idx as usize]));
+
+        dst_start as u32
+    }
+
+    /// Removes slots at specified indices, which components were dropped or moved out.
+    ///
+    /// Remaining entities keep their order in deterministic mode.
+    /// Otherwise vacated slots are filled with entities from the end.
+    /// Calls `moved` with id and new index of each entity that was moved.
+    unsafe fn compact(&mut self, indices: &[u32], mut moved: impl FnMut(u32, u32)) {
+        let len = self.entities.len();
+        let new_len = len - indices.len();
+
+        if self.config.deterministic {
+            let mut removed = indices.iter().peekable();
+            let start = match removed.peek() {
+                None => return,
+                Some(&&idx) => idx as usize,
+            };
+
+            let mut to = start;
+            for from in start..len {
+                if removed.peek() == Some(&&(from as u32)) {
+                    removed.next();
+                    continue;
+                }
+                self.move_entity(from, to);
+                moved(self.entities[to].idx, to as u32);
+                to += 1;
+            }
+        } else {
+            let mut removed = indices.iter().rev().peekable();
+            let mut from = len;
+
+            for &to in indices.iter().take_while(|&&idx| (idx as usize) < new_len) {
+                loop {
+                    from -= 1;
+                    if removed.peek() == Some(&&(from as u32)) {
+                        removed.next();
+                        continue;
+                    }
+                    break;
+                }
+
+                debug_assert!(from >= new_len);
+                let to = to as usize;
+                self.move_entity(from, to);
+                moved(self.entities[to].idx, to as u32);
+            }
+        }
+
+        #[cfg(debug_assertions)]
+        for &type_idx in self.indices.iter() {
+            let component = &self.components[type_idx];
+            for idx in new_len..len {
+                *component.entity_versions.as_ptr().add(idx) = 0;
+            }
+        }
+
+        self.entities.truncate(new_len);
+    }
+
+    /// Moves entity with its components from one slot to another vacated slot.
+    unsafe fn move_entity(&mut self, from: usize, to: usize) {
+        for &type_idx in self.indices.iter() {
+            let component = &self.components[type_idx];
+            let size = component.layout.size();
+
+            let version = *component.entity_versions.as_ptr().add(from);
+            *component.entity_versions.as_ptr().add(to) = version;
+
+            let chunk_version = &mut *component.chunk_versions.as_ptr().add(chunk_idx(to));
+            if *chunk_version < version {
+                *chunk_version = version;
+            }
+
+            ptr::copy_nonoverlapping(
+                component.ptr.as_ptr().add(from * size),
+                component.ptr.as_ptr().add(to * size),
+                size,
+            );
+        }
+
+        self.entities[to] = self.entities[from];
+    }
+
+    /// Returns pointer to the component of specified type
+    /// of the entity at specified index.
+    ///
+    /// Returns `None` if archetype does not contain component with specified id.
     #[inline]
-    pub(crate) fn entities(&self) -> &[EntityId] {
+    pub(crate) fn component_ptr(&self, idx: u32, type_id: TypeId) -> Option<NonNull<u8>> {
+        debug_assert!((idx as usize) < self.entities.len());
+
+        let component = &self.components[self.set.get(type_id)?];
+        let size = component.layout.size();
+        Some(unsafe { NonNull::new_unchecked(component.ptr.as_ptr().add(idx as usize * size)) })
+    }
+
+    /// Returns ids of entities in this archetype.
+    /// Entity at index `idx` owns components at index `idx` in every column.
+    #[inline]
+    pub fn entities(&self) -> &[EntityId] {
         &self.entities
     }
 
+    /// Returns column of components of type `T`.
+    ///
+    /// Returns `None` if archetype does not contain component `T`.
+    #[inline]
+    pub fn column<T>(&self) -> Option<Column<'_, T>>
+    where
+        T: Component,
+    {
+        let idx = self.id_index(TypeId::of::<T>())?;
+        Some(Column {
+            data: &self.components[idx],
+            len: self.entities.len(),
+            marker: PhantomData,
+        })
+    }
+
     /// Returns iterator over component type infos.
     #[inline]
     pub(crate) unsafe fn data(&self, idx: usize) -> &ComponentData {
@@ -744,22 +1782,59 @@ impl Archetype {
         &self.components.get_unchecked(idx)
     }
 
+    /// Returns number of entities in this archetype.
     #[inline]
-    pub(crate) fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         self.entities.len()
     }
 
+    /// Returns `true` if archetype has no entities.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Reserves space for at least `additional` entities.
+    ///
+    /// # Panics
+    ///
+    /// Panics if archetype has fixed capacity
+    /// and it is not enough for `additional` entities.
     #[inline]
     pub(crate) fn reserve(&mut self, additional: usize) {
+        if self.config.fixed_capacity.is_some() {
+            assert!(
+                self.entities.capacity() - self.entities.len() >= additional,
+                "Archetype capacity exceeded"
+            );
+            return;
+        }
+
         let cap = self.entities.capacity();
         self.entities.reserve(additional);
+        self.grow_columns(cap);
+    }
 
-        if cap != self.entities.capacity() {
+    /// Grows storage to fit exactly `additional` more entities.
+    fn grow_exact(&mut self, additional: usize) {
+        let cap = self.entities.capacity();
+        self.entities.reserve_exact(additional);
+        self.grow_columns(cap);
+    }
+
+    /// Grows component columns to the capacity of entities list.
+    fn grow_columns(&mut self, old_cap: usize) {
+        if old_cap != self.entities.capacity() {
             // Capacity changed
             for &idx in &*self.indices {
                 let component = &mut self.components[idx];
                 unsafe {
-                    component.grow(self.entities.len(), cap, self.entities.capacity());
+                    component.grow(
+                        &self.config.alloc,
+                        self.entities.len(),
+                        old_cap,
+                        self.entities.capacity(),
+                    );
                 }
             }
         }
@@ -903,23 +1978,111 @@ impl Archetype {
     }
 }
 
-pub(crate) const CHUNK_LEN_USIZE: usize = 0x100;
+/// Number of entities in one chunk of an archetype.
+/// Versions are tracked per chunk in addition to per entity,
+/// so that unmodified chunks can be skipped at once.
+pub const CHUNK_LEN: usize = 0x100;
 
+/// Returns index of the chunk that contains entity at index `idx`.
 #[inline]
-pub(crate) const fn chunk_idx(idx: usize) -> usize {
+pub const fn chunk_idx(idx: usize) -> usize {
     idx >> 8
 }
 
 #[inline]
 pub(crate) const fn chunks_count(entities: usize) -> usize {
-    entities + (CHUNK_LEN_USIZE - 1) / CHUNK_LEN_USIZE
+    chunk_idx(entities + CHUNK_LEN - 1)
 }
 
+/// Returns index of the chunk if entity at index `idx`
+/// is the first one in that chunk.
 #[inline]
-pub(crate) const fn first_of_chunk(idx: usize) -> Option<usize> {
-    if idx % CHUNK_LEN_USIZE == 0 {
+pub const fn first_of_chunk(idx: usize) -> Option<usize> {
+    if idx % CHUNK_LEN == 0 {
         Some(chunk_idx(idx))
     } else {
         None
     }
 }
+
+/// Typed view of a column of components in an [`Archetype`].
+/// Returned by [`Archetype::column`].
+///
+/// Column stores components of all entities of the archetype
+/// in order of [`Archetype::entities`] along with their versions.
+/// Component is modified after some epoch if its version is greater than that epoch.
+/// Versions of chunks allow skipping [`CHUNK_LEN`] unmodified components at once.
+///
+/// Pointers are valid while archetype is borrowed.
+/// Mutable access to components is allowed only to queries
+/// that report it with [`Query::access`].
+///
+/// [`Query::access`]: `edict::query::Query::access`
+pub struct Column<'a, T> {
+    data: &'a ComponentData,
+    len: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Column<'_, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Column<'_, T> {}
+
+impl<T> fmt::Debug for Column<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Column")
+            .field("component", &self.data.info.debug_name)
+            .field("len", &self.len)
+            .field("version", &self.version())
+            .finish()
+    }
+}
+
+impl<T> Column<'_, T> {
+    /// Returns number of components in the column.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if column has no components.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns pointer to the first component in the column.
+    /// Component of entity at index `idx` is located at `ptr().add(idx)`.
+    #[inline]
+    pub fn ptr(&self) -> NonNull<T> {
+        self.data.ptr.cast()
+    }
+
+    /// Returns pointer to versions of components in the column.
+    /// Version of component at index `idx` is located at `entity_versions().add(idx)`.
+    #[inline]
+    pub fn entity_versions(&self) -> NonNull<u64> {
+        self.data.entity_versions
+    }
+
+    /// Returns pointer to versions of chunks in the column.
+    /// Version of chunk is not less than versions of its components.
+    /// Version of chunk that contains component at index `idx`
+    /// is located at `chunk_versions().add(chunk_idx(idx))`.
+    #[inline]
+    pub fn chunk_versions(&self) -> NonNull<u64> {
+        self.data.chunk_versions
+    }
+
+    /// Returns version of the whole column.
+    /// It is not less than versions of all chunks.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        unsafe { *self.data.version.get() }
+    }
+}
@@ -278,15 +278,21 @@ impl EntityBuilder {
             };
 
             unsafe {
-                let ptr = alloc::alloc::alloc(layout);
-                let mut ptr = NonNull::new(ptr).unwrap();
+                let mut ptr = if layout.size() == 0 {
+                    NonNull::new_unchecked(layout.align() as *mut u8)
+                } else {
+                    NonNull::new(alloc::alloc::alloc(layout)).unwrap()
+                };
 
                 ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len);
 
                 swap(&mut self.ptr, &mut ptr);
                 swap(&mut self.layout, &mut layout);
 
-                alloc::alloc::dealloc(ptr.as_ptr(), layout);
+                // Initial and zero-sized buffers are not allocated.
+                if layout.size() != 0 {
+                    alloc::alloc::dealloc(ptr.as_ptr(), layout);
+                }
             }
         }
 
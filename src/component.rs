@@ -3,11 +3,18 @@
 use core::{
     alloc::Layout,
     any::{type_name, TypeId},
+    cmp::Ordering,
+    fmt,
     ptr::{self, drop_in_place, slice_from_raw_parts_mut},
 };
 
+use crate::entity::EntityMap;
+
 /// Trait that is implemented for all types that can act as a component.
 /// Currently is implemented for all `'static` types.
+///
+/// Components are not required to be `Send` or `Sync`,
+/// as `World` that holds them is neither `Send` nor `Sync`.
 pub trait Component: 'static {
     /// Returns [`ComponentInfo`] for this component type.
     fn info() -> ComponentInfo;
@@ -42,6 +49,36 @@ pub struct ComponentInfo {
 
     /// Function that replaces component at target location.
     pub set_one: unsafe fn(*mut u8, *mut u8),
+
+    /// Function that formats component using its `fmt::Debug` implementation.
+    /// Set only for component types registered with [`World::register_debug`].
+    ///
+    /// [`World::register_debug`]: `edict::world::World::register_debug`
+    pub debug_fmt: Option<unsafe fn(*const u8, &mut fmt::Formatter<'_>) -> fmt::Result>,
+
+    /// Function that writes clone of the component to uninitialized target location.
+    /// Set only for component types registered with [`World::register_clone`].
+    ///
+    /// [`World::register_clone`]: `edict::world::World::register_clone`
+    pub clone_one: Option<unsafe fn(*const u8, *mut u8)>,
+
+    /// Function that rewrites entity ids in the component using its `MapEntities` implementation.
+    /// Set only for component types registered with [`World::register_map_entities`].
+    ///
+    /// [`World::register_map_entities`]: `edict::world::World::register_map_entities`
+    pub map_entities: Option<unsafe fn(*mut u8, &EntityMap)>,
+
+    /// Stable id of the component type that orders archetypes in deterministic mode.
+    /// Set only for component types registered with [`World::register_canonical_id`].
+    ///
+    /// [`World::register_canonical_id`]: `edict::world::World::register_canonical_id`
+    pub canonical_id: Option<u64>,
+
+    /// Function that compares two components using their `Ord` implementation.
+    /// Set only for component types registered with [`World::register_ord`].
+    ///
+    /// [`World::register_ord`]: `edict::world::World::register_ord`
+    pub cmp: Option<unsafe fn(*const u8, *const u8) -> Ordering>,
 }
 
 impl ComponentInfo {
@@ -59,6 +96,21 @@ impl ComponentInfo {
             },
             drop_one: |ptr| unsafe { drop_in_place::<T>(ptr.cast()) },
             set_one: |src, dst| unsafe { *(dst as *mut T) = ptr::read(src as *mut T) },
+            debug_fmt: None,
+            clone_one: None,
+            map_entities: None,
+            canonical_id: None,
+            cmp: None,
         }
     }
 }
+
+/// Trait for component types which value may be shared by many entities.
+///
+/// Value of shared component is stored once per archetype
+/// and is part of the archetype key.
+/// Entities with equal shared values are stored together.
+/// Implemented for all `'static` types that implement `PartialEq`.
+pub trait SharedComponent: Component + PartialEq {}
+
+impl<T> SharedComponent for T where T: Component + PartialEq {}
@@ -0,0 +1,271 @@
+//! This module implements [`Registry`] of replicated component types
+//! used by [`World::write_delta`] and [`World::apply_delta`].
+//!
+//! Delta is a sequence of records in compact binary format:
+//! despawns and component removals that happened since the `Tracks`
+//! followed by values of inserted and modified components.
+//! Entities are identified with [`EntityId::bits`] of the writing `World`.
+//! Reading `World` maps them to its own entities with [`EntityMap`].
+//!
+//! [`World::write_delta`]: `edict::world::World::write_delta`
+//! [`World::apply_delta`]: `edict::world::World::apply_delta`
+//! [`EntityId::bits`]: `edict::entity::EntityId::bits`
+//! [`EntityMap`]: `edict::entity::EntityMap`
+
+use core::{
+    any::{type_name, TypeId},
+    convert::TryInto,
+    fmt,
+};
+
+use alloc::{boxed::Box, vec::Vec};
+use hashbrown::HashMap;
+
+use crate::{
+    allocator::{Allocator, Global},
+    component::Component,
+    entity::EntityId,
+    hash::NoOpHasherBuilder,
+    world::World,
+};
+
+pub(crate) const DESPAWN: u8 = 0;
+pub(crate) const REMOVE: u8 = 1;
+pub(crate) const SET: u8 = 2;
+
+type EncodeFn = dyn Fn(*const u8, &mut Vec<u8>) + Send + Sync;
+type ApplyFn<A> = dyn Fn(&mut World<A>, &EntityId, &[u8]) -> Result<(), DeltaError> + Send + Sync;
+
+struct Replicated<A: Allocator> {
+    id: TypeId,
+    debug_name: &'static str,
+    encode: Box<EncodeFn>,
+    apply: Box<ApplyFn<A>>,
+    remove: fn(&mut World<A>, &EntityId),
+}
+
+/// Collection of component types that are replicated with deltas,
+/// together with functions to encode and decode their values.
+///
+/// Component types are identified in deltas by order of registration,
+/// so writing and reading sides must register same types in the same order.
+///
+/// Registry is used with `World` that uses allocator `A`.
+pub struct Registry<A: Allocator = Global> {
+    replicated: Vec<Replicated<A>>,
+    ids: HashMap<TypeId, u32, NoOpHasherBuilder>,
+}
+
+impl<A> fmt::Debug for Registry<A>
+where
+    A: Allocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.replicated.iter().map(|r| r.debug_name))
+            .finish()
+    }
+}
+
+impl<A> Default for Registry<A>
+where
+    A: Allocator,
+{
+    #[inline]
+    fn default() -> Self {
+        Registry::new()
+    }
+}
+
+impl<A> Registry<A>
+where
+    A: Allocator,
+{
+    /// Returns new empty registry.
+    #[inline]
+    pub fn new() -> Self {
+        Registry {
+            replicated: Vec::new(),
+            ids: HashMap::with_hasher(NoOpHasherBuilder),
+        }
+    }
+
+    /// Registers component type to be replicated.
+    /// `encode` appends encoded value to the output
+    /// and `decode` restores value from bytes produced by `encode`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if component type is already registered.
+    pub fn register<T>(&mut self, encode: fn(&T, &mut Vec<u8>), decode: fn(&[u8]) -> Option<T>)
+    where
+        T: Component,
+    {
+        let idx = self.replicated.len() as u32;
+        let old = self.ids.insert(TypeId::of::<T>(), idx);
+        assert!(
+            old.is_none(),
+            "Component `{}` is already registered",
+            type_name::<T>()
+        );
+
+        self.replicated.push(Replicated {
+            id: TypeId::of::<T>(),
+            debug_name: type_name::<T>(),
+            encode: Box::new(move |ptr, out| encode(unsafe { &*ptr.cast::<T>() }, out)),
+            apply: Box::new(move |world, entity, bytes| {
+                let value = decode(bytes).ok_or(DeltaError::InvalidComponent {
+                    debug_name: type_name::<T>(),
+                })?;
+                world
+                    .try_insert(entity, value)
+                    .expect("Entity is alive while delta is applied");
+                Ok(())
+            }),
+            remove: |world, entity| {
+                let _ = world.remove::<T>(entity);
+            },
+        });
+    }
+
+    /// Returns `true` if component type with specified id is registered.
+    #[inline]
+    pub fn contains_id(&self, id: TypeId) -> bool {
+        self.ids.contains_key(&id)
+    }
+
+    /// Returns index of the component type in deltas.
+    #[inline]
+    pub(crate) fn index(&self, id: TypeId) -> Option<u32> {
+        self.ids.get(&id).copied()
+    }
+
+    /// Appends encoded component value to the output.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to valid value of component type at specified index.
+    #[inline]
+    pub(crate) unsafe fn encode(&self, idx: u32, ptr: *const u8, out: &mut Vec<u8>) {
+        (self.replicated[idx as usize].encode)(ptr, out)
+    }
+
+    /// Decodes component value and inserts it to the entity.
+    /// Returns id of the component type.
+    pub(crate) fn apply(
+        &self,
+        idx: u32,
+        world: &mut World<A>,
+        entity: &EntityId,
+        bytes: &[u8],
+    ) -> Result<TypeId, DeltaError> {
+        let replicated = self
+            .replicated
+            .get(idx as usize)
+            .ok_or(DeltaError::UnknownComponent { idx })?;
+        (replicated.apply)(world, entity, bytes)?;
+        Ok(replicated.id)
+    }
+
+    /// Removes component from the entity.
+    pub(crate) fn remove(
+        &self,
+        idx: u32,
+        world: &mut World<A>,
+        entity: &EntityId,
+    ) -> Result<(), DeltaError> {
+        let replicated = self
+            .replicated
+            .get(idx as usize)
+            .ok_or(DeltaError::UnknownComponent { idx })?;
+        (replicated.remove)(world, entity);
+        Ok(())
+    }
+}
+
+/// Error that may occur when delta is applied to the `World`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DeltaError {
+    /// Delta ended in the middle of a record.
+    UnexpectedEnd,
+
+    /// Delta contains record of unknown kind.
+    InvalidRecord {
+        /// Tag of the record.
+        tag: u8,
+    },
+
+    /// Delta references component type not present in the [`Registry`].
+    UnknownComponent {
+        /// Index of the component type in the delta.
+        idx: u32,
+    },
+
+    /// Component value failed to decode.
+    InvalidComponent {
+        /// Name of the component type.
+        debug_name: &'static str,
+    },
+}
+
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => f.write_str("Unexpected end of delta"),
+            Self::InvalidRecord { tag } => write!(f, "Invalid delta record `{}`", tag),
+            Self::UnknownComponent { idx } => {
+                write!(f, "Component `{}` is not registered for replication", idx)
+            }
+            Self::InvalidComponent { debug_name } => {
+                write!(f, "Failed to decode component `{}`", debug_name)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DeltaError {}
+
+/// Reads delta records from bytes.
+pub(crate) struct DeltaReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> DeltaReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        DeltaReader { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DeltaError> {
+        if self.bytes.len() < len {
+            return Err(DeltaError::UnexpectedEnd);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DeltaError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DeltaError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Reads entity id of the record with specified tag.
+    pub fn read_entity(&mut self, tag: u8) -> Result<EntityId, DeltaError> {
+        let bits = u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap());
+        EntityId::from_bits(bits).ok_or(DeltaError::InvalidRecord { tag })
+    }
+}
+
+/// Writes delta record header for the entity.
+pub(crate) fn write_header(out: &mut Vec<u8>, tag: u8, entity: &EntityId) {
+    out.push(tag);
+    out.extend_from_slice(&entity.bits().to_le_bytes());
+}
@@ -2,19 +2,28 @@ use core::num::NonZeroU32;
 
 #[cfg(feature = "rc")]
 use core::{
+    alloc::Layout,
     marker::PhantomData,
-    ptr::NonNull,
+    ptr::{self, NonNull},
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use alloc::{fmt, vec::Vec};
+use alloc::fmt;
 
-#[cfg(feature = "rc")]
-use alloc::boxed::Box;
+#[cfg(feature = "leak-detection")]
+use alloc::vec::Vec;
+
+use crate::allocator::{AllocVec, SharedAllocator};
 
 #[cfg(feature = "rc")]
 use crate::world::OwnershipError;
 
+#[cfg(feature = "leak-detection")]
+use core::{panic::Location, sync::atomic::AtomicU64};
+
+#[cfg(feature = "leak-detection")]
+use std::sync::Mutex;
+
 use crate::world::NoSuchEntity;
 
 #[cfg(feature = "rc")]
@@ -23,6 +32,7 @@ use super::{queue::DropQueue, strong::StrongInner, typed::Entity};
 use super::EntityId;
 
 /// Stores entity information in the World
+#[derive(Clone, Copy)]
 struct EntityData {
     /// Entity generation.
     gen: u32,
@@ -51,26 +61,96 @@ impl fmt::Debug for EntityData {
 pub(super) struct EntityDataShared {
     pub refs: AtomicUsize,
     pub queue: DropQueue,
+
+    /// Locations where live strong references were created.
+    #[cfg(feature = "leak-detection")]
+    pub locations: Mutex<Vec<&'static Location<'static>>>,
+
+    /// Maintenance cycle when entity got its first strong reference.
+    #[cfg(feature = "leak-detection")]
+    pub since: AtomicU64,
 }
 
 #[cfg(feature = "rc")]
 impl EntityDataShared {
-    fn alloc(queue: DropQueue) -> NonNull<Self> {
-        let ptr = Box::new(EntityDataShared {
-            refs: AtomicUsize::new(1),
-            queue,
-        });
+    fn alloc(queue: DropQueue, alloc: &SharedAllocator) -> NonNull<Self> {
+        let ptr = alloc.alloc(Layout::new::<Self>()).cast::<Self>();
+
+        // Shared data is never deallocated,
+        // it is reused for entities spawned in the same slot.
+        unsafe {
+            ptr::write(
+                ptr.as_ptr(),
+                EntityDataShared {
+                    refs: AtomicUsize::new(1),
+                    queue,
+
+                    #[cfg(feature = "leak-detection")]
+                    locations: Mutex::new(Vec::new()),
+
+                    #[cfg(feature = "leak-detection")]
+                    since: AtomicU64::new(0),
+                },
+            );
+        }
 
-        NonNull::from(Box::leak(ptr))
+        ptr
     }
 }
 
+#[cfg(feature = "leak-detection")]
+impl EntityDataShared {
+    /// Records location of new strong reference.
+    pub fn track(&self, location: &'static Location<'static>) {
+        self.locations.lock().unwrap().push(location);
+    }
+
+    /// Forgets location of released strong reference.
+    pub fn untrack(&self, location: &'static Location<'static>) {
+        let mut locations = self.locations.lock().unwrap();
+        if let Some(idx) = locations.iter().position(|l| *l == location) {
+            locations.swap_remove(idx);
+        }
+    }
+}
+
+/// Entity that holds strong references for a long time.
+/// Returned from [`World::leak_report`].
+///
+/// [`World::leak_report`]: `edict::world::World::leak_report`
+#[cfg(feature = "leak-detection")]
+#[derive(Clone, Debug)]
+pub struct LeakedEntity {
+    /// Id of the entity.
+    pub id: EntityId,
+
+    /// Number of strong references to the entity.
+    pub strong_count: usize,
+
+    /// Number of maintenance cycles since entity got its first strong reference.
+    pub cycles: u64,
+
+    /// Locations where live strong references were created.
+    pub locations: Vec<&'static Location<'static>>,
+}
+
 pub(crate) struct Entities {
-    array: Vec<EntityData>,
-    free_entity_ids: Vec<u32>,
+    array: AllocVec<EntityData>,
+    free_entity_ids: AllocVec<u32>,
+
+    /// Number of entity slots allocated up front.
+    /// `None` if slots are allocated on demand.
+    fixed_capacity: Option<usize>,
 
     #[cfg(feature = "rc")]
     queue: DropQueue,
+
+    /// Allocator of entity slots and shared data.
+    alloc: SharedAllocator,
+
+    /// Number of maintenance cycles.
+    #[cfg(feature = "leak-detection")]
+    cycle: u64,
 }
 
 impl fmt::Debug for Entities {
@@ -83,31 +163,119 @@ impl fmt::Debug for Entities {
 
 impl Entities {
     #[cfg(feature = "rc")]
-    pub fn new(inline_cap: usize) -> Self {
-        let queue = DropQueue::new(inline_cap);
+    pub fn new(inline_cap: usize, alloc: SharedAllocator) -> Self {
+        let queue = DropQueue::new(inline_cap, alloc.clone());
 
         Entities {
-            array: Vec::new(),
-            free_entity_ids: Vec::new(),
+            array: AllocVec::new(alloc.clone()),
+            free_entity_ids: AllocVec::new(alloc.clone()),
+            fixed_capacity: None,
             queue,
+            alloc,
+
+            #[cfg(feature = "leak-detection")]
+            cycle: 0,
         }
     }
 
     #[cfg(not(feature = "rc"))]
-    pub fn new() -> Self {
+    pub fn new(alloc: SharedAllocator) -> Self {
         Entities {
-            array: Vec::new(),
-            free_entity_ids: Vec::new(),
+            array: AllocVec::new(alloc.clone()),
+            free_entity_ids: AllocVec::new(alloc.clone()),
+            fixed_capacity: None,
+            alloc,
         }
     }
 
+    /// Returns copy of entities with same ids and locations.
+    /// All entities in the copy are owned by the `World`.
+    #[cfg(feature = "rc")]
+    pub fn fork(&self, inline_cap: usize) -> Self {
+        let mut entities = Entities {
+            array: self.fork_array(),
+            free_entity_ids: self.free_entity_ids.clone(),
+            fixed_capacity: None,
+            queue: DropQueue::new(inline_cap, self.alloc.clone()),
+            alloc: self.alloc.clone(),
+
+            #[cfg(feature = "leak-detection")]
+            cycle: 0,
+        };
+
+        if let Some(cap) = self.fixed_capacity {
+            entities.fix_capacity(cap);
+        }
+        entities
+    }
+
+    /// Returns copy of entities with same ids and locations.
+    #[cfg(not(feature = "rc"))]
+    pub fn fork(&self) -> Self {
+        let mut entities = Entities {
+            array: self.fork_array(),
+            free_entity_ids: self.free_entity_ids.clone(),
+            fixed_capacity: None,
+            alloc: self.alloc.clone(),
+        };
+
+        if let Some(cap) = self.fixed_capacity {
+            entities.fix_capacity(cap);
+        }
+        entities
+    }
+
+    /// Allocates slots for `cap` entities up front.
+    /// Spawning more entities panics instead of allocating.
+    pub fn fix_capacity(&mut self, cap: usize) {
+        self.array
+            .reserve_exact(cap.saturating_sub(self.array.len()));
+        self.free_entity_ids
+            .reserve_exact(cap.saturating_sub(self.free_entity_ids.len()));
+        self.fixed_capacity = Some(cap);
+    }
+
+    /// Returns `true` if capacity is fixed and no more entities can be spawned.
+    pub fn is_full(&self) -> bool {
+        !self.has_space(1)
+    }
+
+    /// Returns `true` if `additional` more entities can be spawned.
+    pub fn has_space(&self, additional: usize) -> bool {
+        match self.fixed_capacity {
+            None => true,
+            Some(cap) => {
+                self.free_entity_ids.len() + cap.saturating_sub(self.array.len()) >= additional
+            }
+        }
+    }
+
+    /// Allocates slot for new entity.
+    fn push(&mut self, data: EntityData) {
+        assert!(!self.is_full(), "Entity capacity exceeded");
+        self.array.push(data);
+    }
+
+    fn fork_array(&self) -> AllocVec<EntityData> {
+        let mut array = AllocVec::new(self.alloc.clone());
+        array.extend(self.array.iter().map(|data| EntityData {
+            gen: data.gen,
+            archetype: data.archetype,
+            idx: data.idx,
+
+            #[cfg(feature = "rc")]
+            shared: None,
+        }));
+        array
+    }
+
     pub fn spawn(&mut self) -> EntityId {
         match self.free_entity_ids.pop() {
             None => {
                 let id = self.array.len() as u32;
                 let gen = first_gen();
 
-                self.array.push(EntityData {
+                self.push(EntityData {
                     gen: gen.get(),
                     idx: 0,
                     archetype: 0,
@@ -135,15 +303,17 @@ impl Entities {
     }
 
     #[cfg(feature = "rc")]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
     pub fn spawn_owning(&mut self) -> Entity {
         match self.free_entity_ids.pop() {
             None => {
                 let id = self.array.len() as u32;
                 let gen = first_gen();
 
-                let shared = EntityDataShared::alloc(self.queue.clone());
+                let shared = EntityDataShared::alloc(self.queue.clone(), &self.alloc);
+                self.strong_since(shared);
 
-                self.array.push(EntityData {
+                self.push(EntityData {
                     gen: gen.get(),
                     idx: 0,
                     archetype: 0,
@@ -151,10 +321,7 @@ impl Entities {
                 });
 
                 Entity {
-                    inner: StrongInner {
-                        id: EntityId::new(id, gen),
-                        shared,
-                    },
+                    inner: StrongInner::new(EntityId::new(id, gen), shared),
                     marker: PhantomData,
                 }
             }
@@ -164,7 +331,7 @@ impl Entities {
 
                 let shared = match data.shared {
                     None => {
-                        let shared = EntityDataShared::alloc(self.queue.clone());
+                        let shared = EntityDataShared::alloc(self.queue.clone(), &self.alloc);
                         data.shared = Some(shared);
                         shared
                     }
@@ -179,12 +346,10 @@ impl Entities {
                         shared
                     }
                 };
+                self.strong_since(shared);
 
                 Entity {
-                    inner: StrongInner {
-                        id: EntityId::new(id, gen),
-                        shared,
-                    },
+                    inner: StrongInner::new(EntityId::new(id, gen), shared),
                     marker: PhantomData,
                 }
             }
@@ -192,6 +357,7 @@ impl Entities {
     }
 
     #[cfg(feature = "rc")]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
     pub fn take_ownership(&mut self, id: &EntityId) -> Result<Entity, OwnershipError> {
         if self.array.len() as u32 <= id.idx {
             return Err(OwnershipError::NoSuchEntity);
@@ -203,7 +369,7 @@ impl Entities {
 
         let shared = match data.shared {
             None => {
-                let shared = EntityDataShared::alloc(self.queue.clone());
+                let shared = EntityDataShared::alloc(self.queue.clone(), &self.alloc);
                 data.shared = Some(shared);
                 shared
             }
@@ -216,12 +382,44 @@ impl Entities {
                 shared
             }
         };
+        self.strong_since(shared);
 
         Ok(Entity {
-            inner: StrongInner {
-                id: *id,
-                shared: shared,
-            },
+            inner: StrongInner::new(*id, shared),
+            marker: PhantomData,
+        })
+    }
+
+    /// Returns new strong reference to the entity
+    /// if it is alive and has strong references.
+    #[cfg(feature = "rc")]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
+    pub fn upgrade(&self, id: &EntityId) -> Option<Entity> {
+        let data = self.array.get(id.idx as usize)?;
+        if id.gen.get() != data.gen {
+            return None;
+        }
+
+        let shared = data.shared?;
+        let refs = &unsafe { &*shared.as_ptr() }.refs;
+
+        let mut old = refs.load(Ordering::Relaxed);
+        loop {
+            // Zero refs means that last reference is dropped
+            // and entity is waiting in the drop queue.
+            // `usize::MAX` means that entity is owned by the `World`.
+            if old == 0 || old == usize::MAX {
+                return None;
+            }
+
+            match refs.compare_exchange_weak(old, old + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(actual) => old = actual,
+            }
+        }
+
+        Some(Entity {
+            inner: StrongInner::new(*id, shared),
             marker: PhantomData,
         })
     }
@@ -240,8 +438,14 @@ impl Entities {
                     unsafe { unreachable_unchecked() }
                 }
                 refs.store(usize::MAX, Ordering::Relaxed);
+
+                #[cfg(feature = "leak-detection")]
+                unsafe { &*shared.as_ptr() }.untrack(entity.inner.location);
             }
         };
+
+        // Reference is given to the `World` and must not be released.
+        core::mem::forget(entity);
     }
 
     pub fn despawn(&mut self, id: &EntityId) -> Result<(u32, u32), DespawnError> {
@@ -269,6 +473,96 @@ impl Entities {
         Ok((data.archetype, data.idx))
     }
 
+    /// Checks that entity is alive and can be despawned without despawning it.
+    pub fn check_despawn(&self, id: &EntityId) -> Result<(), DespawnError> {
+        if self.array.len() as u32 <= id.idx {
+            return Err(NoSuchEntity.into());
+        }
+        let data = &self.array[id.idx as usize];
+        if id.gen.get() != data.gen {
+            return Err(NoSuchEntity.into());
+        }
+
+        #[cfg(feature = "rc")]
+        if let Some(shared) = data.shared {
+            if unsafe { &*shared.as_ptr() }.refs.load(Ordering::Relaxed) != usize::MAX {
+                return Err(DespawnError::NotOwned);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns number of strong references to the entity.
+    /// Entities owned by the `World` have none.
+    #[cfg(feature = "rc")]
+    pub fn strong_count(&self, id: &EntityId) -> Result<usize, NoSuchEntity> {
+        let data = self.array.get(id.idx as usize).ok_or(NoSuchEntity)?;
+        if id.gen.get() != data.gen {
+            return Err(NoSuchEntity);
+        }
+
+        match data.shared {
+            None => Ok(0),
+            Some(shared) => match unsafe { &*shared.as_ptr() }.refs.load(Ordering::Relaxed) {
+                usize::MAX => Ok(0),
+                refs => Ok(refs),
+            },
+        }
+    }
+
+    /// Records current maintenance cycle as start of strong references to the entity.
+    #[cfg(feature = "rc")]
+    #[inline]
+    fn strong_since(&self, shared: NonNull<EntityDataShared>) {
+        #[cfg(feature = "leak-detection")]
+        unsafe { &*shared.as_ptr() }
+            .since
+            .store(self.cycle, Ordering::Relaxed);
+
+        #[cfg(not(feature = "leak-detection"))]
+        let _ = shared;
+    }
+
+    /// Starts next maintenance cycle.
+    #[cfg(feature = "leak-detection")]
+    pub fn next_cycle(&mut self) {
+        self.cycle += 1;
+    }
+
+    /// Returns entities that hold strong references
+    /// for more than `cycles` maintenance cycles.
+    #[cfg(feature = "leak-detection")]
+    pub fn leak_report(&self, cycles: u64) -> Vec<LeakedEntity> {
+        let mut report = Vec::new();
+
+        for (idx, data) in self.array.iter().enumerate() {
+            let shared = match data.shared {
+                None => continue,
+                Some(shared) => unsafe { &*shared.as_ptr() },
+            };
+
+            let strong_count = shared.refs.load(Ordering::Relaxed);
+            if strong_count == 0 || strong_count == usize::MAX {
+                continue;
+            }
+
+            let held = self.cycle - shared.since.load(Ordering::Relaxed);
+            if held <= cycles {
+                continue;
+            }
+
+            report.push(LeakedEntity {
+                id: EntityId::new(idx as u32, NonZeroU32::new(data.gen).unwrap()),
+                strong_count,
+                cycles: held,
+                locations: shared.locations.lock().unwrap().clone(),
+            });
+        }
+
+        report
+    }
+
     pub fn set_location(&mut self, id: u32, archetype: u32, idx: u32) {
         let data = &mut self.array[id as usize];
         data.archetype = archetype;
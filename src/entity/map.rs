@@ -0,0 +1,177 @@
+use alloc::vec::Vec;
+use core::{fmt, iter::FromIterator};
+
+use hashbrown::HashMap;
+
+use crate::hash::MulHasherBuilder;
+
+use super::EntityId;
+
+/// Table that maps old entity ids to new ones.
+///
+/// Returned by [`World::transfer`] and [`World::merge`]
+/// and can be filled manually when entities are recreated
+/// from serialized or replicated data.
+/// Used to rewrite entity ids in components that implement [`MapEntities`].
+///
+/// [`World::transfer`]: `edict::world::World::transfer`
+/// [`World::merge`]: `edict::world::World::merge`
+#[derive(Clone)]
+pub struct EntityMap {
+    map: HashMap<u64, EntityId, MulHasherBuilder>,
+}
+
+impl fmt::Debug for EntityMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Default for EntityMap {
+    #[inline]
+    fn default() -> Self {
+        EntityMap::new()
+    }
+}
+
+impl EntityMap {
+    /// Returns new empty map.
+    #[inline]
+    pub fn new() -> Self {
+        EntityMap {
+            map: HashMap::with_hasher(MulHasherBuilder),
+        }
+    }
+
+    /// Maps `old` id to `new` id.
+    /// Returns previous mapping of `old` id if there was one.
+    #[inline]
+    pub fn insert(&mut self, old: EntityId, new: EntityId) -> Option<EntityId> {
+        self.map.insert(old.bits(), new)
+    }
+
+    /// Removes mapping of `old` id.
+    /// Returns new id it was mapped to if there was one.
+    #[inline]
+    pub fn remove(&mut self, old: &EntityId) -> Option<EntityId> {
+        self.map.remove(&old.bits())
+    }
+
+    /// Returns new id for the `old` id if there is one.
+    #[inline]
+    pub fn get(&self, old: &EntityId) -> Option<EntityId> {
+        self.map.get(&old.bits()).copied()
+    }
+
+    /// Returns new id for the `old` id,
+    /// or `old` id itself if it is not mapped.
+    #[inline]
+    pub fn map(&self, old: EntityId) -> EntityId {
+        self.get(&old).unwrap_or(old)
+    }
+
+    /// Returns number of mapped ids.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if no ids are mapped.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns iterator over pairs of old and new ids in arbitrary order.
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (EntityId, EntityId)> + '_ {
+        self.map
+            .iter()
+            .map(|(&bits, &new)| (EntityId::from_bits(bits).unwrap(), new))
+    }
+}
+
+impl Extend<(EntityId, EntityId)> for EntityMap {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (EntityId, EntityId)>,
+    {
+        self.map
+            .extend(iter.into_iter().map(|(old, new)| (old.bits(), new)));
+    }
+}
+
+impl FromIterator<(EntityId, EntityId)> for EntityMap {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (EntityId, EntityId)>,
+    {
+        let mut map = EntityMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Trait for values that hold entity ids,
+/// which need to be rewritten when entities get new ids.
+///
+/// Components that implement this trait and are registered
+/// with [`World::register_map_entities`] are remapped by [`World::transfer`],
+/// [`World::merge`] and [`World::map_entities`].
+///
+/// Strong references are tied to the `World` that created them
+/// and cannot be remapped.
+/// Store [`EntityId`] in components that may move between worlds.
+///
+/// [`World::register_map_entities`]: `edict::world::World::register_map_entities`
+/// [`World::transfer`]: `edict::world::World::transfer`
+/// [`World::merge`]: `edict::world::World::merge`
+/// [`World::map_entities`]: `edict::world::World::map_entities`
+pub trait MapEntities {
+    /// Rewrites entity ids in this value using provided map.
+    /// Ids that are not present in the map are left unchanged.
+    fn map_entities(&mut self, map: &EntityMap);
+}
+
+impl MapEntities for EntityId {
+    #[inline]
+    fn map_entities(&mut self, map: &EntityMap) {
+        *self = map.map(*self);
+    }
+}
+
+impl<T> MapEntities for Option<T>
+where
+    T: MapEntities,
+{
+    #[inline]
+    fn map_entities(&mut self, map: &EntityMap) {
+        if let Some(value) = self {
+            value.map_entities(map);
+        }
+    }
+}
+
+impl<T> MapEntities for Vec<T>
+where
+    T: MapEntities,
+{
+    #[inline]
+    fn map_entities(&mut self, map: &EntityMap) {
+        for value in self {
+            value.map_entities(map);
+        }
+    }
+}
+
+impl<T, const N: usize> MapEntities for [T; N]
+where
+    T: MapEntities,
+{
+    #[inline]
+    fn map_entities(&mut self, map: &EntityMap) {
+        for value in self {
+            value.map_entities(map);
+        }
+    }
+}
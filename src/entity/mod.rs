@@ -3,13 +3,20 @@
 //! Strong, weak and raw ids.
 
 pub(crate) use self::entities::Entities;
-pub use self::id::EntityId;
+pub use self::{
+    id::EntityId,
+    map::{EntityMap, MapEntities},
+};
 
 #[cfg(feature = "rc")]
 pub use self::typed::{Entity, SharedEntity};
 
+#[cfg(feature = "leak-detection")]
+pub use self::entities::LeakedEntity;
+
 mod entities;
 mod id;
+mod map;
 
 #[cfg(feature = "rc")]
 mod queue;
@@ -5,7 +5,7 @@ use core::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use alloc::vec::Vec;
+use crate::allocator::{AllocVec, SharedAllocator};
 
 #[derive(PartialEq, Eq)]
 pub(crate) struct DropQueue {
@@ -38,7 +38,8 @@ struct DropQueueInner<Q: ?Sized> {
     refs: AtomicUsize,
     lock: AtomicUsize,
     cursor: AtomicUsize,
-    tail: UnsafeCell<Vec<u32>>,
+    tail: UnsafeCell<AllocVec<u32>>,
+    alloc: SharedAllocator,
     buffer: Q,
 }
 
@@ -49,9 +50,10 @@ impl Drop for DropQueue {
 
         if old == 1 {
             unsafe {
-                ptr::drop_in_place(inner);
+                let alloc = (*inner).alloc.clone();
                 let layout = Layout::for_value(&*inner);
-                alloc::alloc::dealloc(inner.cast(), layout)
+                ptr::drop_in_place(inner);
+                alloc.deallocate(self.inner.cast(), layout)
             };
         }
     }
@@ -66,14 +68,15 @@ impl Clone for DropQueue {
 }
 
 impl DropQueue {
-    pub fn new(inline_cap: usize) -> Self {
+    pub fn new(inline_cap: usize, alloc: SharedAllocator) -> Self {
         //
         // !!!WARNING!!!
         // This code relies on layout of the `DropQueueInner` type.
         // Any changes in the fields, their types or order must be reflected here.
         //
         let atomic = Layout::new::<AtomicUsize>();
-        let tail = Layout::new::<UnsafeCell<Vec<u32>>>();
+        let tail = Layout::new::<UnsafeCell<AllocVec<u32>>>();
+        let shared_alloc = Layout::new::<SharedAllocator>();
         let buffer = match Layout::array::<UnsafeCell<u32>>(inline_cap) {
             Err(err) => {
                 panic!(
@@ -88,6 +91,7 @@ impl DropQueue {
         let (queue_inner, lock_offset) = atomic.extend(atomic).unwrap();
         let (queue_inner, cursor_offset) = queue_inner.extend(atomic).unwrap();
         let (queue_inner, tail_offset) = queue_inner.extend(tail).unwrap();
+        let (queue_inner, alloc_offset) = queue_inner.extend(shared_alloc).unwrap();
 
         match queue_inner.extend(buffer) {
             Err(err) => {
@@ -100,15 +104,16 @@ impl DropQueue {
                 let queue_inner = queue_inner.pad_to_align();
 
                 unsafe {
-                    let ptr = alloc::alloc::alloc(queue_inner);
+                    let ptr = alloc.alloc(queue_inner).as_ptr();
 
                     ptr::write(ptr.add(refs_offset) as _, AtomicUsize::new(1));
                     ptr::write(ptr.add(lock_offset) as _, AtomicUsize::new(0));
                     ptr::write(ptr.add(cursor_offset) as _, AtomicUsize::new(0));
                     ptr::write(
                         ptr.add(tail_offset) as _,
-                        UnsafeCell::new(Vec::<u32>::new()),
+                        UnsafeCell::new(AllocVec::<u32>::new(alloc.clone())),
                     );
+                    ptr::write(ptr.add(alloc_offset) as _, alloc);
                     ptr::write_bytes(
                         ptr.add(buffer_offset) as *mut UnsafeCell<u32>,
                         0,
@@ -130,7 +135,7 @@ impl DropQueue {
         unsafe { &*self.inner.as_ptr() }.drop_entity(id);
     }
 
-    pub fn drain<'a>(&'a self, extend: &mut Vec<u32>) {
+    pub fn drain<'a>(&'a self, extend: &mut AllocVec<u32>) {
         unsafe { &*self.inner.as_ptr() }.drain(extend)
     }
 }
@@ -210,7 +215,7 @@ impl DropQueueInner<[UnsafeCell<u32>]> {
         }
     }
 
-    fn drain<'a>(&'a self, extend: &mut Vec<u32>) {
+    fn drain<'a>(&'a self, extend: &mut AllocVec<u32>) {
         loop {
             let res = self.lock.compare_exchange_weak(
                 0,
@@ -224,7 +229,9 @@ impl DropQueueInner<[UnsafeCell<u32>]> {
                     let len = self.cursor.swap(0, Ordering::Relaxed);
 
                     extend.extend(self.buffer[..len].iter().map(|id| unsafe { *id.get() }));
-                    extend.append(unsafe { &mut *self.tail.get() });
+                    let tail = unsafe { &mut *self.tail.get() };
+                    extend.extend_from_slice(tail);
+                    tail.clear();
 
                     self.lock.fetch_sub(isize::MAX as usize, Ordering::Release);
                     break;
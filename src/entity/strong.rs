@@ -1,11 +1,17 @@
 use core::{ptr::NonNull, sync::atomic::Ordering};
 
+#[cfg(feature = "leak-detection")]
+use core::panic::Location;
+
 use super::{entities::EntityDataShared, id::EntityId};
 
-#[derive(PartialEq, Eq)]
 pub(super) struct StrongInner {
     pub id: EntityId,
     pub shared: NonNull<EntityDataShared>,
+
+    /// Location where this reference was created.
+    #[cfg(feature = "leak-detection")]
+    pub location: &'static Location<'static>,
 }
 
 #[allow(dead_code)]
@@ -26,9 +32,44 @@ unsafe impl Send for StrongInner {}
 /// `EntityDataShared` is `Send + Sync`.
 unsafe impl Sync for StrongInner {}
 
+impl StrongInner {
+    /// Wraps reference already counted in `shared`.
+    #[inline]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
+    pub fn new(id: EntityId, shared: NonNull<EntityDataShared>) -> Self {
+        #[cfg(feature = "leak-detection")]
+        let location = {
+            let location = Location::caller();
+            unsafe { &*shared.as_ptr() }.track(location);
+            location
+        };
+
+        StrongInner {
+            id,
+            shared,
+
+            #[cfg(feature = "leak-detection")]
+            location,
+        }
+    }
+}
+
+impl PartialEq for StrongInner {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.shared == other.shared
+    }
+}
+
+impl Eq for StrongInner {}
+
 impl Drop for StrongInner {
     fn drop(&mut self) {
         let shared = unsafe { &*self.shared.as_ptr() };
+
+        #[cfg(feature = "leak-detection")]
+        shared.untrack(self.location);
+
         let old = shared.refs.fetch_sub(1, Ordering::Release);
         if old == 1 {
             shared.queue.drop_entity(self.id.idx);
@@ -37,13 +78,11 @@ impl Drop for StrongInner {
 }
 
 impl Clone for StrongInner {
+    #[cfg_attr(feature = "leak-detection", track_caller)]
     fn clone(&self) -> Self {
         unsafe {
             (*self.shared.as_ptr()).refs.fetch_add(1, Ordering::Relaxed);
         }
-        StrongInner {
-            id: self.id,
-            shared: self.shared,
-        }
+        StrongInner::new(self.id, self.shared)
     }
 }
@@ -1,6 +1,6 @@
 use core::{fmt, marker::PhantomData, ops::Deref};
 
-use crate::{bundle::Bundle, world::World};
+use crate::{allocator::Allocator, bundle::Bundle, world::World};
 
 use super::{id::EntityId, strong::StrongInner};
 
@@ -9,7 +9,7 @@ use super::{id::EntityId, strong::StrongInner};
 ///
 /// Supports pinning components to the enitity, making them accessible through [`World::get`]
 /// without wrapping in `Option`.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(PartialEq, Eq)]
 pub struct Entity<T = ()> {
     pub(super) inner: StrongInner,
     pub(super) marker: PhantomData<fn() -> T>,
@@ -45,6 +45,17 @@ impl<T> Entity<T> {
     }
 }
 
+impl<T> Clone for Entity<T> {
+    #[inline]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
+    fn clone(&self) -> Self {
+        Entity {
+            inner: self.inner.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
 impl<T> fmt::Debug for Entity<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Entity")
@@ -75,7 +86,7 @@ impl<T> Deref for Entity<T> {
 ///
 /// This type is generic over list of pinned components.
 /// Pinned components cannot be removed and thus they can be accessed with guarantee.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(PartialEq, Eq)]
 pub struct SharedEntity<T = ()> {
     inner: Entity<T>,
 }
@@ -87,6 +98,16 @@ impl<T> SharedEntity<T> {
     }
 }
 
+impl<T> Clone for SharedEntity<T> {
+    #[inline]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
+    fn clone(&self) -> Self {
+        SharedEntity {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 impl<T> fmt::Debug for SharedEntity<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Entity")
@@ -169,7 +190,7 @@ macro_rules! for_tuple {
         /// let entity = world.spawn_owning((0u32,));
         /// let entity = entity.pin::<u8>(&mut world);
         /// ```
-        pub fn pin<T: 'static>(self, world: &mut World) -> Entity<($($a,)* T,)> {
+        pub fn pin<T: 'static>(self, world: &mut World<impl Allocator>) -> Entity<($($a,)* T,)> {
             assert!(world.has_component_owning::<T, _>(&self));
 
             drop(world);
@@ -0,0 +1,172 @@
+//! This module implements [`Events`] queue
+//! and [`EventReader`] cursor to read events from it.
+
+use core::{any::Any, fmt, marker::PhantomData, mem};
+
+use alloc::vec::Vec;
+
+/// Double-buffered queue of events of one type.
+///
+/// Events are kept until two updates happen after they were sent.
+/// `World` keeps `Events` for each event type sent with [`World::send_event`]
+/// and updates them in [`World::maintain`].
+///
+/// [`World::send_event`]: `edict::world::World::send_event`
+/// [`World::maintain`]: `edict::world::World::maintain`
+pub struct Events<E> {
+    /// Events sent before last update.
+    old: Vec<E>,
+
+    /// Events sent since last update.
+    new: Vec<E>,
+
+    /// Sequence number of the first event in `old`.
+    start: u64,
+}
+
+impl<E> fmt::Debug for Events<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("old", &self.old.len())
+            .field("new", &self.new.len())
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+impl<E> Default for Events<E> {
+    #[inline]
+    fn default() -> Self {
+        Events::new()
+    }
+}
+
+impl<E> Events<E> {
+    /// Returns new empty queue.
+    #[inline]
+    pub const fn new() -> Self {
+        Events {
+            old: Vec::new(),
+            new: Vec::new(),
+            start: 0,
+        }
+    }
+
+    /// Adds event to the queue.
+    #[inline]
+    pub fn send(&mut self, event: E) {
+        self.new.push(event);
+    }
+
+    /// Returns iterator over events not yet seen by the reader
+    /// and advances the reader past them.
+    ///
+    /// Events dropped before the reader saw them are skipped.
+    #[inline]
+    pub fn read(&self, reader: &mut EventReader<E>) -> impl Iterator<Item = &E> {
+        let skip = reader.next.saturating_sub(self.start) as usize;
+        reader.next = self.end();
+        self.old.iter().chain(&self.new).skip(skip)
+    }
+
+    /// Returns number of events in the queue.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.old.len() + self.new.len()
+    }
+
+    /// Returns `true` if queue contains no events.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.old.is_empty() && self.new.is_empty()
+    }
+
+    /// Drops events sent before previous update.
+    /// Events sent since previous update are kept until next one.
+    #[inline]
+    pub fn update(&mut self) {
+        self.start += self.old.len() as u64;
+        self.old.clear();
+        mem::swap(&mut self.old, &mut self.new);
+    }
+
+    /// Returns sequence number of the next event to be sent.
+    #[inline]
+    fn end(&self) -> u64 {
+        self.start + self.len() as u64
+    }
+}
+
+/// Cursor into [`Events`] queue.
+///
+/// Remembers which events were already read
+/// so that each event is seen exactly once by a reader.
+#[allow(missing_copy_implementations)]
+pub struct EventReader<E> {
+    next: u64,
+    marker: PhantomData<fn() -> E>,
+}
+
+impl<E> fmt::Debug for EventReader<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventReader")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<E> Clone for EventReader<E> {
+    #[inline]
+    fn clone(&self) -> Self {
+        EventReader {
+            next: self.next,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<E> Default for EventReader<E> {
+    #[inline]
+    fn default() -> Self {
+        EventReader::new()
+    }
+}
+
+impl<E> EventReader<E> {
+    /// Returns new `EventReader` instance
+    /// that would see all events still in the queue.
+    #[inline]
+    pub const fn new() -> Self {
+        EventReader {
+            next: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Type-erased [`Events`] stored in the `World`.
+pub(crate) trait AnyEvents: fmt::Debug {
+    fn update(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<E> AnyEvents for Events<E>
+where
+    E: 'static,
+{
+    #[inline]
+    fn update(&mut self) {
+        Events::update(self)
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
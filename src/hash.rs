@@ -85,6 +85,7 @@ impl Hasher for NoOpHasher {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct NoOpHasherBuilder;
 
 impl BuildHasher for NoOpHasherBuilder {
@@ -147,6 +148,7 @@ impl Hasher for MulHasher {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct MulHasherBuilder;
 
 impl BuildHasher for MulHasherBuilder {
@@ -0,0 +1,306 @@
+//! Secondary indices over component values.
+//!
+//! Indices are kept by the `World` and updated lazily.
+//! Despawns and removals of components are applied immediately,
+//! while modified components are found using entity versions
+//! when index is accessed.
+//! Each index owns [`Tracks`] that marks last refresh.
+
+use core::{
+    any::{Any, TypeId},
+    cell::{Ref, RefCell},
+    fmt,
+    hash::Hash,
+    mem,
+    ops::{Bound, RangeBounds},
+};
+
+use alloc::{boxed::Box, collections::BTreeMap};
+use hashbrown::HashMap;
+use smallvec::SmallVec;
+
+use crate::{
+    archetype::Archetype,
+    component::Component,
+    entity::EntityId,
+    hash::{MulHasherBuilder, NoOpHasherBuilder},
+    query::{Modified, QueryTrackedIter},
+    world::Tracks,
+};
+
+/// Type-erased index stored in the `World`.
+pub(crate) trait AnyIndex: fmt::Debug {
+    fn as_any(&self) -> &dyn Any;
+
+    /// Updates index with components modified after last refresh.
+    fn refresh(&mut self, archetypes: &[Archetype], epoch: u64);
+
+    /// Removes despawned entity from the index.
+    fn despawn(&mut self, entity: EntityId);
+
+    /// Removes entity from the index if indexed component is removed.
+    fn remove(&mut self, entity: EntityId, id: TypeId);
+}
+
+/// Collection of indices of the `World`.
+#[derive(Debug)]
+pub(crate) struct Indices {
+    map: HashMap<TypeId, RefCell<Box<dyn AnyIndex>>, NoOpHasherBuilder>,
+}
+
+impl Default for Indices {
+    fn default() -> Self {
+        Indices {
+            map: HashMap::with_hasher(NoOpHasherBuilder),
+        }
+    }
+}
+
+impl Indices {
+    /// Adds index unless index of the same type is already added.
+    pub fn add<I>(&mut self, index: I)
+    where
+        I: AnyIndex + 'static,
+    {
+        self.map
+            .entry(TypeId::of::<I>())
+            .or_insert_with(|| RefCell::new(Box::new(index)));
+    }
+
+    /// Returns refreshed index of specified type.
+    pub fn get<I>(&self, archetypes: &[Archetype], epoch: u64) -> Option<Ref<'_, I>>
+    where
+        I: AnyIndex + 'static,
+    {
+        let cell = self.map.get(&TypeId::of::<I>())?;
+        cell.borrow_mut().refresh(archetypes, epoch);
+
+        Some(Ref::map(cell.borrow(), |index| {
+            index.as_any().downcast_ref::<I>().unwrap()
+        }))
+    }
+
+    /// Returns `true` if no indices are added.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline]
+    pub fn despawn(&mut self, entity: EntityId) {
+        for index in self.map.values_mut() {
+            index.get_mut().despawn(entity);
+        }
+    }
+
+    #[inline]
+    pub fn remove(&mut self, entity: EntityId, id: TypeId) {
+        for index in self.map.values_mut() {
+            index.get_mut().remove(entity, id);
+        }
+    }
+}
+
+/// Calls `f` for each component of type `T` reported by `Modified<&T>`
+/// since `tracks` and updates `tracks` to `epoch`.
+pub(crate) fn for_each_modified<T>(
+    archetypes: &[Archetype],
+    tracks: &mut Tracks,
+    epoch: u64,
+    mut f: impl FnMut(EntityId, &T),
+) where
+    T: Component,
+{
+    if tracks.epoch == epoch {
+        return;
+    }
+
+    let iter = QueryTrackedIter::<Modified<&T>, ()>::new(tracks.epoch, epoch, archetypes, ());
+    for (entity, value) in iter {
+        f(entity, value);
+    }
+    tracks.epoch = epoch;
+}
+
+/// Entities with the same indexed value.
+type Entities = SmallVec<[EntityId; 1]>;
+
+/// Map from component values to entities used by [`ValueIndex`].
+pub(crate) trait ValueMap<T>: 'static {
+    fn new() -> Self;
+
+    /// Adds entity to entities with specified value.
+    fn push(&mut self, value: T, entity: EntityId);
+
+    /// Removes entity from entities with specified value.
+    fn unlink(&mut self, value: &T, entity: EntityId);
+}
+
+impl<T> ValueMap<T> for HashMap<T, Entities, MulHasherBuilder>
+where
+    T: Hash + Eq + 'static,
+{
+    fn new() -> Self {
+        HashMap::with_hasher(MulHasherBuilder)
+    }
+
+    fn push(&mut self, value: T, entity: EntityId) {
+        self.entry(value).or_default().push(entity);
+    }
+
+    fn unlink(&mut self, value: &T, entity: EntityId) {
+        if let Some(entities) = self.get_mut(value) {
+            entities.retain(|e| *e != entity);
+            if entities.is_empty() {
+                self.remove(value);
+            }
+        }
+    }
+}
+
+impl<T> ValueMap<T> for BTreeMap<T, Entities>
+where
+    T: Ord + 'static,
+{
+    fn new() -> Self {
+        BTreeMap::new()
+    }
+
+    fn push(&mut self, value: T, entity: EntityId) {
+        self.entry(value).or_default().push(entity);
+    }
+
+    fn unlink(&mut self, value: &T, entity: EntityId) {
+        if let Some(entities) = self.get_mut(value) {
+            entities.retain(|e| *e != entity);
+            if entities.is_empty() {
+                self.remove(value);
+            }
+        }
+    }
+}
+
+/// Index of entities by value of component `T` stored in map `M`.
+pub(crate) struct ValueIndex<M, T> {
+    entities: M,
+    values: HashMap<EntityId, T, MulHasherBuilder>,
+    tracks: Tracks,
+}
+
+/// Index of entities by value of component `T`.
+pub(crate) type Index<T> = ValueIndex<HashMap<T, Entities, MulHasherBuilder>, T>;
+
+/// Index of entities ordered by value of component `T`.
+pub(crate) type OrderedIndex<T> = ValueIndex<BTreeMap<T, Entities>, T>;
+
+impl<M, T> fmt::Debug for ValueIndex<M, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValueIndex")
+            .field("values", &self.values.len())
+            .field("tracks", &self.tracks)
+            .finish()
+    }
+}
+
+impl<M, T> ValueIndex<M, T>
+where
+    M: ValueMap<T>,
+    T: Component + PartialEq + Clone,
+{
+    pub fn new() -> Self {
+        ValueIndex {
+            entities: M::new(),
+            values: HashMap::with_hasher(MulHasherBuilder),
+            tracks: Tracks::new(),
+        }
+    }
+
+    fn insert(&mut self, entity: EntityId, value: &T) {
+        if self.values.get(&entity) == Some(value) {
+            return;
+        }
+
+        self.unlink(entity);
+        self.values.insert(entity, value.clone());
+        self.entities.push(value.clone(), entity);
+    }
+
+    fn unlink(&mut self, entity: EntityId) {
+        if let Some(old) = self.values.remove(&entity) {
+            self.entities.unlink(&old, entity);
+        }
+    }
+}
+
+impl<T> Index<T>
+where
+    T: Component + Hash + Eq + Clone,
+{
+    /// Returns entities with component equal to `value`.
+    pub fn get(&self, value: &T) -> &[EntityId] {
+        match self.entities.get(value) {
+            None => &[],
+            Some(entities) => entities,
+        }
+    }
+}
+
+impl<T> OrderedIndex<T>
+where
+    T: Component + Ord + Clone,
+{
+    /// Returns iterator over entities with component in `range`
+    /// in order of component values.
+    /// Returns empty iterator if `range` is inverted.
+    pub fn range<R>(&self, range: R) -> impl Iterator<Item = EntityId> + '_
+    where
+        R: RangeBounds<T>,
+    {
+        let inverted = match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+            (Bound::Included(start), Bound::Included(end))
+            | (Bound::Included(start), Bound::Excluded(end))
+            | (Bound::Excluded(start), Bound::Included(end)) => start > end,
+            _ => false,
+        };
+
+        // `BTreeMap::range` panics on inverted range.
+        let entries = match inverted {
+            true => None,
+            false => Some(self.entities.range(range)),
+        };
+
+        entries
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, entities)| entities.iter().copied())
+    }
+}
+
+impl<M, T> AnyIndex for ValueIndex<M, T>
+where
+    M: ValueMap<T>,
+    T: Component + PartialEq + Clone,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn refresh(&mut self, archetypes: &[Archetype], epoch: u64) {
+        let mut tracks = mem::take(&mut self.tracks);
+        for_each_modified::<T>(archetypes, &mut tracks, epoch, |entity, value| {
+            self.insert(entity, value)
+        });
+        self.tracks = tracks;
+    }
+
+    fn despawn(&mut self, entity: EntityId) {
+        self.unlink(entity);
+    }
+
+    fn remove(&mut self, entity: EntityId, id: TypeId) {
+        if id == TypeId::of::<T>() {
+            self.unlink(entity);
+        }
+    }
+}
@@ -42,6 +42,8 @@
 //! ### no_std support
 //!
 //! Edict supports `no_std` environment, but requires `alloc`.
+//! Custom allocator set with `World::new_in` is used for storage of components and entities,
+//! global allocator is still required for bookkeeping.
 //! With `"std"` feature error types implement `Error` trait,
 //! apart from that only few internal pieces depend on `"std"` feature.
 //! `"std"` feature is enabled by default and must be turned off for `no_std` environemnt.
@@ -62,18 +64,25 @@
 extern crate alloc;
 extern crate self as edict;
 
+pub mod allocator;
+pub mod archetype;
 pub mod bundle;
 pub mod component;
+pub mod delta;
 pub mod entity;
+pub mod event;
+pub mod prefab;
 pub mod prelude;
 #[cfg(feature = "rc")]
 pub mod proof;
 pub mod query;
+#[cfg(feature = "spatial")]
+pub mod spatial;
 pub mod world;
 
-mod archetype;
 mod hash;
 mod idx;
+mod index;
 mod typeidset;
 
 #[cfg(test)]
@@ -0,0 +1,159 @@
+//! This module implements [`Prefab`] type,
+//! which captures set of components to instantiate entities by cloning.
+
+use core::{
+    alloc::Layout,
+    any::TypeId,
+    fmt,
+    ptr::{self, NonNull},
+};
+
+use smallvec::SmallVec;
+
+use crate::component::ComponentInfo;
+
+/// Template of an entity.
+///
+/// Holds values of cloneable components,
+/// which are cloned into each entity instantiated from the prefab.
+///
+/// Created with [`World::make_prefab`] from [`EntityBuilder`]
+/// or with [`World::prefab_of`] from an existing entity.
+/// Entities are instantiated with [`World::instantiate`],
+/// [`World::instantiate_with`] and [`World::instantiate_batch`].
+///
+/// [`EntityBuilder`]: `edict::bundle::EntityBuilder`
+/// [`World::make_prefab`]: `edict::world::World::make_prefab`
+/// [`World::prefab_of`]: `edict::world::World::prefab_of`
+/// [`World::instantiate`]: `edict::world::World::instantiate`
+/// [`World::instantiate_with`]: `edict::world::World::instantiate_with`
+/// [`World::instantiate_batch`]: `edict::world::World::instantiate_batch`
+pub struct Prefab {
+    ptr: NonNull<u8>,
+    layout: Layout,
+
+    ids: SmallVec<[TypeId; 8]>,
+    infos: SmallVec<[ComponentInfo; 8]>,
+    offsets: SmallVec<[usize; 8]>,
+}
+
+impl fmt::Debug for Prefab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ds = f.debug_struct("Prefab");
+        for info in &self.infos {
+            ds.field("component", &info.debug_name);
+        }
+        ds.finish()
+    }
+}
+
+impl Drop for Prefab {
+    fn drop(&mut self) {
+        for (info, &offset) in self.infos.iter().zip(&self.offsets) {
+            unsafe { (info.drop_one)(self.ptr.as_ptr().add(offset)) }
+        }
+
+        if self.layout.size() != 0 {
+            unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
+
+impl Prefab {
+    /// Creates new prefab from components at specified pointers.
+    /// Components are moved if `take` is true and cloned otherwise.
+    ///
+    /// # Safety
+    ///
+    /// Pointers must reference valid components of types specified by infos.
+    /// If `take` is true, ownership of components is transferred to the prefab.
+    /// Otherwise all infos must have `clone_one` function.
+    pub(crate) unsafe fn new<'a>(
+        components: impl Iterator<Item = (&'a ComponentInfo, NonNull<u8>)> + Clone,
+        take: bool,
+    ) -> Self {
+        let mut layout = Layout::new::<[u8; 0]>();
+        let mut offsets = SmallVec::new();
+
+        for (info, _) in components.clone() {
+            let (extended, offset) = layout.extend(info.layout).expect("Prefab overflow");
+            layout = extended;
+            offsets.push(offset);
+        }
+
+        let layout = layout.pad_to_align();
+
+        let ptr = if layout.size() == 0 {
+            NonNull::new_unchecked(layout.align() as *mut u8)
+        } else {
+            NonNull::new(alloc::alloc::alloc(layout)).unwrap()
+        };
+
+        let mut ids = SmallVec::new();
+        let mut infos = SmallVec::new();
+
+        for ((info, src), &offset) in components.zip(&offsets) {
+            let dst = ptr.as_ptr().add(offset);
+            if take {
+                ptr::copy_nonoverlapping(src.as_ptr(), dst, info.layout.size());
+            } else {
+                let clone_one = info.clone_one.expect("Component must be cloneable");
+                clone_one(src.as_ptr(), dst);
+            }
+
+            ids.push(info.id);
+            infos.push(*info);
+        }
+
+        Prefab {
+            ptr,
+            layout,
+            ids,
+            infos,
+            offsets,
+        }
+    }
+
+    /// Returns reference to component from prefab.
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: 'static,
+    {
+        let idx = self.ids.iter().position(|id| *id == TypeId::of::<T>())?;
+        let offset = self.offsets[idx];
+        Some(unsafe { &*self.ptr.as_ptr().add(offset).cast::<T>() })
+    }
+
+    /// Returns iterator over component types in this prefab.
+    pub fn component_types(&self) -> impl Iterator<Item = &ComponentInfo> {
+        self.infos.iter()
+    }
+
+    /// Returns true if prefab has specified type id.
+    pub fn contains_id(&self, id: TypeId) -> bool {
+        self.ids.contains(&id)
+    }
+
+    /// Returns ids of component types in this prefab.
+    pub(crate) fn ids(&self) -> &[TypeId] {
+        &self.ids
+    }
+
+    /// Returns infos of component types in this prefab.
+    pub(crate) fn infos(&self) -> &[ComponentInfo] {
+        &self.infos
+    }
+
+    /// Returns iterator over components in this prefab
+    /// with pointers to their values.
+    pub(crate) fn components(&self) -> impl Iterator<Item = (&ComponentInfo, NonNull<u8>)> + '_ {
+        self.infos
+            .iter()
+            .zip(&self.offsets)
+            .map(move |(info, &offset)| {
+                (info, unsafe {
+                    NonNull::new_unchecked(self.ptr.as_ptr().add(offset))
+                })
+            })
+    }
+}
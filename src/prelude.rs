@@ -2,13 +2,13 @@
 
 pub use crate::{
     bundle::{Bundle, DynamicBundle, EntityBuilder},
-    component::Component,
+    component::{Component, SharedComponent},
     entity::EntityId,
     query::{
         Alt, ImmutableQuery, Modified, NonTrackingQuery, Query, QueryItem, QueryIter,
-        QueryTrackedIter,
+        QueryTrackedIter, Shared,
     },
-    world::{EntityError, MissingComponents, NoSuchEntity, Tracks, World},
+    world::{EntityError, EntityRef, MissingComponents, NoSuchEntity, Tracks, World},
 };
 
 #[cfg(feature = "rc")]
@@ -0,0 +1,152 @@
+//! Compile-time check that query does not alias mutable access to a component.
+//!
+//! Each query in a tuple is mapped to [`ReadAccess`] or [`WriteAccess`] marker
+//! of the component it accesses, or to [`NoAccess`] if it accesses no component,
+//! and markers are collected into a list.
+//! For every [`WriteAccess`] marker position of its component in the list is inferred.
+//! If component occurs in the list more than once, position is ambiguous
+//! and query does not satisfy [`NoAlias`] bound.
+
+use core::marker::PhantomData;
+
+#[cfg(feature = "rc")]
+use crate::proof::Skip;
+
+use super::{Alt, Modified, Shared};
+
+/// Marker of shared access to component `T`.
+#[allow(missing_debug_implementations)]
+pub struct ReadAccess<T>(PhantomData<fn() -> T>);
+
+/// Marker of mutable access to component `T`.
+#[allow(missing_debug_implementations)]
+pub struct WriteAccess<T>(PhantomData<fn() -> T>);
+
+/// Marker of query that accesses no component.
+#[derive(Clone, Copy, Debug)]
+pub enum NoAccess {}
+
+/// Position of the marker at the head of access list.
+#[derive(Clone, Copy, Debug)]
+pub enum Here {}
+
+/// Position of the marker in the tail of access list.
+#[allow(missing_debug_implementations)]
+pub struct There<I>(PhantomData<fn() -> I>);
+
+/// Query with statically known access to at most one component.
+///
+/// Implemented for `&T`, `&mut T`, `Alt<T>`,
+/// `Option` and `Modified` wrappers of them,
+/// and for `Shared<T>`, `Skip` and `()` that access no component.
+pub trait QueryAccess {
+    /// [`ReadAccess`] or [`WriteAccess`] marker of the component
+    /// or [`NoAccess`].
+    type Access;
+}
+
+impl QueryAccess for () {
+    type Access = NoAccess;
+}
+
+impl<T> QueryAccess for Shared<T> {
+    type Access = NoAccess;
+}
+
+#[cfg(feature = "rc")]
+impl QueryAccess for Skip {
+    type Access = NoAccess;
+}
+
+impl<T> QueryAccess for &T {
+    type Access = ReadAccess<T>;
+}
+
+impl<T> QueryAccess for &mut T {
+    type Access = WriteAccess<T>;
+}
+
+impl<T> QueryAccess for Alt<T> {
+    type Access = WriteAccess<T>;
+}
+
+impl<Q> QueryAccess for Option<Q>
+where
+    Q: QueryAccess,
+{
+    type Access = Q::Access;
+}
+
+impl<Q> QueryAccess for Modified<Q>
+where
+    Q: QueryAccess,
+{
+    type Access = Q::Access;
+}
+
+/// List of access markers that contains access to component `T` at position `I`.
+pub trait FindAccess<T, I> {}
+
+impl<T, L> FindAccess<T, Here> for (ReadAccess<T>, L) {}
+impl<T, L> FindAccess<T, Here> for (WriteAccess<T>, L) {}
+
+impl<T, H, L, I> FindAccess<T, There<I>> for (H, L) where L: FindAccess<T, I> {}
+
+/// Access marker that does not alias other accesses in list `L`.
+///
+/// Shared access and no access never alias.
+/// Mutable access does not alias if its component occurs in the list once,
+/// at position `I`.
+pub trait CheckAccess<L, I> {}
+
+impl<L> CheckAccess<L, Here> for NoAccess {}
+
+impl<T, L> CheckAccess<L, Here> for ReadAccess<T> {}
+
+impl<T, L, I> CheckAccess<L, I> for WriteAccess<T> where L: FindAccess<T, I> {}
+
+/// Query that does not access a component mutably
+/// along with other access to it.
+///
+/// Implemented for queries listed in [`QueryAccess`]
+/// and tuples of them that do not alias.
+/// `I` is inferred and should be left to the compiler.
+///
+/// ```compile_fail,E0283
+/// # use edict::query::{Alt, Modified, NoAlias};
+/// fn check<Q: NoAlias<I>, I>() {}
+/// check::<(Alt<u32>, Modified<&u32>), _>();
+/// ```
+pub trait NoAlias<I> {}
+
+impl<Q> NoAlias<Here> for Q where Q: QueryAccess {}
+
+macro_rules! access_list {
+    () => { () };
+    ($head:ident $($tail:ident)*) => {
+        (<$head as QueryAccess>::Access, access_list!($($tail)*))
+    };
+}
+
+macro_rules! for_tuple {
+    () => {
+        for_tuple!(for A IA, B IB, C IC, D ID, E IE, F IF, G IG, H IH, I II, J IJ, K IK, L IL, M IM, N IN, O IO, P IP);
+    };
+
+    (for) => {};
+
+    (for $a:ident $i:ident $(, $tail_a:ident $tail_i:ident)*) => {
+        for_tuple!(for $($tail_a $tail_i),*);
+        for_tuple!(impl [access_list!($a $($tail_a)*)] $a $i $(, $tail_a $tail_i)*);
+    };
+
+    (impl [$list:ty] $($a:ident $i:ident),+) => {
+        impl<$($a,)+ $($i,)+> NoAlias<($($i,)+)> for ($($a,)+)
+        where
+            $($a: QueryAccess,)+
+            $(<$a as QueryAccess>::Access: CheckAccess<$list, $i>,)+
+        {}
+    };
+}
+
+for_tuple!();
@@ -7,15 +7,20 @@
 //! [`Query`] trait has a lot of implementations and is composable using tuples.
 
 pub use self::{
+    alias::{
+        CheckAccess, FindAccess, Here, NoAccess, NoAlias, QueryAccess, ReadAccess, There,
+        WriteAccess,
+    },
     alt::{Alt, FetchAlt},
     filter::{Filter, With, Without},
     modified::{Modified, ModifiedFetchAlt, ModifiedFetchRead, ModifiedFetchWrite},
     read::FetchRead,
+    shared::{FetchShared, Shared},
     write::FetchWrite,
 };
 
 use core::{
-    any::TypeId,
+    any::{type_name, TypeId},
     marker::PhantomData,
     ops::Range,
     ptr::{self},
@@ -23,15 +28,17 @@ use core::{
 };
 
 use crate::{
-    archetype::{chunk_idx, first_of_chunk, Archetype, CHUNK_LEN_USIZE},
+    archetype::{chunk_idx, first_of_chunk, Archetype, CHUNK_LEN},
     entity::EntityId,
 };
 
+mod alias;
 mod alt;
 mod filter;
 mod modified;
 mod option;
 mod read;
+mod shared;
 
 #[cfg(feature = "rc")]
 mod skip;
@@ -110,6 +117,19 @@ where
     }
 }
 
+/// Panics if query `Q` causes mutable reference aliasing.
+#[inline]
+pub(crate) fn assert_valid<Q>()
+where
+    Q: Query,
+{
+    assert!(
+        Q::is_valid(),
+        "Query `{}` accesses a component mutably along with other access to it",
+        type_name::<Q>()
+    );
+}
+
 /// Trait for types that can query sets of components from entities in the world.
 /// Queries implement efficient iteration over entities while yielding
 /// sets of references to the components and optionally `EntityId` to address same components later.
@@ -134,6 +154,11 @@ pub unsafe trait Query {
     }
 
     /// Function to validate that query does not cause mutable reference aliasing.
+    ///
+    /// Mutable queries of `World` require [`NoAlias`] and are checked at compile time.
+    /// This check is still performed at runtime when query is created,
+    /// to catch generic component types that turn out to be the same type,
+    /// and is the only check for queries used with `World::query_mut_dynamic`.
     fn is_valid() -> bool;
 
     /// Returns what kind of access the query performs on the component type.
@@ -559,7 +584,8 @@ where
                 Some(idx) => {
                     if let Some(chunk_idx) = first_of_chunk(idx) {
                         if unsafe { self.fetch.skip_chunk(chunk_idx) } {
-                            self.indices.nth(CHUNK_LEN_USIZE - 1);
+                            // Skip the rest of the chunk.
+                            self.indices.nth(CHUNK_LEN - 2);
                             continue;
                         }
                         self.visit_chunk = Q::mutates();
@@ -590,7 +616,8 @@ where
         while let Some(idx) = self.indices.next() {
             if let Some(chunk_idx) = first_of_chunk(idx) {
                 if unsafe { self.fetch.skip_chunk(chunk_idx) } {
-                    self.indices.nth(CHUNK_LEN_USIZE - 1);
+                    // Skip the rest of the chunk.
+                    self.indices.nth(CHUNK_LEN - 2);
                     continue;
                 }
                 self.visit_chunk = Q::mutates();
@@ -615,14 +642,15 @@ where
             {
                 continue;
             }
-            if let Some(mut fetch) = unsafe { Q::fetch(archetype, 0, self.epoch) } {
+            if let Some(mut fetch) = unsafe { Q::fetch(archetype, self.tracks, self.epoch) } {
                 let entities = archetype.entities().as_ptr();
                 let mut indices = 0..archetype.len();
 
                 while let Some(idx) = indices.next() {
                     if let Some(chunk_idx) = first_of_chunk(idx) {
                         if unsafe { fetch.skip_chunk(chunk_idx) } {
-                            self.indices.nth(CHUNK_LEN_USIZE - 1);
+                            // Skip the rest of the chunk.
+                            indices.nth(CHUNK_LEN - 2);
                             continue;
                         }
                         self.visit_chunk = Q::mutates();
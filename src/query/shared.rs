@@ -0,0 +1,84 @@
+use core::{any::TypeId, marker::PhantomData, ptr::NonNull};
+
+use crate::{archetype::Archetype, component::SharedComponent};
+
+use super::{Access, Fetch, ImmutableQuery, NonTrackingQuery, Query};
+
+/// Query that reads value of shared component.
+///
+/// Value is fetched once per archetype and yielded for every entity in it.
+/// Archetypes without shared component of type `T` are skipped.
+#[derive(Debug)]
+pub struct Shared<T> {
+    marker: PhantomData<fn() -> T>,
+}
+
+/// `Fetch` type for the `Shared<T>` query.
+#[allow(missing_debug_implementations)]
+pub struct FetchShared<T> {
+    ptr: NonNull<T>,
+}
+
+impl<'a, T> Fetch<'a> for FetchShared<T>
+where
+    T: SharedComponent,
+{
+    type Item = &'a T;
+
+    #[inline]
+    fn dangling() -> Self {
+        FetchShared {
+            ptr: NonNull::dangling(),
+        }
+    }
+
+    #[inline]
+    unsafe fn get_item(&mut self, _idx: usize) -> &'a T {
+        &*self.ptr.as_ptr()
+    }
+}
+
+unsafe impl<T> Query for Shared<T>
+where
+    T: SharedComponent,
+{
+    type Fetch = FetchShared<T>;
+
+    #[inline]
+    fn mutates() -> bool {
+        false
+    }
+
+    #[inline]
+    fn access(_ty: TypeId) -> Access {
+        // Shared values are not stored in component columns.
+        Access::None
+    }
+
+    #[inline]
+    fn allowed_with<Q: Query>() -> bool {
+        true
+    }
+
+    #[inline]
+    fn is_valid() -> bool {
+        true
+    }
+
+    #[inline]
+    fn skip_archetype(archetype: &Archetype, _: u64) -> bool {
+        archetype.shared::<T>().is_none()
+    }
+
+    #[inline]
+    unsafe fn fetch(archetype: &Archetype, _tracks: u64, _epoch: u64) -> Option<FetchShared<T>> {
+        let value = archetype.shared::<T>()?;
+
+        Some(FetchShared {
+            ptr: NonNull::from(value),
+        })
+    }
+}
+
+unsafe impl<T> ImmutableQuery for Shared<T> where T: SharedComponent {}
+unsafe impl<T> NonTrackingQuery for Shared<T> where T: SharedComponent {}
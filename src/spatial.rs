@@ -0,0 +1,241 @@
+//! This module implements spatial index over position components.
+//!
+//! Component type that implements [`Position`] can be indexed
+//! with [`World::add_spatial_index`].
+//! Entities are bucketed into uniform grid of cubic cells.
+//! Grid is updated lazily, only entities reported by `Modified<&P>` query
+//! since last update are moved between cells.
+//!
+//! [`World::add_spatial_index`]: `edict::world::World::add_spatial_index`
+
+use core::{
+    any::{Any, TypeId},
+    fmt,
+    marker::PhantomData,
+    mem,
+};
+
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+use crate::{
+    archetype::Archetype,
+    component::Component,
+    entity::EntityId,
+    hash::MulHasherBuilder,
+    index::{for_each_modified, AnyIndex},
+    world::Tracks,
+};
+
+/// Trait for components that hold position of an entity.
+///
+/// Two dimensional positions can use `0.0` for the third coordinate.
+pub trait Position: Component {
+    /// Returns position as `[x, y, z]`.
+    fn position(&self) -> [f32; 3];
+}
+
+/// Number of bits of cell coordinate per axis packed into cell key.
+const CELL_BITS: u32 = 21;
+const CELL_MASK: u64 = (1 << CELL_BITS) - 1;
+
+/// Returns coordinate of the cell that contains `x`.
+/// Coordinates beyond `i32` range are saturated.
+#[inline]
+fn cell_coord(x: f32, cell_size: f32) -> i32 {
+    let c = x / cell_size;
+    let i = c as i32;
+    if i as f32 > c {
+        i.saturating_sub(1)
+    } else {
+        i
+    }
+}
+
+/// Packs cell coordinates into single key.
+/// Distant cells may share a key, they are told apart by positions.
+#[inline]
+fn cell_key(cell: [i32; 3]) -> u64 {
+    ((cell[0] as u64 & CELL_MASK) << (CELL_BITS * 2))
+        | ((cell[1] as u64 & CELL_MASK) << CELL_BITS)
+        | (cell[2] as u64 & CELL_MASK)
+}
+
+/// Uniform grid index of entities by position component `P`.
+pub(crate) struct SpatialGrid<P> {
+    cell_size: f32,
+    cells: HashMap<u64, Vec<(EntityId, [f32; 3])>, MulHasherBuilder>,
+    keys: HashMap<EntityId, u64, MulHasherBuilder>,
+    tracks: Tracks,
+    marker: PhantomData<fn() -> P>,
+}
+
+impl<P> fmt::Debug for SpatialGrid<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpatialGrid")
+            .field("cell_size", &self.cell_size)
+            .field("cells", &self.cells.len())
+            .field("entities", &self.keys.len())
+            .field("tracks", &self.tracks)
+            .finish()
+    }
+}
+
+impl<P> SpatialGrid<P>
+where
+    P: Position,
+{
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "Cell size must be positive");
+
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::with_hasher(MulHasherBuilder),
+            keys: HashMap::with_hasher(MulHasherBuilder),
+            tracks: Tracks::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns entities with position inside box between `min` and `max` inclusive.
+    pub fn aabb(&self, min: [f32; 3], max: [f32; 3]) -> Vec<EntityId> {
+        let mut result = Vec::new();
+        self.for_each_in(min, max, |entity, _| result.push(entity));
+        result
+    }
+
+    /// Returns entities with position within `radius` from `center`.
+    pub fn radius(&self, center: [f32; 3], radius: f32) -> Vec<EntityId> {
+        let min = [center[0] - radius, center[1] - radius, center[2] - radius];
+        let max = [center[0] + radius, center[1] + radius, center[2] + radius];
+        let radius_sq = radius * radius;
+
+        let mut result = Vec::new();
+        self.for_each_in(min, max, |entity, pos| {
+            let dx = pos[0] - center[0];
+            let dy = pos[1] - center[1];
+            let dz = pos[2] - center[2];
+            if dx * dx + dy * dy + dz * dz <= radius_sq {
+                result.push(entity);
+            }
+        });
+        result
+    }
+
+    /// Calls `f` for each entity with position inside box between `min` and `max`.
+    fn for_each_in(&self, min: [f32; 3], max: [f32; 3], mut f: impl FnMut(EntityId, [f32; 3])) {
+        let inside = |pos: &[f32; 3]| (0..3).all(|i| min[i] <= pos[i] && pos[i] <= max[i]);
+
+        let lo = [
+            cell_coord(min[0], self.cell_size),
+            cell_coord(min[1], self.cell_size),
+            cell_coord(min[2], self.cell_size),
+        ];
+        let hi = [
+            cell_coord(max[0], self.cell_size),
+            cell_coord(max[1], self.cell_size),
+            cell_coord(max[2], self.cell_size),
+        ];
+
+        let finite = min.iter().chain(&max).all(|v| v.is_finite());
+
+        let count = (0..3).try_fold(1u64, |acc, i| {
+            let len = (hi[i] as i64 - lo[i] as i64 + 1).max(0) as u64;
+            acc.checked_mul(len)
+        });
+
+        match count {
+            Some(count) if finite && count <= self.cells.len() as u64 => {
+                for x in lo[0]..=hi[0] {
+                    for y in lo[1]..=hi[1] {
+                        for z in lo[2]..=hi[2] {
+                            if let Some(cell) = self.cells.get(&cell_key([x, y, z])) {
+                                for (entity, pos) in cell {
+                                    if inside(pos) {
+                                        f(*entity, *pos);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {
+                // Box covers more cells than there are occupied
+                // or is unbounded.
+                for cell in self.cells.values() {
+                    for (entity, pos) in cell {
+                        if inside(pos) {
+                            f(*entity, *pos);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, entity: EntityId, pos: [f32; 3]) {
+        let key = cell_key([
+            cell_coord(pos[0], self.cell_size),
+            cell_coord(pos[1], self.cell_size),
+            cell_coord(pos[2], self.cell_size),
+        ]);
+
+        match self.keys.insert(entity, key) {
+            Some(old) if old == key => {
+                let cell = self.cells.get_mut(&key).unwrap();
+                let item = cell.iter_mut().find(|(e, _)| *e == entity).unwrap();
+                item.1 = pos;
+                return;
+            }
+            Some(old) => self.unlink_from(entity, old),
+            None => {}
+        }
+
+        self.cells.entry(key).or_default().push((entity, pos));
+    }
+
+    fn unlink(&mut self, entity: EntityId) {
+        if let Some(key) = self.keys.remove(&entity) {
+            self.unlink_from(entity, key);
+        }
+    }
+
+    fn unlink_from(&mut self, entity: EntityId, key: u64) {
+        if let Some(cell) = self.cells.get_mut(&key) {
+            if let Some(idx) = cell.iter().position(|(e, _)| *e == entity) {
+                cell.swap_remove(idx);
+            }
+            if cell.is_empty() {
+                self.cells.remove(&key);
+            }
+        }
+    }
+}
+
+impl<P> AnyIndex for SpatialGrid<P>
+where
+    P: Position,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn refresh(&mut self, archetypes: &[Archetype], epoch: u64) {
+        let mut tracks = mem::take(&mut self.tracks);
+        for_each_modified::<P>(archetypes, &mut tracks, epoch, |entity, pos| {
+            self.insert(entity, pos.position())
+        });
+        self.tracks = tracks;
+    }
+
+    fn despawn(&mut self, entity: EntityId) {
+        self.unlink(entity);
+    }
+
+    fn remove(&mut self, entity: EntityId, id: TypeId) {
+        if id == TypeId::of::<P>() {
+            self.unlink(entity);
+        }
+    }
+}
@@ -1,9 +1,16 @@
 use crate::{
-    query::Modified,
-    world::{EntityError, World},
+    archetype::CHUNK_LEN,
+    bundle::EntityBuilder,
+    entity::EntityId,
+    query::{Modified, Shared},
+    world::{
+        CapacityExceeded, CloneError, EntityError, InsertError, NoSuchEntity, QueryConflict,
+        RemoveError, TransferError, World, WorldConfig,
+    },
 };
 
-use alloc::{vec, vec::Vec};
+use alloc::{format, string::String, vec, vec::Vec};
+use core::any::{type_name, TypeId};
 
 /// Tests that entity spawned into world has all components from bundle.
 #[test]
@@ -13,7 +20,10 @@ fn world_spawn() {
     let e = world.spawn((42u32, "qwe"));
     assert_eq!(world.has_component::<u32>(&e), Ok(true));
     assert_eq!(world.has_component::<&str>(&e), Ok(true));
-    assert_eq!(world.query_one_mut::<(&u32, &&str)>(&e), Ok((&42, &"qwe")));
+    assert_eq!(
+        world.query_one_mut::<(&u32, &&str), _>(&e),
+        Ok((&42, &"qwe"))
+    );
 }
 
 /// Tests that entity does not have a component that wasn't in spawn bundle
@@ -26,13 +36,29 @@ fn world_insert() {
     assert_eq!(world.has_component::<u32>(&e), Ok(true));
     assert_eq!(world.has_component::<&str>(&e), Ok(false));
     assert_eq!(
-        world.query_one_mut::<(&u32, &&str)>(&e),
+        world.query_one_mut::<(&u32, &&str), _>(&e),
         Err(EntityError::MissingComponents)
     );
 
     assert_eq!(world.try_insert(&e, "qwe"), Ok(()));
     assert_eq!(world.has_component::<&str>(&e), Ok(true));
-    assert_eq!(world.query_one_mut::<(&u32, &&str)>(&e), Ok((&42, &"qwe")));
+    assert_eq!(
+        world.query_one_mut::<(&u32, &&str), _>(&e),
+        Ok((&42, &"qwe"))
+    );
+}
+
+/// Tests that components are looked up on entity spawned without components.
+#[test]
+fn world_empty_entity() {
+    let mut world = World::new();
+
+    let e = world.spawn(());
+    assert_eq!(world.has_component::<u32>(&e), Ok(false));
+    assert_eq!(
+        world.query_one::<&u32>(&e),
+        Err(EntityError::MissingComponents)
+    );
 }
 
 /// Tests that entity does not have a component that was removed.
@@ -43,12 +69,15 @@ fn world_remove() {
     let e = world.spawn((42u32, "qwe"));
     assert_eq!(world.has_component::<u32>(&e), Ok(true));
     assert_eq!(world.has_component::<&str>(&e), Ok(true));
-    assert_eq!(world.query_one_mut::<(&u32, &&str)>(&e), Ok((&42, &"qwe")));
+    assert_eq!(
+        world.query_one_mut::<(&u32, &&str), _>(&e),
+        Ok((&42, &"qwe"))
+    );
 
     assert_eq!(world.remove::<&str>(&e), Ok("qwe"));
     assert_eq!(world.has_component::<&str>(&e), Ok(false));
     assert_eq!(
-        world.query_one_mut::<(&u32, &&str)>(&e),
+        world.query_one_mut::<(&u32, &&str), _>(&e),
         Err(EntityError::MissingComponents)
     );
 }
@@ -62,7 +91,7 @@ fn world_insert_bundle() {
     assert_eq!(world.has_component::<u32>(&e), Ok(true));
     assert_eq!(world.has_component::<&str>(&e), Ok(false));
     assert_eq!(
-        world.query_one_mut::<(&u32, &&str)>(&e),
+        world.query_one_mut::<(&u32, &&str), _>(&e),
         Err(EntityError::MissingComponents)
     );
 
@@ -70,7 +99,7 @@ fn world_insert_bundle() {
     assert_eq!(world.has_component::<&str>(&e), Ok(true));
     assert_eq!(world.has_component::<bool>(&e), Ok(true));
     assert_eq!(
-        world.query_one_mut::<(&u32, &&str, &bool)>(&e),
+        world.query_one_mut::<(&u32, &&str, &bool), _>(&e),
         Ok((&42, &"qwe", &true))
     );
 }
@@ -83,13 +112,16 @@ fn world_remove_bundle() {
     let e = world.spawn((42u32, "qwe"));
     assert_eq!(world.has_component::<u32>(&e), Ok(true));
     assert_eq!(world.has_component::<&str>(&e), Ok(true));
-    assert_eq!(world.query_one_mut::<(&u32, &&str)>(&e), Ok((&42, &"qwe")));
+    assert_eq!(
+        world.query_one_mut::<(&u32, &&str), _>(&e),
+        Ok((&42, &"qwe"))
+    );
 
     // When removing a bundle, any missing component is simply ignored.
     assert_eq!(world.remove_bundle::<(&str, bool)>(&e), Ok(()));
     assert_eq!(world.has_component::<&str>(&e), Ok(false));
     assert_eq!(
-        world.query_one_mut::<(&u32, &&str)>(&e),
+        world.query_one_mut::<(&u32, &&str), _>(&e),
         Err(EntityError::MissingComponents)
     );
 }
@@ -117,7 +149,7 @@ fn version_test() {
         vec![]
     );
 
-    *world.query_one_mut::<&mut u32>(&e).unwrap() = 42;
+    *world.query_one_mut::<&mut u32, _>(&e).unwrap() = 42;
 
     assert_eq!(
         world
@@ -152,7 +184,7 @@ fn version_despawn_test() {
         vec![]
     );
 
-    *world.query_one_mut::<&mut u32>(&e2).unwrap() = 50;
+    *world.query_one_mut::<&mut u32, _>(&e2).unwrap() = 50;
     assert_eq!(world.despawn(&e1), Ok(()));
 
     assert_eq!(
@@ -188,8 +220,8 @@ fn version_insert_test() {
         vec![]
     );
 
-    *world.query_one_mut::<&mut u32>(&e1).unwrap() = 50;
-    *world.query_one_mut::<&mut u32>(&e2).unwrap() = 100;
+    *world.query_one_mut::<&mut u32, _>(&e1).unwrap() = 50;
+    *world.query_one_mut::<&mut u32, _>(&e2).unwrap() = 100;
 
     assert_eq!(world.try_insert(&e1, true), Ok(()));
 
@@ -201,3 +233,1637 @@ fn version_insert_test() {
         vec![(e2, &100), (e1, &50)]
     );
 }
+
+/// Tests that entity components can be inspected without knowing their types.
+#[test]
+fn world_entity_inspect() {
+    let mut world = World::new();
+
+    let e = world.spawn((42u32, "qwe"));
+
+    let mut ids = world
+        .entity_components(&e)
+        .unwrap()
+        .map(|info| info.id)
+        .collect::<Vec<_>>();
+    ids.sort();
+
+    let mut expected = vec![TypeId::of::<u32>(), TypeId::of::<&str>()];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let entity = world.entity(&e).unwrap();
+    assert_eq!(entity.id(), e);
+    assert!(entity.contains::<u32>());
+    assert!(!entity.contains::<bool>());
+    assert_eq!(entity.get::<u32>(), Some(&42));
+    assert_eq!(entity.get::<bool>(), None);
+
+    let ptr = world.component_ptr(&e, TypeId::of::<&str>()).unwrap();
+    assert_eq!(unsafe { *ptr.cast::<&str>().as_ref() }, "qwe");
+    assert_eq!(
+        world.component_ptr(&e, TypeId::of::<bool>()),
+        Err(EntityError::MissingComponents)
+    );
+
+    assert_eq!(world.despawn(&e), Ok(()));
+    assert!(world.entity(&e).is_err());
+}
+
+/// Tests that registered components are printed with their values.
+#[test]
+fn world_debug_entity() {
+    let mut world = World::new();
+
+    let e = world.spawn((42u32, false));
+    world.register_debug::<u32>();
+
+    let text = format!("{:?}", world.debug_entity(&e).unwrap());
+    assert!(text.contains("u32: 42"));
+    assert!(text.contains("bool: .."));
+
+    world.register_debug::<bool>();
+    let text = format!("{:?}", world.dump());
+    assert!(text.contains("u32: 42"));
+    assert!(text.contains("bool: false"));
+
+    // Registration applies to archetypes created later.
+    let e = world.spawn((true,));
+    let text = format!("{:?}", world.debug_entity(&e).unwrap());
+    assert!(text.contains("bool: true"));
+}
+
+/// Tests that entities and worlds with registered cloneable components can be cloned.
+#[test]
+fn world_clone() {
+    let mut world = World::new();
+
+    let e = world.spawn((42u32, "qwe"));
+    world.register_clone::<u32>();
+
+    assert_eq!(
+        world.clone_entity(&e),
+        Err(CloneError::NotCloneable {
+            debug_name: type_name::<&str>()
+        })
+    );
+    assert!(world.try_clone().is_err());
+
+    world.register_clone::<&str>();
+
+    let c = world.clone_entity(&e).unwrap();
+    assert_ne!(c, e);
+    assert_eq!(world.query_one::<(&u32, &&str)>(&c), Ok((&42, &"qwe")));
+
+    let mut fork = world.try_clone().unwrap();
+    *fork.query_one_mut::<&mut u32, _>(&e).unwrap() = 11;
+
+    assert_eq!(world.query_one::<&u32>(&e), Ok(&42));
+    assert_eq!(fork.query_one::<&u32>(&e), Ok(&11));
+    assert_eq!(fork.query_one::<&u32>(&c), Ok(&42));
+    assert_eq!(fork.query::<&u32>().into_iter().count(), 2);
+
+    assert_eq!(fork.despawn(&c), Ok(()));
+    assert!(world.is_alive(&c));
+}
+
+/// Tests that panic in component clone leaves neither entity nor cloned components behind.
+#[cfg(feature = "std")]
+#[test]
+fn world_clone_panic() {
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicIsize, Ordering};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    /// Counts live values.
+    struct Counted(Arc<AtomicIsize>);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Counted(self.0.clone())
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    struct Poison;
+
+    impl Clone for Poison {
+        fn clone(&self) -> Self {
+            panic!("Poison cannot be cloned")
+        }
+    }
+
+    let live = Arc::new(AtomicIsize::new(1));
+
+    let mut world = World::new();
+    let e = world.spawn((42u32, Counted(live.clone()), Poison));
+    world.register_clone::<u32>();
+    world.register_clone::<Counted>();
+    world.register_clone::<Poison>();
+
+    let result = catch_unwind(AssertUnwindSafe(|| world.clone_entity(&e)));
+    assert!(result.is_err());
+
+    assert_eq!(live.load(Ordering::Relaxed), 1);
+    assert_eq!(world.query::<&u32>().into_iter().count(), 1);
+    // No id was allocated for the clone.
+    assert_eq!(world.spawn(()).idx, 1);
+}
+
+/// Tests that entities are instantiated from prefab with clones of its components.
+#[test]
+fn world_prefab() {
+    let mut world = World::new();
+
+    let mut builder = EntityBuilder::new();
+    builder.add(42u32);
+    builder.add(String::from("qwe"));
+
+    assert!(world.make_prefab(builder).is_err());
+
+    world.register_clone::<u32>();
+    world.register_clone::<String>();
+    world.register_clone::<bool>();
+
+    let mut builder = EntityBuilder::new();
+    builder.add(42u32);
+    builder.add(String::from("qwe"));
+    let prefab = world.make_prefab(builder).unwrap();
+
+    let e = world.instantiate(&prefab);
+    assert_eq!(
+        world.query_one::<(&u32, &String)>(&e),
+        Ok((&42, &String::from("qwe")))
+    );
+
+    let batch = world.instantiate_batch(&prefab, 3);
+    assert_eq!(batch.len(), 3);
+    for e in &batch {
+        assert_eq!(world.query_one::<&u32>(e), Ok(&42));
+    }
+
+    let e = world.instantiate_with(&prefab, (11u32, true));
+    assert_eq!(
+        world.query_one::<(&u32, &String, &bool)>(&e),
+        Ok((&11, &String::from("qwe"), &true))
+    );
+
+    let prefab = world.prefab_of(&e).unwrap();
+    assert_eq!(prefab.get::<bool>(), Some(&true));
+    drop(world);
+
+    let mut world = World::new();
+    let e = world.instantiate(&prefab);
+    assert_eq!(world.query_one::<&u32>(&e), Ok(&11));
+}
+
+/// Tests that entities are moved between worlds with new ids.
+#[test]
+fn world_transfer() {
+    let mut staging = World::new();
+    let a = staging.spawn((1u32, String::from("a")));
+    let b = staging.spawn((2u32,));
+    let c = staging.spawn((3u32, String::from("c")));
+
+    let mut world = World::new();
+    world.spawn((0u32,));
+
+    let map = world.transfer(&mut staging, vec![c, a]).unwrap();
+    assert_eq!(map.len(), 2);
+    assert!(!staging.is_alive(&a));
+    assert!(!staging.is_alive(&c));
+    assert_eq!(staging.query_one::<&u32>(&b), Ok(&2));
+
+    assert_eq!(
+        world.query_one::<(&u32, &String)>(&map.get(&c).unwrap()),
+        Ok((&3, &String::from("c")))
+    );
+    assert_eq!(
+        world.query_one::<(&u32, &String)>(&map.get(&a).unwrap()),
+        Ok((&1, &String::from("a")))
+    );
+
+    let d = staging.spawn((4u32, String::from("d")));
+    let map = world.merge(&mut staging).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(staging.query::<&u32>().into_iter().count(), 0);
+    assert_eq!(world.query::<&u32>().into_iter().count(), 5);
+
+    assert_eq!(
+        world.query_one::<&String>(&map.get(&d).unwrap()),
+        Ok(&String::from("d"))
+    );
+}
+
+/// Tests that entity ids in registered components are remapped.
+#[test]
+fn world_map_entities() {
+    use crate::entity::{EntityId, EntityMap, MapEntities};
+
+    struct Parent(EntityId);
+
+    impl MapEntities for Parent {
+        fn map_entities(&mut self, map: &EntityMap) {
+            self.0.map_entities(map);
+        }
+    }
+
+    let mut staging = World::new();
+    staging.register_map_entities::<Parent>();
+
+    let outside = staging.spawn((0u32,));
+    let parent = staging.spawn((1u32,));
+    let child = staging.spawn((2u32, Parent(parent)));
+    let orphan = staging.spawn((3u32, Parent(outside)));
+
+    let mut world = World::new();
+    let map = world
+        .transfer(&mut staging, vec![parent, child, orphan])
+        .unwrap();
+
+    let new_parent = map.get(&parent).unwrap();
+    let new_child = map.get(&child).unwrap();
+    let new_orphan = map.get(&orphan).unwrap();
+
+    assert_eq!(
+        world.query_one::<&Parent>(&new_child).map(|p| p.0),
+        Ok(new_parent)
+    );
+    assert_eq!(
+        world.query_one::<&Parent>(&new_orphan).map(|p| p.0),
+        Ok(outside)
+    );
+
+    let mut map = EntityMap::new();
+    map.insert(outside, new_parent);
+    world.map_entities(&map);
+    assert_eq!(
+        world.query_one::<&Parent>(&new_orphan).map(|p| p.0),
+        Ok(new_parent)
+    );
+}
+
+/// Tests that entity given back to the world with `keep` stays owned by the world.
+#[cfg(feature = "rc")]
+#[test]
+fn world_keep() {
+    let mut world = World::new();
+    let e = world.spawn_owning((1u32,));
+    let id = *e;
+
+    world.keep(e);
+    world.maintain();
+
+    assert!(world.is_alive(&id));
+    assert_eq!(world.despawn(&id), Ok(()));
+    assert!(!world.is_alive(&id));
+}
+
+/// Tests that entities with strong references are not moved between worlds.
+#[cfg(feature = "rc")]
+#[test]
+fn world_transfer_owned() {
+    let mut staging = World::new();
+    let a = staging.spawn((1u32,));
+    let b = staging.spawn_owning((2u32,));
+
+    let mut world = World::new();
+    assert_eq!(
+        world.transfer(&mut staging, vec![a, *b]).unwrap_err(),
+        TransferError::NotOwned
+    );
+    assert!(staging.is_alive(&a));
+    assert_eq!(
+        world.merge(&mut staging).unwrap_err(),
+        TransferError::NotOwned
+    );
+
+    staging.keep(b);
+    let map = world.merge(&mut staging).unwrap();
+    assert_eq!(map.len(), 2);
+}
+
+/// Tests that changes are replicated with deltas.
+#[test]
+fn world_delta() {
+    use crate::{delta::Registry, entity::EntityMap, world::Tracks};
+    use core::convert::TryInto;
+
+    let mut registry = Registry::new();
+    registry.register::<u32>(
+        |value, out| out.extend_from_slice(&value.to_le_bytes()),
+        |bytes| Some(u32::from_le_bytes(bytes.try_into().ok()?)),
+    );
+    registry.register::<String>(
+        |value, out| out.extend_from_slice(value.as_bytes()),
+        |bytes| String::from_utf8(bytes.to_vec()).ok(),
+    );
+
+    let mut server = World::new();
+    let a = server.spawn((1u32, String::from("a")));
+    let b = server.spawn((2u32, true));
+    let c = server.spawn((3u32,));
+
+    let mut client = World::new();
+    let mut map = EntityMap::new();
+    let mut tracks = Tracks::new();
+
+    let mut delta = Vec::new();
+    server.write_delta(&mut tracks, &registry, &mut delta);
+    client.apply_delta(&registry, &delta, &mut map).unwrap();
+
+    assert_eq!(map.len(), 3);
+    let client_a = map.get(&a).unwrap();
+    let client_b = map.get(&b).unwrap();
+    assert_eq!(
+        client.query_one::<(&u32, &String)>(&client_a),
+        Ok((&1, &String::from("a")))
+    );
+    assert_eq!(client.query_one::<&u32>(&client_b), Ok(&2));
+    assert_eq!(client.has_component::<bool>(&client_b), Ok(false));
+
+    delta.clear();
+    server.write_delta(&mut tracks, &registry, &mut delta);
+    assert!(delta.is_empty());
+
+    *server.query_one_mut::<&mut u32, _>(&b).unwrap() = 20;
+    server.remove::<String>(&a).unwrap();
+    server.despawn(&c).unwrap();
+    let d = server.spawn((4u32,));
+
+    delta.clear();
+    server.write_delta(&mut tracks, &registry, &mut delta);
+    client.apply_delta(&registry, &delta, &mut map).unwrap();
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(client.query_one::<&u32>(&client_b), Ok(&20));
+    assert_eq!(client.has_component::<String>(&client_a), Ok(false));
+    assert_eq!(client.query_one::<&u32>(&map.get(&d).unwrap()), Ok(&4));
+    assert_eq!(client.query::<&u32>().into_iter().count(), 3);
+
+    assert!(client.apply_delta(&registry, &[2], &mut map).is_err());
+}
+
+/// Tests that despawns are logged from `enable_delta_log`
+/// and log is trimmed as `Tracks` are updated.
+#[test]
+fn world_delta_log() {
+    use crate::{
+        delta::{Registry, SET},
+        entity::EntityMap,
+        world::Tracks,
+    };
+    use core::convert::TryInto;
+
+    let mut registry = Registry::new();
+    registry.register::<u32>(
+        |value, out| out.extend_from_slice(&value.to_le_bytes()),
+        |bytes| Some(u32::from_le_bytes(bytes.try_into().ok()?)),
+    );
+
+    let mut server = World::new();
+    server.register_clone::<u32>();
+    server.enable_delta_log();
+    let a = server.spawn((1u32,));
+    let b = server.spawn((2u32,));
+
+    // Client starts from a copy of the server.
+    let mut client = server.try_clone().unwrap();
+    let mut map = EntityMap::new();
+    map.insert(a, a);
+    map.insert(b, b);
+    let mut tracks = server.tracks_now();
+
+    server.despawn(&a).unwrap();
+
+    let mut delta = Vec::new();
+    server.write_delta(&mut tracks, &registry, &mut delta);
+    client.apply_delta(&registry, &delta, &mut map).unwrap();
+    assert!(!client.is_alive(&a));
+    assert!(client.is_alive(&b));
+
+    // Despawn is not newer than the only outstanding `Tracks` and is dropped.
+    let mut fresh = Tracks::new();
+    delta.clear();
+    server.write_delta(&mut fresh, &registry, &mut delta);
+    assert_eq!(delta[0], SET);
+
+    server.release_delta_tracks(&tracks);
+    server.release_delta_tracks(&fresh);
+}
+
+/// Tests that each reader sees each event once
+/// and events are dropped after two maintenance cycles.
+#[test]
+fn world_events() {
+    use crate::event::EventReader;
+
+    let mut world = World::new();
+    let mut early = EventReader::<u32>::new();
+    assert_eq!(world.read_events(&mut early).count(), 0);
+
+    world.send_event(1u32);
+    world.send_event(2u32);
+
+    let mut late = EventReader::<u32>::new();
+    assert_eq!(
+        world.read_events(&mut early).copied().collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(world.read_events(&mut early).count(), 0);
+
+    world.maintain();
+    world.send_event(3u32);
+
+    assert_eq!(
+        world.read_events(&mut early).copied().collect::<Vec<_>>(),
+        vec![3]
+    );
+
+    world.maintain();
+    assert_eq!(
+        world.read_events(&mut late).copied().collect::<Vec<_>>(),
+        vec![3]
+    );
+
+    world.maintain();
+    assert!(world.events::<u32>().unwrap().is_empty());
+    assert_eq!(world.read_events(&mut EventReader::<u32>::new()).count(), 0);
+}
+
+/// Tests that observers are notified about spawned and despawned entities.
+#[test]
+fn world_observers() {
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    let spawned = Rc::new(RefCell::new(Vec::new()));
+    let despawned = Rc::new(RefCell::new(Vec::new()));
+
+    let mut world = World::new();
+
+    let log = spawned.clone();
+    world.on_spawn(move |id, _| log.borrow_mut().push(id));
+
+    let log = despawned.clone();
+    world.on_despawn(move |id, entity| {
+        log.borrow_mut().push((id, entity.get::<u32>().copied()));
+    });
+
+    let a = world.spawn((1u32,));
+    let batch: Vec<_> = world.spawn_batch(vec![(2u32,), (3u32,)]).collect();
+    assert_eq!(*spawned.borrow(), vec![a, batch[0], batch[1]]);
+
+    world.despawn(&a).unwrap();
+    assert_eq!(*despawned.borrow(), vec![(a, Some(1))]);
+
+    #[cfg(feature = "rc")]
+    {
+        let b = world.spawn_owning((4u32,));
+        let b_id = *b;
+        drop(b);
+        world.maintain();
+        assert_eq!(despawned.borrow().last(), Some(&(b_id, Some(4))));
+    }
+
+    despawned.borrow_mut().clear();
+    drop(world);
+
+    let mut dropped = despawned.borrow().clone();
+    dropped.sort_by_key(|(_, value)| *value);
+    assert_eq!(dropped, vec![(batch[0], Some(2)), (batch[1], Some(3))]);
+}
+
+/// Tests that weak ids are upgraded only while entity has strong references.
+#[cfg(feature = "rc")]
+#[test]
+fn world_upgrade() {
+    let mut world = World::new();
+
+    let owned = world.spawn((1u32,));
+    assert!(world.upgrade(&owned).is_none());
+
+    let e = world.spawn_owning((2u32,));
+    let id = *e;
+
+    let upgraded = world.upgrade(&id).unwrap();
+    assert_eq!(*upgraded, id);
+
+    drop(e);
+    world.maintain();
+    assert!(world.is_alive(&id));
+
+    drop(upgraded);
+    assert!(world.upgrade(&id).is_none());
+
+    world.maintain();
+    assert!(!world.is_alive(&id));
+    assert!(world.upgrade(&id).is_none());
+}
+
+/// Tests that strong references to an entity are counted
+/// and long-held ones are reported by leak detection.
+#[cfg(feature = "rc")]
+#[test]
+fn world_strong_count() {
+    let mut world = World::new();
+
+    let owned = world.spawn((1u32,));
+    assert_eq!(world.strong_count(&owned), Ok(0));
+
+    let e = world.spawn_owning((2u32,));
+    let id = *e;
+    assert_eq!(world.strong_count(&id), Ok(1));
+
+    let clone = e.clone();
+    assert_eq!(world.strong_count(&id), Ok(2));
+
+    #[cfg(feature = "leak-detection")]
+    {
+        world.maintain();
+        world.maintain();
+
+        assert!(world.leak_report(2).is_empty());
+
+        let leaks = world.leak_report(1);
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].id, id);
+        assert_eq!(leaks[0].strong_count, 2);
+        assert_eq!(leaks[0].cycles, 2);
+        assert_eq!(leaks[0].locations.len(), 2);
+        assert!(leaks[0].locations.iter().all(|l| l.file() == file!()));
+    }
+
+    drop(e);
+    drop(clone);
+    assert_eq!(world.strong_count(&id), Ok(0));
+
+    world.maintain();
+    assert_eq!(world.strong_count(&id), Err(NoSuchEntity));
+}
+
+/// Tests that entities owned by despawned entities are despawned in cascade.
+#[cfg(feature = "rc")]
+#[test]
+fn world_cascade_despawn() {
+    let mut world = World::new();
+
+    let c = world.spawn_owning((3u32,));
+    let c_id = *c;
+    let b = world.spawn_owning((c,));
+    let b_id = *b;
+    let a = world.spawn_owning((b,));
+    let a_id = *a;
+
+    world.set_cascade_depth(1);
+    drop(a);
+
+    assert_eq!(world.maintain(), 1);
+    assert!(!world.is_alive(&a_id));
+    assert!(!world.is_alive(&b_id));
+    assert!(world.is_alive(&c_id));
+
+    assert_eq!(world.maintain(), 0);
+    assert!(!world.is_alive(&c_id));
+
+    let c = world.spawn_owning((3u32,));
+    let c_id = *c;
+    let b = world.spawn_owning((c,));
+    let b_id = *b;
+    let a = world.spawn((b,));
+
+    // Released before the call, left for `maintain`.
+    let other = world.spawn_owning((4u32,));
+    let other_id = *other;
+    drop(other);
+
+    assert_eq!(world.despawn_recursive(&a), Ok(2));
+    assert!(!world.is_alive(&a));
+    assert!(!world.is_alive(&b_id));
+    assert!(!world.is_alive(&c_id));
+    assert!(world.is_alive(&other_id));
+
+    assert_eq!(world.maintain(), 0);
+    assert!(!world.is_alive(&other_id));
+}
+
+/// Tests that growing archetype writes its version arrays within allocated blocks.
+#[test]
+fn world_grow_versions() {
+    use crate::allocator::{AllocError, Allocator, Global};
+    use core::{alloc::Layout, ptr::NonNull};
+
+    const CANARY: [u8; 8] = *b"canary!!";
+
+    /// Allocator that checks bytes after each block on deallocation.
+    struct Guarded;
+
+    fn padded(layout: Layout) -> Layout {
+        Layout::from_size_align(layout.size() + CANARY.len(), layout.align()).unwrap()
+    }
+
+    unsafe impl Allocator for Guarded {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            let ptr = Global.allocate(padded(layout))?;
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    CANARY.as_ptr(),
+                    ptr.as_ptr().add(layout.size()),
+                    CANARY.len(),
+                )
+            };
+            Ok(ptr)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            let canary = core::slice::from_raw_parts(ptr.as_ptr().add(layout.size()), CANARY.len());
+            assert_eq!(canary, CANARY, "Write past the end of memory block");
+            Global.deallocate(ptr, padded(layout))
+        }
+    }
+
+    let mut world = World::new_in(Guarded);
+    let first = world.spawn((0u32,));
+
+    let mut tracks = world.tracks_now();
+    *world.query_one_mut::<&mut u32, _>(&first).unwrap() = 1;
+
+    for i in 2..10000u32 {
+        world.spawn((i,));
+    }
+
+    let modified = world
+        .query::<Modified<&u32>>()
+        .tracked_iter(&mut tracks)
+        .filter(|&(_, value)| *value == 1)
+        .count();
+    assert_eq!(modified, 1);
+}
+
+/// Tests that memory of components and entities is allocated with the world allocator
+/// and returned to it when world is dropped.
+#[test]
+fn world_allocator() {
+    use crate::allocator::{AllocError, Allocator, Global};
+    use alloc::sync::Arc;
+    use core::{
+        alloc::Layout,
+        ptr::NonNull,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    struct Counting(Arc<AtomicUsize>);
+
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            self.0.fetch_add(layout.size(), Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.fetch_sub(layout.size(), Ordering::Relaxed);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    let live = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new_in(Counting(live.clone()));
+    let before = live.load(Ordering::Relaxed);
+
+    let e = world.spawn((42u32, String::from("qwe")));
+    world.spawn_batch((0..1000u32).map(|i| (i,))).spawn_all();
+    assert!(live.load(Ordering::Relaxed) > before);
+
+    assert_eq!(world.query_one::<&String>(&e).unwrap(), "qwe");
+
+    // Entities without components need only entity table and entity list of archetype.
+    let before = live.load(Ordering::Relaxed);
+    for _ in 0..1000 {
+        world.spawn(());
+    }
+    assert!(live.load(Ordering::Relaxed) - before >= 2000 * core::mem::size_of::<EntityId>());
+
+    drop(world);
+    assert_eq!(live.load(Ordering::Relaxed), 0);
+}
+
+/// Tests that `World` with fixed capacity reports `CapacityExceeded` instead of growing.
+#[test]
+fn world_fixed_capacity() {
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 3,
+        max_archetypes: 2,
+        archetype_capacity: 2,
+    });
+
+    let a = world.try_spawn((1u32,)).unwrap();
+    let b = world.try_spawn((2u32,)).unwrap();
+    assert_eq!(world.try_spawn((3u32,)), Err(CapacityExceeded));
+
+    let c = world.try_spawn((3u32, 3u8)).unwrap();
+    assert_eq!(world.try_spawn((4u32, 4u8)), Err(CapacityExceeded));
+
+    let tracks = world.tracks_now();
+    assert_eq!(
+        world.try_insert(&a, "qwe"),
+        Err(InsertError::CapacityExceeded)
+    );
+    assert_eq!(
+        world.try_insert_bundle(&a, ("qwe", 1u16)),
+        Err(InsertError::CapacityExceeded)
+    );
+    assert_eq!(world.has_component::<&str>(&a), Ok(false));
+
+    // Failed insertions do not advance change detection.
+    assert_eq!(world.tracks_now(), tracks);
+
+    world.despawn(&c).unwrap();
+    assert_eq!(world.try_insert(&a, 1u8), Ok(()));
+    assert_eq!(world.try_spawn((5u32,)).map(|_| ()), Ok(()));
+    assert_eq!(world.try_spawn((6u16,)), Err(CapacityExceeded));
+    assert_eq!(world.query_one_mut::<&u32, _>(&b), Ok(&2));
+}
+
+/// Tests that removing components fails without changes
+/// when `World` with fixed capacity has no space for the entity.
+#[test]
+fn world_fixed_capacity_remove() {
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 4,
+        max_archetypes: 2,
+        archetype_capacity: 1,
+    });
+
+    let a = world.spawn((1u32,));
+    let b = world.spawn((2u32, 2u8));
+
+    assert_eq!(
+        world.try_remove::<u8>(&b),
+        Err(RemoveError::CapacityExceeded)
+    );
+    assert_eq!(
+        world.try_remove_bundle::<(u8,)>(&b),
+        Err(RemoveError::CapacityExceeded)
+    );
+    assert_eq!(world.query_one_mut::<(&u32, &u8), _>(&b), Ok((&2, &2)));
+
+    // No archetype is left for the entity without `u32`.
+    assert_eq!(
+        world.try_remove::<u32>(&b),
+        Err(RemoveError::CapacityExceeded)
+    );
+    assert_eq!(world.query_one_mut::<(&u32, &u8), _>(&b), Ok((&2, &2)));
+
+    world.despawn(&a).unwrap();
+    assert_eq!(world.try_remove::<u8>(&b), Ok(2));
+    assert_eq!(world.query_one_mut::<&u32, _>(&b), Ok(&2));
+}
+
+/// Tests that non-try removal panics
+/// when `World` with fixed capacity has no space for the entity.
+#[test]
+#[should_panic(expected = "World capacity exceeded")]
+fn world_fixed_capacity_remove_panic() {
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 4,
+        max_archetypes: 2,
+        archetype_capacity: 1,
+    });
+
+    world.spawn((1u32,));
+    let b = world.spawn((2u32, 2u8));
+    let _ = world.remove::<u8>(&b);
+}
+
+/// Tests that setting and removing shared values fails without changes
+/// when `World` with fixed capacity has no space for the entity.
+#[test]
+fn world_fixed_capacity_shared() {
+    #[derive(Debug, PartialEq)]
+    struct Team(u32);
+
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 4,
+        max_archetypes: 3,
+        archetype_capacity: 1,
+    });
+
+    let a = world.spawn((1u32,));
+    let b = world.spawn((2u32, 2u8));
+    assert_eq!(world.try_set_shared(&a, Team(1)), Ok(()));
+
+    // Archetype with `Team(1)` is full.
+    let c = world.spawn((3u32,));
+    assert_eq!(
+        world.try_set_shared(&c, Team(1)),
+        Err(InsertError::CapacityExceeded)
+    );
+    assert_eq!(world.get_shared::<Team>(&c), Ok(None));
+
+    // No archetype is left for `Team(2)`.
+    assert_eq!(
+        world.try_set_shared(&b, Team(2)),
+        Err(InsertError::CapacityExceeded)
+    );
+    assert_eq!(world.get_shared::<Team>(&b), Ok(None));
+
+    // Archetype without shared values is full.
+    assert_eq!(
+        world.try_remove_shared::<Team>(&a),
+        Err(RemoveError::CapacityExceeded)
+    );
+    assert_eq!(world.get_shared::<Team>(&a), Ok(Some(&Team(1))));
+
+    world.despawn(&c).unwrap();
+    assert_eq!(world.try_remove_shared::<Team>(&a), Ok(()));
+    assert_eq!(world.get_shared::<Team>(&a), Ok(None));
+}
+
+/// Tests that cloning an entity fails without changes
+/// when `World` with fixed capacity has no space for the clone.
+#[test]
+fn world_fixed_capacity_clone() {
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 4,
+        max_archetypes: 1,
+        archetype_capacity: 1,
+    });
+    world.register_clone::<u32>();
+
+    let a = world.spawn((1u32,));
+    assert_eq!(world.clone_entity(&a), Err(CloneError::CapacityExceeded));
+
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 2,
+        max_archetypes: 1,
+        archetype_capacity: 4,
+    });
+    world.register_clone::<u32>();
+
+    let a = world.spawn((1u32,));
+    world.spawn((2u32,));
+
+    // Entity table is full.
+    assert_eq!(world.clone_entity(&a), Err(CloneError::CapacityExceeded));
+    assert_eq!(world.query::<&u32>().into_iter().count(), 2);
+}
+
+/// Tests that instantiating prefabs fails without spawning any entity
+/// when `World` with fixed capacity has no space for instances.
+#[test]
+fn world_fixed_capacity_instantiate() {
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 4,
+        max_archetypes: 2,
+        archetype_capacity: 2,
+    });
+    world.register_clone::<u32>();
+    world.register_clone::<bool>();
+
+    let mut builder = EntityBuilder::new();
+    builder.add(42u32);
+    let prefab = world.make_prefab(builder).unwrap();
+
+    assert_eq!(
+        world.try_instantiate_batch(&prefab, 3),
+        Err(CapacityExceeded)
+    );
+    assert_eq!(world.query::<&u32>().into_iter().count(), 0);
+
+    world.try_instantiate(&prefab).unwrap();
+    world.try_instantiate_with(&prefab, (true,)).unwrap();
+    world.try_instantiate_with(&prefab, (true,)).unwrap();
+
+    assert_eq!(
+        world.try_instantiate_with(&prefab, (true,)),
+        Err(CapacityExceeded)
+    );
+    assert_eq!(
+        world.try_instantiate_with(&prefab, (1u8,)),
+        Err(CapacityExceeded)
+    );
+    assert_eq!(
+        world.try_instantiate_batch(&prefab, 2),
+        Err(CapacityExceeded)
+    );
+    assert_eq!(world.query::<&u32>().into_iter().count(), 3);
+}
+
+/// Tests that moving entities from another world fails without moving any
+/// when `World` with fixed capacity has no space for them.
+#[test]
+fn world_fixed_capacity_transfer() {
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 4,
+        max_archetypes: 2,
+        archetype_capacity: 2,
+    });
+    world.spawn((1u32,));
+
+    let mut staging = World::new();
+    let a = staging.spawn((2u32,));
+    let b = staging.spawn((3u32,));
+    let c = staging.spawn((4u32, 4u8));
+    let d = staging.spawn((5u16,));
+
+    assert_eq!(
+        world.transfer(&mut staging, vec![a, b]).unwrap_err(),
+        TransferError::CapacityExceeded
+    );
+    assert_eq!(
+        world.transfer(&mut staging, vec![c, d]).unwrap_err(),
+        TransferError::CapacityExceeded
+    );
+    assert_eq!(
+        world.merge(&mut staging).unwrap_err(),
+        TransferError::CapacityExceeded
+    );
+    assert_eq!(staging.query::<&u32>().into_iter().count(), 3);
+    assert_eq!(world.query::<&u32>().into_iter().count(), 1);
+
+    // Duplicate ids are moved once.
+    let map = world.transfer(&mut staging, vec![a, c, a]).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(world.query::<&u32>().into_iter().count(), 3);
+}
+
+/// Tests that batch operations move, replace and drop components of entities
+/// grouped by archetype and keep entity order in deterministic mode.
+#[test]
+fn world_batch() {
+    let mut world = World::new();
+
+    let entities: Vec<_> = (0..10u32).map(|i| world.spawn((i,))).collect();
+    let other = world.spawn((10u32, 10u8));
+
+    let inserted = world.insert_batch(
+        entities
+            .iter()
+            .step_by(2)
+            .map(|e| (*e, 1u8))
+            .chain(Some((entities[0], 2u8)))
+            .chain(Some((other, 3u8))),
+    );
+    assert_eq!(inserted, 6);
+
+    for (i, e) in entities.iter().enumerate() {
+        assert_eq!(world.query_one_mut::<&u32, _>(e), Ok(&(i as u32)));
+        assert_eq!(world.has_component::<u8>(e), Ok(i % 2 == 0));
+    }
+    assert_eq!(world.query_one_mut::<&u8, _>(&entities[0]), Ok(&2));
+    assert_eq!(world.query_one_mut::<&u8, _>(&other), Ok(&3));
+
+    assert_eq!(world.remove_batch::<u8>(&entities[..5]), 3);
+    for e in &entities[..5] {
+        assert_eq!(world.has_component::<u8>(e), Ok(false));
+    }
+    assert_eq!(world.has_component::<u8>(&entities[6]), Ok(true));
+
+    assert_eq!(world.despawn_batch(&entities[3..]), 7);
+    assert_eq!(world.despawn_batch(&entities[3..]), 0);
+
+    for (i, e) in entities.iter().enumerate() {
+        match i {
+            0..=2 => assert_eq!(world.query_one_mut::<&u32, _>(e), Ok(&(i as u32))),
+            _ => assert!(!world.is_alive(e)),
+        }
+    }
+    assert_eq!(world.query_one_mut::<(&u32, &u8), _>(&other), Ok((&10, &3)));
+
+    let mut world = World::new();
+    world.set_deterministic(true);
+
+    let entities: Vec<_> = (0..8u32).map(|i| world.spawn((i,))).collect();
+    world.insert_batch([5, 1, 6, 2].iter().map(|&i| (entities[i], i as u8)));
+    world.despawn_batch(&[entities[0], entities[6]]);
+    world.remove_batch::<u8>(&[entities[5], entities[1]]);
+
+    let order: Vec<_> = world
+        .query::<(&u32, Option<&u8>)>()
+        .into_iter()
+        .map(|(_, (v, u))| (*v, u.copied()))
+        .collect();
+    assert_eq!(
+        order,
+        [
+            (3, None),
+            (4, None),
+            (7, None),
+            (1, None),
+            (5, None),
+            (2, Some(2))
+        ]
+    );
+}
+
+/// Tests that batch operations on `World` with fixed capacity
+/// report `CapacityExceeded` and leave all entities unchanged.
+#[test]
+fn world_batch_fixed_capacity() {
+    let mut world = World::with_capacity(WorldConfig {
+        max_entities: 4,
+        max_archetypes: 2,
+        archetype_capacity: 3,
+    });
+
+    let a: Vec<_> = (0..3u32).map(|i| world.spawn((i,))).collect();
+    let c = world.spawn((3u32, 3u8));
+
+    let tracks = world.tracks_now();
+    assert_eq!(
+        world.try_insert_batch(a.iter().map(|e| (*e, 0u8))),
+        Err(CapacityExceeded)
+    );
+    assert_eq!(
+        world.try_insert_batch(a.iter().map(|e| (*e, 0u16))),
+        Err(CapacityExceeded)
+    );
+    assert_eq!(world.tracks_now(), tracks);
+    for e in &a {
+        assert_eq!(world.has_component::<u8>(e), Ok(false));
+    }
+
+    assert_eq!(
+        world.try_insert_batch(a[..2].iter().map(|e| (*e, 0u8))),
+        Ok(2)
+    );
+
+    assert_eq!(
+        world.try_remove_batch::<u8>(&[c, a[0], a[1]]),
+        Err(CapacityExceeded)
+    );
+    assert_eq!(world.has_component::<u8>(&c), Ok(true));
+
+    assert_eq!(world.try_remove_batch::<u8>(&[c, a[0]]), Ok(2));
+    for (i, e) in a.iter().chain(Some(&c)).enumerate() {
+        assert_eq!(world.query_one_mut::<&u32, _>(e), Ok(&(i as u32)));
+        assert_eq!(world.has_component::<u8>(e), Ok(i == 1));
+    }
+}
+
+/// Tests that shared values are set and removed per entity, read once per archetype
+/// and kept when other components of the entity change.
+#[test]
+fn world_shared() {
+    #[derive(Debug, PartialEq)]
+    struct Team(u32);
+
+    let mut world = World::new();
+
+    let a = world.spawn((1u32,));
+    let b = world.spawn((2u32,));
+    let c = world.spawn((3u32,));
+
+    assert_eq!(world.set_shared(&a, Team(1)), Ok(()));
+    assert_eq!(world.set_shared(&b, Team(1)), Ok(()));
+    assert_eq!(world.set_shared(&c, Team(2)), Ok(()));
+
+    assert_eq!(world.get_shared::<Team>(&a), Ok(Some(&Team(1))));
+    assert_eq!(world.get_shared::<Team>(&c), Ok(Some(&Team(2))));
+
+    let mut items: Vec<_> = world
+        .query::<(&u32, Shared<Team>)>()
+        .into_iter()
+        .map(|(_, (v, team))| (*v, team.0))
+        .collect();
+    items.sort_unstable();
+    assert_eq!(items, [(1, 1), (2, 1), (3, 2)]);
+
+    // Shared value is kept when components change.
+    assert_eq!(world.try_insert(&a, "qwe"), Ok(()));
+    assert_eq!(world.get_shared::<Team>(&a), Ok(Some(&Team(1))));
+    assert_eq!(
+        world.query_one_mut::<(&u32, &&str), _>(&a),
+        Ok((&1, &"qwe"))
+    );
+
+    assert_eq!(world.remove_shared::<Team>(&b), Ok(()));
+    assert_eq!(world.get_shared::<Team>(&b), Ok(None));
+    assert_eq!(world.query::<Shared<Team>>().into_iter().count(), 2);
+    assert_eq!(world.query_one_mut::<&u32, _>(&b), Ok(&2));
+}
+
+/// Tests that sorting archetypes reorders entities by key
+/// without marking unmodified components as modified.
+#[test]
+fn world_sort_archetypes() {
+    let mut world = World::new();
+
+    let entities: Vec<_> = [5u32, 3, 8, 1, 9, 2]
+        .iter()
+        .map(|&v| world.spawn((v,)))
+        .collect();
+    let other = world.spawn((4u32, "qwe"));
+    let untouched = world.spawn((7u8,));
+
+    let mut tracks = world.tracks_now();
+    *world.query_one_mut::<&mut u32, _>(&entities[2]).unwrap() = 6;
+
+    world.sort_archetypes_by::<u32, _>(|v| *v);
+
+    let values: Vec<u32> = world
+        .query::<&u32>()
+        .into_iter()
+        .filter(|(e, _)| *e != other)
+        .map(|(_, v)| *v)
+        .collect();
+    assert_eq!(values, [1, 2, 3, 5, 6, 9]);
+
+    for (e, v) in entities.iter().zip([5u32, 3, 6, 1, 9, 2].iter()) {
+        assert_eq!(world.query_one_mut::<&u32, _>(e), Ok(v));
+    }
+    assert_eq!(
+        world.query_one_mut::<(&u32, &&str), _>(&other),
+        Ok((&4, &"qwe"))
+    );
+    assert_eq!(world.query_one_mut::<&u8, _>(&untouched), Ok(&7));
+
+    let modified: Vec<_> = world
+        .query::<Modified<&u32>>()
+        .tracked_into_iter(&mut tracks)
+        .map(|(e, _)| e)
+        .collect();
+    assert_eq!(modified, [entities[2]]);
+}
+
+/// Tests that tracking queries do not skip entities that follow skipped chunk
+/// and respect `Tracks` in all archetypes when consumed with `next` and `fold`.
+#[test]
+fn world_tracked_chunks() {
+    let mut world = World::new();
+
+    let a: Vec<_> = (0..3 * CHUNK_LEN as u32)
+        .map(|i| world.spawn((i,)))
+        .collect();
+    let b: Vec<_> = (0..3 * CHUNK_LEN as u32)
+        .map(|i| world.spawn((i, i as u8)))
+        .collect();
+
+    let tracks = world.tracks_now();
+    for e in [
+        &a[CHUNK_LEN],
+        &a[CHUNK_LEN * 2 + 5],
+        &b[CHUNK_LEN],
+        &b[CHUNK_LEN * 2 + 5],
+    ]
+    .iter()
+    {
+        *world.query_one_mut::<&mut u32, _>(e).unwrap() += 1;
+    }
+    let expected = [
+        a[CHUNK_LEN],
+        a[CHUNK_LEN * 2 + 5],
+        b[CHUNK_LEN],
+        b[CHUNK_LEN * 2 + 5],
+    ];
+
+    let mut next_tracks = tracks.clone();
+    let mut iter = world
+        .query::<Modified<&u32>>()
+        .tracked_into_iter(&mut next_tracks);
+    let mut modified = Vec::new();
+    while let Some((e, _)) = iter.next() {
+        modified.push(e);
+    }
+    assert_eq!(modified, expected);
+
+    let mut fold_tracks = tracks.clone();
+    let modified = world
+        .query::<Modified<&u32>>()
+        .tracked_into_iter(&mut fold_tracks)
+        .fold(Vec::new(), |mut modified, (e, _)| {
+            modified.push(e);
+            modified
+        });
+    assert_eq!(modified, expected);
+
+    let mut count_tracks = tracks;
+    let count = world
+        .query::<Modified<&u32>>()
+        .tracked_into_iter(&mut count_tracks)
+        .count();
+    assert_eq!(count, expected.len());
+}
+
+/// Tests that worlds in deterministic mode iterate identically
+/// regardless of history of operations.
+#[test]
+fn world_deterministic() {
+    let mut a = World::new();
+    a.set_deterministic(true);
+
+    a.spawn((1u32,));
+    a.spawn((2u32, 20u8));
+    a.spawn((3u32,));
+    a.spawn((4u32, 40u8));
+    a.spawn((5u32, "x"));
+
+    let mut b = World::new();
+    b.set_deterministic(true);
+    assert!(b.is_deterministic());
+
+    b.spawn((5u32, "x"));
+    let tmp = b.spawn((0u32,));
+    let tmp2 = b.spawn((0u16,));
+    b.spawn((1u32,));
+    let e2 = b.spawn((2u32,));
+    b.try_insert(&e2, 20u8).unwrap();
+    b.spawn((3u32,));
+    b.despawn(&tmp).unwrap();
+    b.spawn((4u32, 40u8));
+    b.despawn(&tmp2).unwrap();
+
+    let order = |world: &World| -> Vec<(u32, Option<u8>)> {
+        world
+            .query::<(&u32, Option<&u8>)>()
+            .into_iter()
+            .map(|(_, (v, u))| (*v, u.copied()))
+            .collect()
+    };
+
+    let expected = [
+        (5, None),
+        (1, None),
+        (3, None),
+        (2, Some(20)),
+        (4, Some(40)),
+    ];
+    assert_eq!(order(&a), expected);
+    assert_eq!(order(&b), expected);
+}
+
+/// Tests that archetypes in deterministic mode are ordered by registered component ids
+/// and registered `Ord` of shared values regardless of order of their creation.
+#[test]
+fn world_deterministic_shared() {
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Team(u32);
+
+    let build = |teams: &[u32]| {
+        let mut world = World::new();
+        world.register_ord::<Team>();
+        world.set_deterministic(true);
+
+        for &team in teams {
+            let e = world.spawn((team,));
+            world.set_shared(&e, Team(team)).unwrap();
+        }
+
+        world
+            .query::<&u32>()
+            .into_iter()
+            .map(|(_, v)| *v)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(build(&[3, 1, 2]), [1, 2, 3]);
+    assert_eq!(build(&[2, 3, 1]), [1, 2, 3]);
+
+    let mut world = World::new();
+    world.set_deterministic(true);
+    world.spawn((1u8,));
+    world.spawn((2u16,));
+
+    let order = |world: &World| -> Vec<bool> {
+        world
+            .query::<Option<&u8>>()
+            .into_iter()
+            .map(|(_, v)| v.is_some())
+            .collect()
+    };
+    assert_eq!(order(&world), [false, true]);
+
+    world.register_canonical_id::<u8>(1);
+    world.register_canonical_id::<u16>(2);
+    assert_eq!(order(&world), [true, false]);
+}
+
+/// Tests that deterministic mode keeps archetypes in order of creation
+/// when they differ only in shared values of type without registered `Ord`.
+#[test]
+fn world_deterministic_unordered_shared() {
+    #[derive(PartialEq)]
+    struct Team(u32);
+
+    let mut world = World::new();
+    world.set_deterministic(true);
+
+    let a = world.spawn((1u32,));
+    let b = world.spawn((2u32,));
+    world.set_shared(&b, Team(2)).unwrap();
+    world.set_shared(&a, Team(1)).unwrap();
+    world.spawn((3u32,));
+
+    let teams: Vec<u32> = world
+        .query::<Shared<Team>>()
+        .into_iter()
+        .map(|(_, team)| team.0)
+        .collect();
+    assert_eq!(teams, [2, 1]);
+}
+
+/// Tests that index finds entities by component value
+/// and follows insertions, modifications, removals and despawns.
+#[test]
+fn world_index() {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    struct Team(u8);
+
+    let mut world = World::new();
+
+    let a = world.spawn((Team(1),));
+    let b = world.spawn((Team(2), 1u32));
+
+    world.add_index::<Team>();
+
+    let c = world.spawn((Team(1), 2u32));
+
+    let lookup = |world: &World, team: u8| -> Vec<EntityId> {
+        let mut entities: Vec<_> = world.lookup(&Team(team)).collect();
+        entities.sort_by_key(|e| e.bits());
+        entities
+    };
+    let sorted = |mut entities: Vec<_>| {
+        entities.sort_by_key(|e: &EntityId| e.bits());
+        entities
+    };
+
+    assert_eq!(lookup(&world, 1), sorted(vec![a, c]));
+    assert_eq!(lookup(&world, 2), [b]);
+    assert!(lookup(&world, 3).is_empty());
+
+    *world.query_one_mut::<&mut Team, _>(&a).unwrap() = Team(3);
+    assert_eq!(lookup(&world, 1), [c]);
+    assert_eq!(lookup(&world, 3), [a]);
+
+    for (_, team) in world.query_mut::<&mut Team, _>() {
+        team.0 += 1;
+    }
+    assert_eq!(lookup(&world, 2), [c]);
+    assert_eq!(lookup(&world, 3), [b]);
+    assert_eq!(lookup(&world, 4), [a]);
+
+    world.remove::<Team>(&b).unwrap();
+    world.despawn(&c).unwrap();
+    assert!(lookup(&world, 2).is_empty());
+    assert!(lookup(&world, 3).is_empty());
+    assert_eq!(lookup(&world, 4), [a]);
+}
+
+/// Tests that ordered index finds entities with component value in range in value order
+/// and returns nothing for inverted ranges.
+#[test]
+fn world_ordered_index() {
+    use core::ops::Bound::{Excluded, Included};
+
+    let mut world = World::new();
+
+    let a = world.spawn((30u32,));
+    let b = world.spawn((10u32, "b"));
+    let c = world.spawn((20u32,));
+
+    world.add_ordered_index::<u32>();
+
+    let d = world.spawn((15u32,));
+
+    let range = |world: &World, range: core::ops::Range<u32>| -> Vec<EntityId> {
+        world.lookup_range(range).collect()
+    };
+
+    assert_eq!(range(&world, 0..100), [b, d, c, a]);
+    assert_eq!(range(&world, 15..30), [d, c]);
+    assert!(range(&world, 15..15).is_empty());
+    assert_eq!(
+        world
+            .lookup_range((Included(30u32), Excluded(15u32)))
+            .count(),
+        0
+    );
+    assert_eq!(
+        world
+            .lookup_range((Excluded(15u32), Excluded(15u32)))
+            .count(),
+        0
+    );
+
+    *world.query_one_mut::<&mut u32, _>(&a).unwrap() = 5;
+    assert_eq!(world.lookup_range(..=10u32).collect::<Vec<_>>(), [a, b]);
+
+    world.remove::<u32>(&b).unwrap();
+    world.despawn(&c).unwrap();
+    assert_eq!(range(&world, 0..100), [a, d]);
+}
+
+/// Tests that spatial index finds entities within radius and bounding box,
+/// follows moved, removed and despawned entities and handles unbounded queries.
+#[cfg(feature = "spatial")]
+#[test]
+fn world_spatial_index() {
+    use crate::spatial::Position;
+
+    struct Pos([f32; 3]);
+
+    impl Position for Pos {
+        fn position(&self) -> [f32; 3] {
+            self.0
+        }
+    }
+
+    let mut world = World::new();
+    world.add_spatial_index::<Pos>(4.0);
+
+    let a = world.spawn((Pos([0.0, 0.0, 0.0]),));
+    let b = world.spawn((Pos([3.0, 0.0, 0.0]),));
+    let c = world.spawn((Pos([-5.0, 5.0, 0.0]),));
+    let d = world.spawn((Pos([100.0, -100.0, 0.0]),));
+
+    let sorted = |iter: &mut dyn Iterator<Item = EntityId>| {
+        let mut entities: Vec<_> = iter.collect();
+        entities.sort_by_key(|e| e.bits());
+        entities
+    };
+
+    assert_eq!(
+        sorted(&mut world.lookup_radius::<Pos>([0.0; 3], 3.5)),
+        [a, b]
+    );
+    assert_eq!(
+        sorted(&mut world.lookup_aabb::<Pos>([-10.0, -1.0, -1.0], [1.0, 10.0, 1.0])),
+        [a, c]
+    );
+    assert_eq!(
+        sorted(&mut world.lookup_aabb::<Pos>([-1000.0; 3], [1000.0; 3])),
+        [a, b, c, d]
+    );
+
+    world.query_one_mut::<&mut Pos, _>(&b).unwrap().0 = [-4.0, 4.0, 0.0];
+    world.query_one_mut::<&mut Pos, _>(&a).unwrap().0 = [1.0, 1.0, 0.0];
+    assert_eq!(
+        sorted(&mut world.lookup_radius::<Pos>([-4.5, 4.5, 0.0], 1.0)),
+        [b, c]
+    );
+    assert_eq!(sorted(&mut world.lookup_radius::<Pos>([0.0; 3], 2.0)), [a]);
+
+    world.remove::<Pos>(&b).unwrap();
+    world.despawn(&d).unwrap();
+    assert_eq!(
+        sorted(&mut world.lookup_aabb::<Pos>([-1000.0; 3], [1000.0; 3])),
+        [a, c]
+    );
+
+    // Positions and bounds beyond range of cell coordinates.
+    let far = world.spawn((Pos([-1.0e30, 0.0, 0.0]),));
+    assert_eq!(
+        sorted(&mut world.lookup_aabb::<Pos>([f32::MIN; 3], [f32::MAX; 3])),
+        sorted(&mut [a, c, far].iter().copied())
+    );
+    assert_eq!(
+        sorted(&mut world.lookup_radius::<Pos>([0.0; 3], f32::INFINITY)),
+        sorted(&mut [a, c, far].iter().copied())
+    );
+    assert_eq!(
+        sorted(&mut world.lookup_radius::<Pos>([-1.0e30, 0.0, 0.0], 1.0)),
+        [far]
+    );
+}
+
+/// Tests that components that are not `Send` or `Sync`
+/// are spawned, inserted and dropped like any other components.
+#[test]
+fn world_not_send() {
+    use alloc::rc::Rc;
+
+    let mut world = World::new();
+
+    let shared = Rc::new(42u32);
+    let e = world.spawn((1u8,));
+    world.try_insert(&e, shared.clone()).unwrap();
+    let b = world.spawn((shared.clone(),));
+    assert_eq!(Rc::strong_count(&shared), 3);
+
+    assert_eq!(
+        world
+            .query_one_mut::<(&u8, &Rc<u32>), _>(&e)
+            .map(|(u, rc)| (*u, **rc)),
+        Ok((1, 42))
+    );
+
+    world.try_insert(&e, 2u16).unwrap();
+    assert_eq!(
+        world.query_one_mut::<&Rc<u32>, _>(&e).map(|rc| **rc),
+        Ok(42)
+    );
+
+    world.despawn(&e).unwrap();
+    world.despawn(&b).unwrap();
+    assert_eq!(Rc::strong_count(&shared), 1);
+}
+
+/// Tests that `WorldCell` hands out non-conflicting queries at once
+/// and releases borrows when queries are dropped.
+#[test]
+fn world_cell() {
+    let mut world = World::new();
+    world.spawn((1u32, 10u16));
+    world.spawn((2u32, 20u16, 100u8));
+
+    let cell = world.cell();
+    {
+        let mut a = cell.query_mut::<&mut u32>();
+        let mut b = cell.query_mut::<&mut u16>();
+        let c = cell.query::<&u8>();
+        let d = cell.query::<&u8>();
+
+        assert_eq!(cell.try_query::<&u32>().err(), Some(QueryConflict));
+        assert_eq!(cell.try_query_mut::<&mut u8>().err(), Some(QueryConflict));
+
+        for ((_, x), (_, y)) in a.iter_mut().zip(b.iter_mut()) {
+            *x += 1;
+            *y += u16::from(*c.iter().next().unwrap().1 + *d.iter().next().unwrap().1);
+        }
+    }
+
+    {
+        let _a = cell.query_mut::<&mut u32>();
+        assert!(cell.try_query::<(&u16, &u8)>().is_ok());
+    }
+    assert!(cell.try_query::<&u32>().is_ok());
+    drop(cell);
+
+    let mut values: Vec<_> = world
+        .query::<(&u32, &u16)>()
+        .into_iter()
+        .map(|(_, (x, y))| (*x, *y))
+        .collect();
+    values.sort();
+    assert_eq!(values, [(2, 210), (3, 220)]);
+}
+
+/// Tests that query aliasing mutable access to a component panics with query type name.
+#[test]
+#[should_panic(expected = "accesses a component mutably along with other access to it")]
+fn world_invalid_query() {
+    let mut world = World::new();
+    world.spawn((1u32,));
+    world.query_mut_dynamic::<(&mut u32, &u32)>();
+}
+
+/// Tests that query checked at compile time still panics
+/// if generic component types turn out to be the same type.
+#[test]
+#[should_panic(expected = "accesses a component mutably along with other access to it")]
+fn world_invalid_generic_query() {
+    use crate::component::Component;
+
+    fn query<A: Component, B: Component>(world: &mut World) {
+        world.query_mut::<(&mut A, &B), _>();
+    }
+
+    let mut world = World::new();
+    world.spawn((1u32,));
+    query::<u32, u32>(&mut world);
+}
+
+/// Tests that queries checked for aliasing at compile time
+/// and queries checked at runtime yield expected items.
+#[test]
+fn world_checked_query() {
+    use crate::query::Alt;
+
+    let mut world = World::new();
+    let e = world.spawn((1u32, 2u16));
+    world.spawn((3u32,));
+
+    for (_, (a, b, c)) in world.query_mut::<(&mut u32, Option<&u16>, Option<Alt<u8>>), _>() {
+        assert!(c.is_none());
+        *a += u32::from(b.copied().unwrap_or(10));
+    }
+
+    assert_eq!(
+        world.query_one_mut::<(&u32, &mut u16), _>(&e),
+        Ok((&3, &mut 2))
+    );
+
+    // Nested tuples do not implement `NoAlias`.
+    assert_eq!(
+        world.query_one_mut_dynamic::<((&mut u32,), &u16)>(&e),
+        Ok(((&mut 3,), &2))
+    );
+    assert_eq!(
+        world
+            .query_mut_dynamic::<((&u32,), Option<&mut u16>)>()
+            .into_iter()
+            .count(),
+        2
+    );
+
+    let mut values: Vec<_> = world.query::<&u32>().into_iter().map(|(_, v)| *v).collect();
+    values.sort_unstable();
+    assert_eq!(values, [3, 13]);
+}
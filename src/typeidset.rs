@@ -37,7 +37,8 @@ impl TypeIdSet {
                 }
             }
 
-            let modulo = entries.len();
+            // Empty set still needs non-zero modulo for lookups.
+            let modulo = entries.len().max(1);
 
             while entries.last() == Some(&no_type_id) {
                 entries.pop();
@@ -0,0 +1,284 @@
+use core::{
+    any::TypeId,
+    cell::{Cell, RefCell},
+    fmt,
+    marker::PhantomData,
+};
+
+use hashbrown::HashMap;
+use smallvec::SmallVec;
+
+use crate::{
+    archetype::Archetype,
+    hash::NoOpHasherBuilder,
+    query::{
+        assert_valid, Access, Filter, ImmutableQuery, NonTrackingQuery, Query, QueryIter,
+        QueryTrackedIter,
+    },
+};
+
+use super::{QueryMut, QueryRef, Tracks};
+
+/// View of the `World` that hands out queries through shared reference.
+/// Created with [`World::cell`].
+///
+/// Borrows of components are tracked at runtime using [`Query::access`].
+/// Any number of queries may be borrowed at once as long as
+/// no component is accessed mutably by one and accessed at all by another.
+///
+/// [`World::cell`]: `edict::world::World::cell`
+pub struct WorldCell<'a> {
+    pub(super) epoch: &'a Cell<u64>,
+    pub(super) archetypes: &'a [Archetype],
+    pub(super) borrows: RefCell<HashMap<TypeId, isize, NoOpHasherBuilder>>,
+}
+
+impl fmt::Debug for WorldCell<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorldCell")
+            .field("epoch", &self.epoch.get())
+            .field("borrows", &self.borrows.borrow().len())
+            .finish()
+    }
+}
+
+impl<'a> WorldCell<'a> {
+    /// Borrows immutable query.
+    ///
+    /// # Panics
+    ///
+    /// Panics if query conflicts with query borrowed before.
+    #[inline]
+    pub fn query<Q>(&self) -> QueryBorrow<'_, QueryRef<'_, Q, ()>>
+    where
+        Q: Query + ImmutableQuery,
+    {
+        self.try_query()
+            .expect("Query conflicts with borrowed query")
+    }
+
+    /// Attempts to borrow immutable query.
+    ///
+    /// If query conflicts with query borrowed before, fails with `Err(QueryConflict)`.
+    #[inline]
+    pub fn try_query<Q>(&self) -> Result<QueryBorrow<'_, QueryRef<'_, Q, ()>>, QueryConflict>
+    where
+        Q: Query + ImmutableQuery,
+    {
+        debug_assert!(Q::is_valid(), "Immutable queries are always valid");
+
+        let borrow = self.borrow::<Q>()?;
+        Ok(QueryBorrow {
+            query: QueryRef {
+                epoch: self.epoch.get(),
+                archetypes: self.archetypes,
+                query: PhantomData,
+                filter: (),
+            },
+            borrow,
+        })
+    }
+
+    /// Borrows query that can mutate components.
+    ///
+    /// # Panics
+    ///
+    /// Panics if query conflicts with query borrowed before.
+    #[inline]
+    pub fn query_mut<Q>(&self) -> QueryBorrow<'_, QueryMut<'_, Q, ()>>
+    where
+        Q: Query,
+    {
+        self.try_query_mut()
+            .expect("Query conflicts with borrowed query")
+    }
+
+    /// Attempts to borrow query that can mutate components.
+    ///
+    /// If query conflicts with query borrowed before, fails with `Err(QueryConflict)`.
+    #[inline]
+    pub fn try_query_mut<Q>(&self) -> Result<QueryBorrow<'_, QueryMut<'_, Q, ()>>, QueryConflict>
+    where
+        Q: Query,
+    {
+        assert_valid::<Q>();
+
+        let borrow = self.borrow::<Q>()?;
+        Ok(QueryBorrow {
+            query: QueryMut {
+                epoch: self.epoch,
+                archetypes: self.archetypes,
+                query: PhantomData,
+                filter: (),
+            },
+            borrow,
+        })
+    }
+
+    /// Acquires borrows of all components accessed by the query.
+    fn borrow<Q>(&self) -> Result<Borrow<'_>, QueryConflict>
+    where
+        Q: Query,
+    {
+        let mut ids = SmallVec::new();
+        for archetype in self.archetypes {
+            for info in archetype.infos() {
+                let mutable = match Q::access(info.id) {
+                    Access::None => continue,
+                    Access::Shared => false,
+                    Access::Mutable => true,
+                };
+                if !ids.iter().any(|&(id, _)| id == info.id) {
+                    ids.push((info.id, mutable));
+                }
+            }
+        }
+
+        let mut borrows = self.borrows.borrow_mut();
+        for &(id, mutable) in &ids {
+            let state = borrows.get(&id).copied().unwrap_or(0);
+            if state < 0 || (mutable && state > 0) {
+                return Err(QueryConflict);
+            }
+        }
+
+        for &(id, mutable) in &ids {
+            let state = borrows.entry(id).or_insert(0);
+            match mutable {
+                true => *state = -1,
+                false => *state += 1,
+            }
+        }
+
+        Ok(Borrow {
+            borrows: &self.borrows,
+            ids,
+        })
+    }
+}
+
+/// Components borrowed by a query.
+/// Released on drop.
+struct Borrow<'a> {
+    borrows: &'a RefCell<HashMap<TypeId, isize, NoOpHasherBuilder>>,
+    ids: SmallVec<[(TypeId, bool); 8]>,
+}
+
+impl Drop for Borrow<'_> {
+    fn drop(&mut self) {
+        let mut borrows = self.borrows.borrow_mut();
+        for &(id, mutable) in &self.ids {
+            let state = borrows.get_mut(&id).unwrap();
+            match mutable {
+                true => *state = 0,
+                false => *state -= 1,
+            }
+        }
+    }
+}
+
+/// Query borrowed from [`WorldCell`].
+/// Wraps [`QueryRef`] or [`QueryMut`].
+///
+/// Components accessed by the query stay borrowed until it is dropped.
+/// Iterators borrow `QueryBorrow`, so they cannot outlive it.
+pub struct QueryBorrow<'a, T> {
+    query: T,
+    borrow: Borrow<'a>,
+}
+
+impl<T> fmt::Debug for QueryBorrow<'_, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryBorrow")
+            .field("query", &self.query)
+            .field("components", &self.borrow.ids.len())
+            .finish()
+    }
+}
+
+impl<'a, Q, F> QueryBorrow<'a, QueryRef<'a, Q, F>>
+where
+    Q: Query,
+    F: Filter + Clone,
+{
+    /// Returns iterator over query results.
+    /// This method is only available with non-tracking queries.
+    #[inline]
+    pub fn iter<'b>(&'b self) -> QueryIter<'b, Q, F>
+    where
+        Q: NonTrackingQuery + ImmutableQuery,
+    {
+        self.query.iter()
+    }
+
+    /// Returns iterator over query results.
+    /// This method is available with tracking queries.
+    #[inline]
+    pub fn tracked_iter<'b>(&'b self, tracks: &mut Tracks) -> QueryTrackedIter<'b, Q, F>
+    where
+        Q: ImmutableQuery,
+    {
+        self.query.tracked_iter(tracks)
+    }
+}
+
+impl<'a, Q, F> QueryBorrow<'a, QueryMut<'a, Q, F>>
+where
+    Q: Query,
+    F: Filter + Clone,
+{
+    /// Returns iterator over immutable query results.
+    /// This method is only available with non-tracking queries.
+    #[inline]
+    pub fn iter<'b>(&'b self) -> QueryIter<'b, Q, F>
+    where
+        Q: NonTrackingQuery + ImmutableQuery,
+    {
+        self.query.iter()
+    }
+
+    /// Returns iterator over query results.
+    /// This method is only available with non-tracking queries.
+    #[inline]
+    pub fn iter_mut<'b>(&'b mut self) -> QueryIter<'b, Q, F>
+    where
+        Q: NonTrackingQuery,
+    {
+        self.query.iter_mut()
+    }
+
+    /// Returns iterator over immutable query results.
+    /// This method is available with tracking queries.
+    #[inline]
+    pub fn tracked_iter<'b>(&'b self, tracks: &mut Tracks) -> QueryTrackedIter<'b, Q, F>
+    where
+        Q: ImmutableQuery,
+    {
+        self.query.tracked_iter(tracks)
+    }
+
+    /// Returns iterator over query results.
+    /// This method is available with tracking queries.
+    #[inline]
+    pub fn tracked_iter_mut<'b>(&'b mut self, tracks: &mut Tracks) -> QueryTrackedIter<'b, Q, F> {
+        self.query.tracked_iter_mut(tracks)
+    }
+}
+
+/// Error returned by [`WorldCell::try_query`] and [`WorldCell::try_query_mut`]
+/// in case query accesses component that is mutably borrowed,
+/// or mutably accesses component that is borrowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct QueryConflict;
+
+impl fmt::Display for QueryConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Query conflicts with borrowed query")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QueryConflict {}
@@ -0,0 +1,21 @@
+/// Capacities of the `World` allocated up front.
+/// Used with [`World::with_capacity`].
+///
+/// `World` created with `WorldConfig` never grows storage of entities and components.
+/// Operations that would need more space fail with [`CapacityExceeded`]
+/// or panic if they have no way to report an error.
+///
+/// [`World::with_capacity`]: `edict::world::World::with_capacity`
+/// [`CapacityExceeded`]: `edict::world::CapacityExceeded`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldConfig {
+    /// Maximum number of entities alive at once.
+    pub max_entities: usize,
+
+    /// Maximum number of archetypes,
+    /// including archetype of entities without components.
+    pub max_archetypes: usize,
+
+    /// Maximum number of entities in one archetype.
+    pub archetype_capacity: usize,
+}
@@ -0,0 +1,78 @@
+use core::fmt;
+
+use crate::{archetype::Archetype, component::ComponentInfo};
+
+use super::EntityRef;
+
+/// Formats entity with values of its components.
+/// Returned by [`World::debug_entity`].
+///
+/// Only components registered with [`World::register_debug`]
+/// are printed with their values.
+///
+/// [`World::debug_entity`]: `edict::world::World::debug_entity`
+/// [`World::register_debug`]: `edict::world::World::register_debug`
+#[derive(Clone, Copy)]
+pub struct DebugEntity<'a> {
+    pub(super) entity: EntityRef<'a>,
+}
+
+impl fmt::Debug for DebugEntity<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ds = f.debug_struct("Entity");
+        ds.field("id", &self.entity.id());
+        for info in self.entity.components() {
+            let ptr = self.entity.component_ptr(info.id).unwrap();
+            ds.field(
+                info.debug_name,
+                &DebugComponent {
+                    info,
+                    ptr: ptr.as_ptr(),
+                },
+            );
+        }
+        ds.finish()
+    }
+}
+
+/// Formats all entities in the world with values of their components.
+/// Returned by [`World::dump`].
+///
+/// [`World::dump`]: `edict::world::World::dump`
+#[derive(Clone, Copy)]
+pub struct WorldDump<'a> {
+    pub(super) archetypes: &'a [Archetype],
+}
+
+impl fmt::Debug for WorldDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut dl = f.debug_list();
+        for (archetype_idx, archetype) in self.archetypes.iter().enumerate() {
+            for (idx, id) in archetype.entities().iter().enumerate() {
+                dl.entry(&DebugEntity {
+                    entity: EntityRef {
+                        id: *id,
+                        archetype_idx: archetype_idx as u32,
+                        idx: idx as u32,
+                        archetype,
+                    },
+                });
+            }
+        }
+        dl.finish()
+    }
+}
+
+struct DebugComponent<'a> {
+    info: &'a ComponentInfo,
+    ptr: *const u8,
+}
+
+impl fmt::Debug for DebugComponent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.info.debug_fmt {
+            None => f.write_str(".."),
+            Some(debug_fmt) => unsafe { debug_fmt(self.ptr, f) },
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use core::any::TypeId;
+
+use alloc::vec::Vec;
+
+use crate::{
+    allocator::Allocator,
+    archetype::{first_of_chunk, CHUNK_LEN},
+    delta::{write_header, DeltaError, DeltaReader, Registry, DESPAWN, REMOVE, SET},
+    entity::{EntityId, EntityMap},
+};
+
+use super::{Tracks, World};
+
+/// Structural change recorded for deltas.
+#[derive(Clone, Copy, Debug)]
+struct LoggedChange {
+    epoch: u64,
+    entity: EntityId,
+
+    /// Removed component type, or `None` if entity is despawned.
+    removed: Option<TypeId>,
+}
+
+/// Log of despawns and removals for deltas.
+/// Disabled until enabled with [`World::enable_delta_log`] or deltas are written.
+#[derive(Debug, Default)]
+pub(super) struct DeltaLog {
+    changes: Option<Vec<LoggedChange>>,
+
+    /// Epochs of `Tracks` updated by `write_delta` and not yet released.
+    /// Changes not newer than all of them are dropped.
+    outstanding: Vec<u64>,
+}
+
+impl DeltaLog {
+    /// Returns `true` if changes are recorded.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.changes.is_some()
+    }
+
+    /// Records despawn of the entity if log is enabled.
+    /// Epoch is advanced, so that change is newer than `Tracks` created before.
+    #[inline]
+    pub fn despawn(&mut self, epoch: &mut u64, entity: EntityId) {
+        if let Some(changes) = &mut self.changes {
+            *epoch += 1;
+            changes.push(LoggedChange {
+                epoch: *epoch,
+                entity,
+                removed: None,
+            });
+        }
+    }
+
+    /// Records removal of the component if log is enabled.
+    /// Epoch is advanced, so that change is newer than `Tracks` created before.
+    #[inline]
+    pub fn remove(&mut self, epoch: &mut u64, entity: EntityId, id: TypeId) {
+        if let Some(changes) = &mut self.changes {
+            *epoch += 1;
+            changes.push(LoggedChange {
+                epoch: *epoch,
+                entity,
+                removed: Some(id),
+            });
+        }
+    }
+
+    /// Stops keeping changes for `Tracks` at specified epoch.
+    fn release(&mut self, epoch: u64) {
+        if let Some(idx) = self.outstanding.iter().position(|&e| e == epoch) {
+            self.outstanding.swap_remove(idx);
+        }
+    }
+
+    /// Drops changes that are not newer than oldest outstanding `Tracks`.
+    fn trim(&mut self) {
+        let oldest = match self.outstanding.iter().min() {
+            None => return,
+            Some(&oldest) => oldest,
+        };
+        if let Some(changes) = &mut self.changes {
+            changes.retain(|change| change.epoch > oldest);
+        }
+    }
+}
+
+impl<A: Allocator> World<A> {
+    /// Starts recording despawns and removals for [`World::write_delta`].
+    ///
+    /// Log is enabled by the first call to [`World::write_delta`] as well.
+    /// Despawns and removals made before log is enabled are never written,
+    /// so this function should be called before any `Tracks` used for deltas is created.
+    pub fn enable_delta_log(&mut self) {
+        self.delta_log.changes.get_or_insert_with(Vec::new);
+    }
+
+    /// Writes changes made to components registered in `registry` since `tracks` into `out`.
+    ///
+    /// Delta contains despawns of entities, removals of components
+    /// and values of components inserted or modified since `tracks`.
+    /// Entities are spawned on the reading side with their first replicated component.
+    /// Components that are not modified are skipped per chunk,
+    /// so idle entities do not add to the cost.
+    ///
+    /// Despawns and removals are recorded after [`World::enable_delta_log`]
+    /// or the first call to this function.
+    /// Records are kept until every `Tracks` updated by this function is updated past them.
+    /// `Tracks` that won't be used with this function again
+    /// must be released with [`World::release_delta_tracks`], otherwise records are kept for it.
+    pub fn write_delta(&mut self, tracks: &mut Tracks, registry: &Registry<A>, out: &mut Vec<u8>) {
+        let log = self.delta_log.changes.get_or_insert_with(Vec::new);
+
+        for change in log.iter().filter(|change| change.epoch > tracks.epoch) {
+            match change.removed {
+                None => write_header(out, DESPAWN, &change.entity),
+                Some(id) => {
+                    if let Some(idx) = registry.index(id) {
+                        write_header(out, REMOVE, &change.entity);
+                        out.extend_from_slice(&idx.to_le_bytes());
+                    }
+                }
+            }
+        }
+
+        for archetype in &self.archetypes {
+            for info in archetype.infos() {
+                let idx = match registry.index(info.id) {
+                    None => continue,
+                    Some(idx) => idx,
+                };
+
+                let data = unsafe { archetype.data(archetype.id_index(info.id).unwrap()) };
+                if unsafe { *data.version.get() } <= tracks.epoch {
+                    continue;
+                }
+
+                let size = data.layout.size();
+                let mut entity_idx = 0;
+                while entity_idx < archetype.len() {
+                    if let Some(chunk_idx) = first_of_chunk(entity_idx) {
+                        let chunk_version = unsafe { *data.chunk_versions.as_ptr().add(chunk_idx) };
+                        if chunk_version <= tracks.epoch {
+                            entity_idx += CHUNK_LEN;
+                            continue;
+                        }
+                    }
+
+                    let version = unsafe { *data.entity_versions.as_ptr().add(entity_idx) };
+                    if version > tracks.epoch {
+                        write_header(out, SET, &archetype.entities()[entity_idx]);
+                        out.extend_from_slice(&idx.to_le_bytes());
+
+                        let len_at = out.len();
+                        out.extend_from_slice(&0u32.to_le_bytes());
+                        unsafe {
+                            registry.encode(idx, data.ptr.as_ptr().add(entity_idx * size), out)
+                        };
+                        let len = (out.len() - len_at - 4) as u32;
+                        out[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+                    }
+
+                    entity_idx += 1;
+                }
+            }
+        }
+
+        self.delta_log.release(tracks.epoch);
+        self.delta_log.outstanding.push(self.epoch);
+        self.delta_log.trim();
+
+        tracks.epoch = self.epoch;
+
+        // Changes made after this call must be newer than `tracks`.
+        self.epoch += 1;
+    }
+
+    /// Applies delta written by [`World::write_delta`] of another `World`.
+    ///
+    /// `map` maps entities of the writing `World` to entities of this `World`.
+    /// Entities missing from `map` are spawned and added to it,
+    /// despawned entities are removed from it.
+    /// Components registered with [`World::register_map_entities`]
+    /// are remapped with `map` after the whole delta is applied.
+    ///
+    /// `registry` must have same component types registered in the same order
+    /// as one used to write the delta.
+    ///
+    /// Records that precede malformed one are applied.
+    pub fn apply_delta(
+        &mut self,
+        registry: &Registry<A>,
+        delta: &[u8],
+        map: &mut EntityMap,
+    ) -> Result<(), DeltaError> {
+        let mut reader = DeltaReader::new(delta);
+        let mut applied = Vec::new();
+
+        let result = loop {
+            if reader.is_empty() {
+                break Ok(());
+            }
+
+            if let Err(err) = self.apply_record(registry, &mut reader, map, &mut applied) {
+                break Err(err);
+            }
+        };
+
+        for (entity, id) in applied {
+            self.map_component_entities(&entity, id, map);
+        }
+
+        result
+    }
+
+    fn apply_record(
+        &mut self,
+        registry: &Registry<A>,
+        reader: &mut DeltaReader<'_>,
+        map: &mut EntityMap,
+        applied: &mut Vec<(EntityId, TypeId)>,
+    ) -> Result<(), DeltaError> {
+        let tag = reader.read_u8()?;
+        let entity = reader.read_entity(tag)?;
+
+        match tag {
+            DESPAWN => {
+                if let Some(local) = map.remove(&entity) {
+                    let _ = self.despawn(&local);
+                }
+            }
+            REMOVE => {
+                let idx = reader.read_u32()?;
+                if let Some(local) = map.get(&entity) {
+                    registry.remove(idx, self, &local)?;
+                }
+            }
+            SET => {
+                let idx = reader.read_u32()?;
+                let len = reader.read_u32()?;
+                let bytes = reader.read_bytes(len as usize)?;
+
+                let local = match map.get(&entity) {
+                    Some(local) if self.is_alive(&local) => local,
+                    _ => {
+                        let local = self.spawn(());
+                        map.insert(entity, local);
+                        local
+                    }
+                };
+
+                let id = registry.apply(idx, self, &local, bytes)?;
+                applied.push((local, id));
+            }
+            tag => return Err(DeltaError::InvalidRecord { tag }),
+        }
+
+        Ok(())
+    }
+
+    /// Rewrites entity ids in the component of the entity
+    /// if component type is registered with [`World::register_map_entities`].
+    fn map_component_entities(&mut self, entity: &EntityId, id: TypeId, map: &EntityMap) {
+        let map_entities = match self.registry.get(&id).and_then(|info| info.map_entities) {
+            None => return,
+            Some(map_entities) => map_entities,
+        };
+
+        if let Ok(ptr) = self.component_ptr(entity, id) {
+            unsafe { map_entities(ptr.as_ptr(), map) }
+        }
+    }
+
+    /// Stops keeping recorded despawns and removals for `tracks`
+    /// updated by [`World::write_delta`].
+    ///
+    /// Should be called when `tracks` won't be used with [`World::write_delta`] again,
+    /// for example when client disconnects.
+    pub fn release_delta_tracks(&mut self, tracks: &Tracks) {
+        self.delta_log.release(tracks.epoch);
+        self.delta_log.trim();
+    }
+
+    /// Drops recorded despawns and removals
+    /// that are not newer than specified `Tracks`.
+    ///
+    /// Records are dropped automatically as `Tracks` are updated by [`World::write_delta`],
+    /// this function drops them regardless of `Tracks` that were not updated yet.
+    pub fn trim_delta_log(&mut self, tracks: &Tracks) {
+        if let Some(log) = &mut self.delta_log.changes {
+            log.retain(|change| change.epoch > tracks.epoch);
+        }
+    }
+}
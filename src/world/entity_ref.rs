@@ -0,0 +1,70 @@
+use core::{any::TypeId, ptr::NonNull};
+
+use crate::{archetype::Archetype, component::ComponentInfo, entity::EntityId};
+
+/// Read-only view of a single entity in the `World`.
+///
+/// Allows inspecting set of components the entity has
+/// without knowing their types statically.
+/// Returned by [`World::entity`].
+///
+/// [`World::entity`]: `edict::world::World::entity`
+#[derive(Clone, Copy, Debug)]
+pub struct EntityRef<'a> {
+    pub(super) id: EntityId,
+    pub(super) archetype_idx: u32,
+    pub(super) idx: u32,
+    pub(super) archetype: &'a Archetype,
+}
+
+impl<'a> EntityRef<'a> {
+    /// Returns id of the entity.
+    #[inline]
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    /// Returns index of the archetype the entity belongs to.
+    ///
+    /// Entities with same set of components share archetype.
+    /// Index stays the same until entity's set of components changes.
+    #[inline]
+    pub fn archetype_id(&self) -> u32 {
+        self.archetype_idx
+    }
+
+    /// Returns iterator over infos of all components the entity has.
+    #[inline]
+    pub fn components(&self) -> impl ExactSizeIterator<Item = &'a ComponentInfo> + Clone + 'a {
+        self.archetype.infos()
+    }
+
+    /// Checks if the entity has component of specified type.
+    #[inline]
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.contains_id(TypeId::of::<T>())
+    }
+
+    /// Checks if the entity has component with specified type id.
+    #[inline]
+    pub fn contains_id(&self, type_id: TypeId) -> bool {
+        self.archetype.contains_id(type_id)
+    }
+
+    /// Returns reference to the component of specified type.
+    /// Returns `None` if entity does not have component of that type.
+    #[inline]
+    pub fn get<T: 'static>(&self) -> Option<&'a T> {
+        let ptr = self.component_ptr(TypeId::of::<T>())?;
+        Some(unsafe { &*ptr.as_ptr().cast::<T>() })
+    }
+
+    /// Returns type-erased pointer to the component with specified type id.
+    /// Returns `None` if entity does not have component with that id.
+    ///
+    /// Pointer is valid for reads while this `EntityRef` borrows the `World`.
+    #[inline]
+    pub fn component_ptr(&self, type_id: TypeId) -> Option<NonNull<u8>> {
+        self.archetype.component_ptr(self.idx, type_id)
+    }
+}
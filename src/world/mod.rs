@@ -2,44 +2,76 @@
 
 use core::{
     any::{type_name, TypeId},
+    cell::{Cell, RefCell},
+    cmp::Ordering,
     fmt,
     hash::{BuildHasher, Hash, Hasher},
     iter::FromIterator,
     iter::FusedIterator,
     marker::PhantomData,
+    ops::RangeBounds,
+    ptr::{self, NonNull},
 };
 
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 use hashbrown::{
     hash_map::{Entry, RawEntryMut},
-    HashMap,
+    HashMap, HashSet,
 };
+use smallvec::SmallVec;
 
+#[cfg(feature = "rc")]
+use crate::{allocator::AllocVec, entity::Entity, proof::Proof};
 use crate::{
-    archetype::{chunk_idx, Archetype, CHUNK_LEN_USIZE},
-    bundle::{Bundle, DynamicBundle},
-    component::{Component, ComponentInfo},
-    entity::{Entities, EntityId},
+    allocator::{Allocator, Global, SharedAllocator},
+    archetype::{chunk_idx, Archetype, SharedValues, StorageConfig, CHUNK_LEN},
+    bundle::{Bundle, DynamicBundle, EntityBuilder},
+    component::{Component, ComponentInfo, SharedComponent},
+    entity::{Entities, EntityId, EntityMap, MapEntities},
+    event::{AnyEvents, EventReader, Events},
     hash::{MulHasherBuilder, NoOpHasherBuilder},
     idx::MAX_IDX_USIZE,
+    index::{Index, Indices, OrderedIndex},
+    prefab::Prefab,
     query::{
-        Fetch, Filter, ImmutableQuery, NonTrackingQuery, Query, QueryItem, QueryIter,
-        QueryTrackedIter, With, Without,
+        assert_valid, Fetch, Filter, ImmutableQuery, NoAlias, NonTrackingQuery, Query, QueryItem,
+        QueryIter, QueryTrackedIter, With, Without,
     },
 };
-#[cfg(feature = "rc")]
-use crate::{entity::Entity, proof::Proof};
 
-pub use self::{meta::EntityMeta, tracks::Tracks};
+#[cfg(feature = "leak-detection")]
+use crate::entity::LeakedEntity;
+
+pub use self::{
+    cell::{QueryBorrow, QueryConflict, WorldCell},
+    config::WorldConfig,
+    debug::{DebugEntity, WorldDump},
+    entity_ref::EntityRef,
+    meta::EntityMeta,
+    tracks::Tracks,
+};
+
+use self::{delta::DeltaLog, observers::Observers};
 
 // mod archetypes;
+mod cell;
+mod config;
+mod debug;
+mod delta;
+mod entity_ref;
 mod meta;
+mod observers;
+#[cfg(feature = "spatial")]
+mod spatial;
 mod tracks;
 
 /// Limits on reserving of space for entities and components
 /// in archetypes when `spawn_batch` is used.
 const MAX_SPAWN_RESERVE: usize = 1024;
 
+/// Inline capacity of the queue of dropped entities.
+const DROP_QUEUE_INLINE_CAP: usize = 1024;
+
 fn spawn_reserve(iter: &impl Iterator, archetype: &mut Archetype) {
     let (lower, upper) = iter.size_hint();
     let additional = match (lower, upper) {
@@ -97,8 +129,22 @@ struct RemoveBundleInfo {
 /// maps entity to location of components in archetypes,
 /// moves components of entities between archetypes,
 /// spawns and despawns entities.
+///
+/// `World` is neither `Send` nor `Sync`, so components that are not thread-safe,
+/// like `Rc`, are never accessed from thread other than one they were inserted on.
+///
+/// ```compile_fail,E0277
+/// fn assert_send<T: Send>() {}
+/// assert_send::<edict::world::World>();
+/// ```
+///
+/// Component columns and entity lists of archetypes, entity locations,
+/// shared data of entities and drop queue are allocated with `A`.
+/// Bookkeeping collections, such as list of archetypes, lookup caches,
+/// component registry, events and indices, use global allocator,
+/// so global allocator must be available even when `A` is not [`Global`].
 #[derive(Debug)]
-pub struct World {
+pub struct World<A: Allocator = Global> {
     /// Global epoch counter of the World.
     /// Incremented on each mutable query.
     epoch: u64,
@@ -133,14 +179,66 @@ pub struct World {
     /// Maps archetype index + removed component ids list to archetype.
     sub_ids: HashMap<(u32, Vec<TypeId>), RemoveBundleInfo, MulHasherBuilder>,
 
+    /// Infos of component types registered with additional glue functions.
+    registry: ComponentRegistry,
+
+    /// Despawns and removals recorded for deltas.
+    delta_log: DeltaLog,
+
+    /// Event queues by event type.
+    events: HashMap<TypeId, Box<dyn AnyEvents>, NoOpHasherBuilder>,
+
+    /// Callbacks fired on entity spawn and despawn.
+    observers: Observers,
+
+    /// Secondary indices over component values.
+    indices: Indices,
+
     /// Array of indices to drop.
     #[cfg(feature = "rc")]
-    drop_queue: Vec<u32>,
+    drop_queue: AllocVec<u32>,
+
+    /// Maximum number of cascade levels despawned in one maintenance.
+    #[cfg(feature = "rc")]
+    cascade_depth: usize,
+
+    /// Configuration of archetypes storage.
+    storage: StorageConfig,
+
+    /// Number of archetypes in canonical order.
+    /// Used only in deterministic mode.
+    canonical_len: usize,
+
+    marker: PhantomData<A>,
+
+    /// Keeps `World` neither `Send` nor `Sync` regardless of its fields.
+    /// Components are not required to be thread-safe,
+    /// so `World` must stay on the thread components were inserted on.
+    /// Do not add `unsafe impl Send` for `World` without bounding components.
+    not_send: PhantomData<*const ()>,
+}
+
+impl<A: Allocator> Drop for World<A> {
+    fn drop(&mut self) {
+        if self.observers.despawn.is_empty() {
+            return;
+        }
+
+        for (archetype_idx, archetype) in self.archetypes.iter().enumerate() {
+            for (idx, &entity) in archetype.entities().iter().enumerate() {
+                self.observers
+                    .despawned(entity, archetype_idx as u32, idx as u32, archetype);
+            }
+        }
+    }
 }
 
-impl Default for World {
+impl<A> Default for World<A>
+where
+    A: Allocator + Default,
+{
     fn default() -> Self {
-        World::new()
+        World::new_in(A::default())
     }
 }
 
@@ -154,12 +252,69 @@ impl World {
     /// as each cache entry would be calculated on first use of each key.
     #[inline]
     pub fn new() -> Self {
+        World::new_in(Global)
+    }
+
+    /// Returns new instance of `World` with storage allocated up front.
+    ///
+    /// Created `World` never reallocates storage of entities and components,
+    /// list of archetypes and drop queue.
+    /// Operations that spawn or move entities and need more space than configured
+    /// fail with `CapacityExceeded` error.
+    /// Use [`World::try_spawn`] family of functions to handle this error,
+    /// other functions panic instead.
+    ///
+    /// Caches of transitions between archetypes and other bookkeeping collections
+    /// are not sized up front and may allocate when new transition is used first time.
+    #[inline]
+    pub fn with_capacity(config: WorldConfig) -> Self {
+        World::with_capacity_in(config, Global)
+    }
+}
+
+impl<A> World<A>
+where
+    A: Allocator,
+{
+    /// Returns new instance of `World` that allocates memory
+    /// for components and entities with specified allocator.
+    ///
+    /// Bookkeeping collections of the `World` still use global allocator.
+    ///
+    /// Created `World` instance contains no entities.
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        World::with_storage(alloc, None, DROP_QUEUE_INLINE_CAP)
+    }
+
+    /// Returns new instance of `World` with storage allocated up front
+    /// with specified allocator.
+    ///
+    /// See [`World::with_capacity`].
+    pub fn with_capacity_in(config: WorldConfig, alloc: A) -> Self {
+        // Drop queue fits all entities and never moves them to its growable tail.
+        let drop_queue_cap = (config.max_entities + 1).max(DROP_QUEUE_INLINE_CAP);
+
+        let mut world = World::with_storage(alloc, Some(config.archetype_capacity), drop_queue_cap);
+        world.entities.fix_capacity(config.max_entities);
+        world.archetypes.reserve_exact(config.max_archetypes);
+        world.keys.reserve(config.max_archetypes);
+        world.ids.reserve(config.max_archetypes);
+        #[cfg(feature = "rc")]
+        world.drop_queue.reserve_exact(config.max_entities);
+        world
+    }
+
+    #[cfg_attr(not(feature = "rc"), allow(unused_variables))]
+    fn with_storage(alloc: A, fixed_capacity: Option<usize>, drop_queue_cap: usize) -> Self {
+        let alloc = SharedAllocator::new(alloc);
+
         World {
             epoch: 0,
             #[cfg(feature = "rc")]
-            entities: Entities::new(1024),
+            entities: Entities::new(drop_queue_cap, alloc.clone()),
             #[cfg(not(feature = "rc"))]
-            entities: Entities::new(),
+            entities: Entities::new(alloc.clone()),
             archetypes: Vec::new(),
             keys: HashMap::with_hasher(NoOpHasherBuilder),
             ids: HashMap::with_hasher(MulHasherBuilder),
@@ -169,15 +324,50 @@ impl World {
             sub_one: HashMap::with_hasher(MulHasherBuilder),
             sub_key: HashMap::with_hasher(MulHasherBuilder),
             sub_ids: HashMap::with_hasher(MulHasherBuilder),
+            registry: HashMap::with_hasher(NoOpHasherBuilder),
+            delta_log: DeltaLog::default(),
+            events: HashMap::with_hasher(NoOpHasherBuilder),
+            observers: Observers::default(),
+            indices: Indices::default(),
             #[cfg(feature = "rc")]
-            drop_queue: Vec::new(),
+            drop_queue: AllocVec::new(alloc.clone()),
+            #[cfg(feature = "rc")]
+            cascade_depth: usize::MAX,
+            storage: StorageConfig {
+                alloc,
+                fixed_capacity,
+                deterministic: false,
+            },
+            canonical_len: 0,
+            marker: PhantomData,
+            not_send: PhantomData,
         }
     }
 
     /// Spawns new entity in this world with provided bundle of components.
     /// World keeps ownership of the spawned entity and entity id is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity.
     #[inline]
     pub fn spawn<B>(&mut self, bundle: B) -> EntityId
+    where
+        B: DynamicBundle,
+    {
+        match self.try_spawn(bundle) {
+            Ok(entity) => entity,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Spawns new entity in this world with provided bundle of components.
+    /// World keeps ownership of the spawned entity and entity id is returned.
+    ///
+    /// If `World` with fixed capacity has no space for the entity,
+    /// fails with `Err(CapacityExceeded)`.
+    #[inline]
+    pub fn try_spawn<B>(&mut self, bundle: B) -> Result<EntityId, CapacityExceeded>
     where
         B: DynamicBundle,
     {
@@ -188,22 +378,49 @@ impl World {
             );
         }
 
+        let archetype_idx = self.spawn_archetype_idx(&bundle)?;
         let entity = self.entities.spawn();
 
-        let archetype_idx =
-            cached_archetype_idx(&mut self.keys, &mut self.ids, &mut self.archetypes, &bundle);
-
         self.epoch += 1;
         let idx = self.archetypes[archetype_idx as usize].spawn(entity, bundle, self.epoch);
         self.entities.set_location(entity.idx, archetype_idx, idx);
-        entity
+        self.observers.spawned(
+            entity,
+            archetype_idx,
+            idx,
+            &self.archetypes[archetype_idx as usize],
+        );
+        Ok(entity)
     }
 
     /// Spawns new entity in this world with provided bundle of components.
     /// Returns owning reference to the entity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity.
     #[cfg(feature = "rc")]
     #[inline]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
     pub fn spawn_owning<B>(&mut self, bundle: B) -> Entity
+    where
+        B: DynamicBundle,
+    {
+        match self.try_spawn_owning(bundle) {
+            Ok(entity) => entity,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Spawns new entity in this world with provided bundle of components.
+    /// Returns owning reference to the entity.
+    ///
+    /// If `World` with fixed capacity has no space for the entity,
+    /// fails with `Err(CapacityExceeded)`.
+    #[cfg(feature = "rc")]
+    #[inline]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
+    pub fn try_spawn_owning<B>(&mut self, bundle: B) -> Result<Entity, CapacityExceeded>
     where
         B: DynamicBundle,
     {
@@ -214,16 +431,68 @@ impl World {
             );
         }
 
+        let archetype_idx = self.spawn_archetype_idx(&bundle)?;
         let entity = self.entities.spawn_owning();
 
-        let archetype_idx =
-            cached_archetype_idx(&mut self.keys, &mut self.ids, &mut self.archetypes, &bundle);
-
         self.epoch += 1;
         let idx = self.archetypes[archetype_idx as usize].spawn(*entity, bundle, self.epoch);
         self.entities.set_location(entity.idx, archetype_idx, idx);
+        self.observers.spawned(
+            *entity,
+            archetype_idx,
+            idx,
+            &self.archetypes[archetype_idx as usize],
+        );
+
+        Ok(entity)
+    }
+
+    /// Returns index of archetype for entity with components from the bundle.
+    /// Fails if `World` with fixed capacity has no space for new entity in it.
+    fn spawn_archetype_idx<B>(&mut self, bundle: &B) -> Result<u32, CapacityExceeded>
+    where
+        B: DynamicBundle,
+    {
+        if self.entities.is_full() {
+            return Err(CapacityExceeded);
+        }
+
+        self.check_archetypes_capacity(|archetype| {
+            archetype.shared_values().is_empty()
+                && bundle.with_ids(|ids| archetype.matches(ids.iter().copied()))
+        })?;
+
+        let archetype_idx = cached_archetype_idx(
+            &mut self.keys,
+            &mut self.ids,
+            &self.registry,
+            &mut self.archetypes,
+            &self.storage,
+            bundle,
+        );
+        let archetype_idx = self.canonical_idx(archetype_idx);
+
+        if self.archetypes[archetype_idx as usize].is_full() {
+            return Err(CapacityExceeded);
+        }
 
-        entity
+        Ok(archetype_idx)
+    }
+
+    /// Fails if list of archetypes has fixed capacity and is full,
+    /// unless archetype for which `matches` returns `true` already exists.
+    fn check_archetypes_capacity(
+        &self,
+        matches: impl Fn(&Archetype) -> bool,
+    ) -> Result<(), CapacityExceeded> {
+        if self.storage.fixed_capacity.is_none()
+            || self.archetypes.len() < self.archetypes.capacity()
+            || self.archetypes.iter().any(matches)
+        {
+            Ok(())
+        } else {
+            Err(CapacityExceeded)
+        }
     }
 
     /// Returns an iterator which spawns and yield entities
@@ -259,14 +528,18 @@ impl World {
         let archetype_idx = cached_archetype_idx(
             &mut self.keys,
             &mut self.ids,
+            &self.registry,
             &mut self.archetypes,
+            &self.storage,
             &PhantomData::<I::Item>,
         );
+        let archetype_idx = self.canonical_idx(archetype_idx);
 
         self.epoch += 1;
 
         let archetype = &mut self.archetypes[archetype_idx as usize];
         let entities = &mut self.entities;
+        let observers = &mut self.observers;
         let epoch = self.epoch;
 
         SpawnBatch {
@@ -275,6 +548,7 @@ impl World {
             archetype_idx,
             archetype,
             entities,
+            observers,
         }
     }
 
@@ -312,14 +586,18 @@ impl World {
         let archetype_idx = cached_archetype_idx(
             &mut self.keys,
             &mut self.ids,
+            &self.registry,
             &mut self.archetypes,
+            &self.storage,
             &PhantomData::<I::Item>,
         );
+        let archetype_idx = self.canonical_idx(archetype_idx);
 
         self.epoch += 1;
 
         let archetype = &mut self.archetypes[archetype_idx as usize];
         let entities = &mut self.entities;
+        let observers = &mut self.observers;
         let epoch = self.epoch;
 
         SpawnBatchOwned {
@@ -328,6 +606,7 @@ impl World {
             archetype_idx,
             archetype,
             entities,
+            observers,
         }
     }
 
@@ -335,11 +614,25 @@ impl World {
     #[cfg(feature = "rc")]
     pub fn despawn(&mut self, entity: &EntityId) -> Result<(), OwnershipError> {
         let (archetype, idx) = self.entities.despawn(entity)?;
+        self.delta_log.despawn(&mut self.epoch, *entity);
+        self.indices.despawn(*entity);
+        self.observers.despawned(
+            *entity,
+            archetype,
+            idx,
+            &self.archetypes[archetype as usize],
+        );
 
         let opt_id = unsafe { self.archetypes[archetype as usize].despawn_unchecked(idx) };
         if let Some(id) = opt_id {
             self.entities.set_location(id, archetype, idx)
         }
+        restore_order(
+            &mut self.entities,
+            &mut self.archetypes[archetype as usize],
+            archetype,
+            idx,
+        );
 
         Ok(())
     }
@@ -348,11 +641,25 @@ impl World {
     #[cfg(not(feature = "rc"))]
     pub fn despawn(&mut self, entity: &EntityId) -> Result<(), NoSuchEntity> {
         let (archetype, idx) = self.entities.despawn(entity)?;
+        self.delta_log.despawn(&mut self.epoch, *entity);
+        self.indices.despawn(*entity);
+        self.observers.despawned(
+            *entity,
+            archetype,
+            idx,
+            &self.archetypes[archetype as usize],
+        );
 
         let opt_id = unsafe { self.archetypes[archetype as usize].despawn_unchecked(idx) };
         if let Some(id) = opt_id {
             self.entities.set_location(id, archetype, idx)
         }
+        restore_order(
+            &mut self.entities,
+            &mut self.archetypes[archetype as usize],
+            archetype,
+            idx,
+        );
 
         Ok(())
     }
@@ -379,17 +686,18 @@ impl World {
     /// old component value is replaced with new one.
     /// Otherwise new component is added to the entity.
     ///
-    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    /// If entity is not alive, fails with `Err(InsertError::NoSuchEntity)`.
+    /// If `World` with fixed capacity has no space for the entity with new component,
+    /// fails with `Err(InsertError::CapacityExceeded)`.
     #[inline]
-    pub fn try_insert<T>(&mut self, entity: &EntityId, component: T) -> Result<(), NoSuchEntity>
+    pub fn try_insert<T>(&mut self, entity: &EntityId, component: T) -> Result<(), InsertError>
     where
         T: Component,
     {
         let (archetype, idx) = self.entities.get(entity).ok_or(NoSuchEntity)?;
 
-        self.epoch += 1;
-
         if self.archetypes[archetype as usize].contains_id(TypeId::of::<T>()) {
+            self.epoch += 1;
             unsafe {
                 self.archetypes[archetype as usize].set(idx, component, self.epoch);
             }
@@ -397,11 +705,27 @@ impl World {
             return Ok(());
         }
 
-        let insert_info =
-            cached_insert_info::<T>(&mut self.add_one, &mut self.archetypes, archetype);
+        let src = &self.archetypes[archetype as usize];
+        self.check_archetypes_capacity(|a| {
+            a.shared_values() == src.shared_values()
+                && a.matches(src.ids().chain(Some(TypeId::of::<T>())))
+        })?;
+
+        let insert_info = cached_insert_info::<T>(
+            &mut self.add_one,
+            &self.registry,
+            &mut self.archetypes,
+            archetype,
+        );
 
         debug_assert_ne!(archetype, insert_info.dst);
 
+        if self.archetypes[insert_info.dst as usize].is_full() {
+            return Err(CapacityExceeded.into());
+        }
+
+        self.epoch += 1;
+
         let (before, after) = self
             .archetypes
             .split_at_mut(archetype.max(insert_info.dst) as usize);
@@ -419,7 +743,9 @@ impl World {
         if let Some(src_id) = opt_src_id {
             self.entities.set_location(src_id, archetype, idx);
         }
+        restore_order(&mut self.entities, src, archetype, idx);
 
+        self.canonicalize_archetypes();
         Ok(())
     }
 
@@ -427,24 +753,57 @@ impl World {
     ///
     /// If entity does not have component of this type, fails with `Err(EntityError::MissingComponent)`.
     /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity without the component.
     #[inline]
     pub fn remove<T>(&mut self, entity: &EntityId) -> Result<T, EntityError>
     where
         T: Component,
     {
-        let (archetype, idx) = self.entities.get(entity).ok_or(EntityError::NoSuchEntity)?;
+        match self.try_remove(entity) {
+            Ok(component) => Ok(component),
+            Err(RemoveError::NoSuchEntity) => Err(EntityError::NoSuchEntity),
+            Err(RemoveError::MissingComponents) => Err(EntityError::MissingComponents),
+            Err(RemoveError::CapacityExceeded) => panic!("{}", CapacityExceeded),
+        }
+    }
 
-        self.epoch += 1;
+    /// Removes component from the specified entity and returns its value.
+    ///
+    /// If entity does not have component of this type, fails with `Err(RemoveError::MissingComponent)`.
+    /// If entity is not alive, fails with `Err(RemoveError::NoSuchEntity)`.
+    /// If `World` with fixed capacity has no space for the entity without the component,
+    /// fails with `Err(RemoveError::CapacityExceeded)`.
+    #[inline]
+    pub fn try_remove<T>(&mut self, entity: &EntityId) -> Result<T, RemoveError>
+    where
+        T: Component,
+    {
+        let (archetype, idx) = self.entities.get(entity).ok_or(NoSuchEntity)?;
 
         if !self.archetypes[archetype as usize].contains_id(TypeId::of::<T>()) {
-            return Err(EntityError::MissingComponents);
+            return Err(MissingComponents.into());
         }
 
+        let src = &self.archetypes[archetype as usize];
+        self.check_archetypes_capacity(|a| {
+            a.shared_values() == src.shared_values()
+                && a.matches(src.ids().filter(|&id| id != TypeId::of::<T>()))
+        })?;
+
         let remove_info =
             cached_remove_info::<T>(&mut self.sub_one, &mut self.archetypes, archetype);
 
         debug_assert_ne!(archetype, remove_info.dst);
 
+        if self.archetypes[remove_info.dst as usize].is_full() {
+            return Err(CapacityExceeded.into());
+        }
+
+        self.epoch += 1;
+
         let (before, after) = self
             .archetypes
             .split_at_mut(archetype.max(remove_info.dst) as usize);
@@ -455,6 +814,9 @@ impl World {
         };
 
         let (dst_idx, opt_src_id, component) = unsafe { src.remove(dst, idx) };
+        self.delta_log
+            .remove(&mut self.epoch, *entity, TypeId::of::<T>());
+        self.indices.remove(*entity, TypeId::of::<T>());
 
         self.entities
             .set_location(entity.idx, remove_info.dst, dst_idx);
@@ -462,7 +824,9 @@ impl World {
         if let Some(src_id) = opt_src_id {
             self.entities.set_location(src_id, archetype, idx);
         }
+        restore_order(&mut self.entities, src, archetype, idx);
 
+        self.canonicalize_archetypes();
         Ok(component)
     }
 
@@ -493,9 +857,11 @@ impl World {
     /// old component value is replaced with new one.
     /// Otherwise new component is added to the entity.
     ///
-    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    /// If entity is not alive, fails with `Err(InsertError::NoSuchEntity)`.
+    /// If `World` with fixed capacity has no space for the entity with new components,
+    /// fails with `Err(InsertError::CapacityExceeded)`.
     #[inline]
-    pub fn try_insert_bundle<B>(&mut self, entity: &EntityId, bundle: B) -> Result<(), NoSuchEntity>
+    pub fn try_insert_bundle<B>(&mut self, entity: &EntityId, bundle: B) -> Result<(), InsertError>
     where
         B: DynamicBundle,
     {
@@ -512,16 +878,33 @@ impl World {
             return Ok(());
         }
 
-        self.epoch += 1;
+        let src = &self.archetypes[archetype as usize];
+        self.check_archetypes_capacity(|a| {
+            a.shared_values() == src.shared_values()
+                && bundle.with_ids(|ids| {
+                    a.matches(
+                        src.ids()
+                            .filter(|&id| !bundle.contains_id(id))
+                            .chain(ids.iter().copied()),
+                    )
+                })
+        })?;
 
         let insert_info = cached_insert_bundle_info(
             &mut self.add_key,
             &mut self.add_ids,
+            &self.registry,
             &mut self.archetypes,
             archetype,
             &bundle,
         );
 
+        if insert_info.dst != archetype && self.archetypes[insert_info.dst as usize].is_full() {
+            return Err(CapacityExceeded.into());
+        }
+
+        self.epoch += 1;
+
         if insert_info.dst == archetype {
             unsafe { self.archetypes[archetype as usize].set_bundle(idx, bundle, self.epoch) };
             return Ok(());
@@ -544,7 +927,9 @@ impl World {
         if let Some(src_id) = opt_src_id {
             self.entities.set_location(src_id, archetype, idx);
         }
+        restore_order(&mut self.entities, src, archetype, idx);
 
+        self.canonicalize_archetypes();
         Ok(())
     }
 
@@ -552,8 +937,30 @@ impl World {
     /// Skips any component type entity doesn't have.
     ///
     /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity without the components.
     #[inline]
     pub fn remove_bundle<B>(&mut self, entity: &EntityId) -> Result<(), NoSuchEntity>
+    where
+        B: Bundle,
+    {
+        match self.try_remove_bundle::<B>(entity) {
+            Ok(()) => Ok(()),
+            Err(RemoveError::CapacityExceeded) => panic!("{}", CapacityExceeded),
+            Err(_) => Err(NoSuchEntity),
+        }
+    }
+
+    /// Drops components of the specified entity with type from the bundle.
+    /// Skips any component type entity doesn't have.
+    ///
+    /// If entity is not alive, fails with `Err(RemoveError::NoSuchEntity)`.
+    /// If `World` with fixed capacity has no space for the entity without the components,
+    /// fails with `Err(RemoveError::CapacityExceeded)`.
+    #[inline]
+    pub fn try_remove_bundle<B>(&mut self, entity: &EntityId) -> Result<(), RemoveError>
     where
         B: Bundle,
     {
@@ -574,309 +981,1985 @@ impl World {
             return Ok(());
         }
 
-        self.epoch += 1;
+        let src = &self.archetypes[archetype as usize];
+        self.check_archetypes_capacity(|a| {
+            a.shared_values() == src.shared_values()
+                && a.matches(src.ids().filter(|&id| !B::static_contains_id(id)))
+        })?;
 
-        let remove_info = cached_remove_bundle_info::<B>(
+        let dst_archetype = cached_remove_bundle_info::<B>(
             &mut self.sub_key,
             &mut self.sub_ids,
             &mut self.archetypes,
             archetype,
-        );
+        )
+        .dst;
 
-        debug_assert_ne!(archetype, remove_info.dst);
+        debug_assert_ne!(archetype, dst_archetype);
+
+        if self.archetypes[dst_archetype as usize].is_full() {
+            return Err(CapacityExceeded.into());
+        }
+
+        self.epoch += 1;
+
+        if self.delta_log.is_enabled() || !self.indices.is_empty() {
+            B::static_with_ids(|ids| {
+                for &id in ids {
+                    if self.archetypes[archetype as usize].contains_id(id) {
+                        self.delta_log.remove(&mut self.epoch, *entity, id);
+                        self.indices.remove(*entity, id);
+                    }
+                }
+            });
+        }
 
         let (before, after) = self
             .archetypes
-            .split_at_mut(archetype.max(remove_info.dst) as usize);
+            .split_at_mut(archetype.max(dst_archetype) as usize);
 
-        let (src, dst) = match archetype < remove_info.dst {
+        let (src, dst) = match archetype < dst_archetype {
             true => (&mut before[archetype as usize], &mut after[0]),
-            false => (&mut after[0], &mut before[remove_info.dst as usize]),
+            false => (&mut after[0], &mut before[dst_archetype as usize]),
         };
 
         let (dst_idx, opt_src_id) = unsafe { src.drop_bundle(dst, idx) };
 
         self.entities
-            .set_location(entity.idx, remove_info.dst, dst_idx);
+            .set_location(entity.idx, dst_archetype, dst_idx);
 
         if let Some(src_id) = opt_src_id {
             self.entities.set_location(src_id, archetype, idx);
         }
+        restore_order(&mut self.entities, src, archetype, idx);
 
+        self.canonicalize_archetypes();
         Ok(())
     }
 
-    /// Checks that entity has components of all types from the bundle.
-    /// Pins those types to the entity.
+    /// Despawns entities with specified ids.
     ///
-    /// Pinning serves as API level contract.
+    /// Entities are grouped by archetype and each archetype
+    /// is compacted once after all its entities in the batch are dropped.
+    /// Ids of entities that are not alive are skipped.
+    /// With "rc" feature ids of entities not owned by the `World` are skipped too.
     ///
-    /// Pinned components are not enforced to stay at entity
-    /// and can be removed using `World::remove` or `World::remove_bundle`
-    /// with a clone of the `Entity` without component types pinned.
-    #[cfg(feature = "rc")]
-    #[inline]
-    pub fn pin_bundle<B>(&mut self, entity: Entity) -> Entity<B>
-    where
-        B: Bundle,
-    {
-        if !B::static_valid() {
-            panic!(
-                "Specified bundle `{}` is not valid. Check for duplicate component types",
-                type_name::<B>()
-            );
+    /// Returns number of despawned entities.
+    pub fn despawn_batch(&mut self, entities: &[EntityId]) -> usize {
+        let mut locations = Vec::with_capacity(entities.len());
+        for entity in entities {
+            if let Ok((archetype, idx)) = self.entities.despawn(entity) {
+                locations.push((archetype, idx, *entity));
+            }
         }
 
-        assert!(self.entities.is_owner_of(&entity));
+        locations.sort_unstable_by_key(|&(archetype, idx, _)| (archetype, idx));
 
-        let (archetype, _idx) = self.entities.get(&entity).unwrap();
+        let mut indices = Vec::new();
+        for group in archetype_groups(&locations) {
+            let archetype_idx = group[0].0;
+            let archetype = &mut self.archetypes[archetype_idx as usize];
 
-        let archetype = &self.archetypes[archetype as usize];
+            indices.clear();
+            for &(_, idx, entity) in group {
+                self.delta_log.despawn(&mut self.epoch, entity);
+                self.indices.despawn(entity);
+                self.observers
+                    .despawned(entity, archetype_idx, idx, archetype);
+                indices.push(idx);
+            }
 
-        if B::static_with_ids(|ids| ids.iter().any(|&id| !archetype.contains_id(id))) {
-            panic!("Attampt to pin missing components");
+            let entities = &mut self.entities;
+            unsafe {
+                archetype.despawn_batch(&indices, |id, idx| {
+                    entities.set_location(id, archetype_idx, idx)
+                });
+            }
         }
 
-        entity.with_bundle()
+        locations.len()
     }
 
-    /// Queries components from specified entity.
+    /// Inserts components to the specified entities.
     ///
-    /// Requires access to all components in query.
-    /// Components proved by entity reference must be queried or skipped in order
-    /// followed by list of optionals.
+    /// If entity already had component of that type,
+    /// old component value is replaced with new one.
+    /// Otherwise new component is added to the entity.
+    /// If batch contains same entity more than once, last value is inserted.
+    ///
+    /// Entities are grouped by archetype and each group
+    /// is moved to destination archetype at once.
+    /// Components for entities that are not alive are dropped.
+    ///
+    /// Returns number of entities components were inserted to.
     ///
     /// # Panics
     ///
-    /// If `Entity` was not created by this world, this function will panic.
-    #[cfg(feature = "rc")]
-    #[inline]
-    pub fn get<'a, Q, A: 'a>(&'a self, entity: &Entity<A>) -> <Q::Fetch as Fetch<'a>>::Item
+    /// Panics if `World` with fixed capacity has no space for entities with new component.
+    pub fn insert_batch<T, I>(&mut self, batch: I) -> usize
     where
-        &'a A: Proof<Q>,
-        Q: Query + ImmutableQuery + NonTrackingQuery,
+        T: Component,
+        I: IntoIterator<Item = (EntityId, T)>,
     {
-        assert!(self.entities.is_owner_of(entity));
+        match self.try_insert_batch(batch) {
+            Ok(inserted) => inserted,
+            Err(err) => panic!("{}", err),
+        }
+    }
 
-        assert!(
-            !Q::mutates(),
-            "Invalid impl of `ImmutableQuery` for `{}`",
-            type_name::<Q>()
-        );
+    /// Inserts components to the specified entities.
+    /// See [`World::insert_batch`].
+    ///
+    /// If `World` with fixed capacity has no space for entities with new component,
+    /// fails with `Err(CapacityExceeded)` and leaves all entities unchanged.
+    pub fn try_insert_batch<T, I>(&mut self, batch: I) -> Result<usize, CapacityExceeded>
+    where
+        T: Component,
+        I: IntoIterator<Item = (EntityId, T)>,
+    {
+        let entities = &self.entities;
+        let mut items: Vec<_> = batch
+            .into_iter()
+            .filter_map(|(entity, value)| {
+                let (archetype, idx) = entities.get(&entity)?;
+                Some((archetype, idx, value))
+            })
+            .collect();
+
+        // Stable sort of reversed batch puts last value for an entity first.
+        items.reverse();
+        items.sort_by_key(|&(archetype, idx, _)| (archetype, idx));
+        items.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1);
+
+        // Destination archetypes are resolved and checked
+        // before any entity is moved.
+        let mut groups = Vec::new();
+        for group in archetype_groups(&items) {
+            let archetype = group[0].0;
+            let src = &self.archetypes[archetype as usize];
+
+            if src.contains_id(TypeId::of::<T>()) {
+                groups.push((archetype, None, group.len()));
+                continue;
+            }
 
-        assert!(
-            !Q::tracks(),
-            "Invalid impl of `NonTrackingQuery` for `{}`",
-            type_name::<Q>()
-        );
+            self.check_archetypes_capacity(|a| {
+                a.shared_values() == src.shared_values()
+                    && a.matches(src.ids().chain(Some(TypeId::of::<T>())))
+            })?;
+
+            let dst_archetype = cached_insert_info::<T>(
+                &mut self.add_one,
+                &self.registry,
+                &mut self.archetypes,
+                archetype,
+            )
+            .dst;
+
+            debug_assert_ne!(archetype, dst_archetype);
+            groups.push((archetype, Some(dst_archetype), group.len()));
+        }
+        self.check_batch_space(&groups)?;
 
-        let (archetype, idx) = self.entities.get(entity).unwrap();
-        let archetype = &self.archetypes[archetype as usize];
-        let mut fetch = unsafe { Q::fetch(archetype, 0, self.epoch) }.expect("Query is prooven");
-        let item = unsafe { fetch.get_item(idx as usize) };
-        item
+        self.epoch += 1;
+        let epoch = self.epoch;
+
+        let inserted = items.len();
+        let mut items = items.into_iter();
+        for (archetype, dst_archetype, len) in groups {
+            let group = items.by_ref().take(len);
+
+            let dst_archetype = match dst_archetype {
+                None => {
+                    for (_, idx, value) in group {
+                        unsafe {
+                            self.archetypes[archetype as usize].set(idx, value, epoch);
+                        }
+                    }
+                    continue;
+                }
+                Some(dst_archetype) => dst_archetype,
+            };
+
+            let values: Vec<_> = group.map(|(_, idx, value)| (idx, value)).collect();
+
+            let (before, after) = self
+                .archetypes
+                .split_at_mut(archetype.max(dst_archetype) as usize);
+
+            let (src, dst) = match archetype < dst_archetype {
+                true => (&mut before[archetype as usize], &mut after[0]),
+                false => (&mut after[0], &mut before[dst_archetype as usize]),
+            };
+
+            let entities = &mut self.entities;
+            let dst_start = unsafe {
+                src.insert_batch(dst, values, epoch, |id, idx| {
+                    entities.set_location(id, archetype, idx)
+                })
+            };
+            set_batch_locations(entities, dst, dst_archetype, dst_start);
+        }
+
+        self.canonicalize_archetypes();
+        Ok(inserted)
     }
 
-    /// Queries components from specified entity.
+    /// Drops components of the specified type from the specified entities.
     ///
-    /// Requires access to all components in query.
-    /// Components proved by entity reference must be queried or skipped in order
-    /// followed by list of optionals.
+    /// Entities are grouped by archetype and each group
+    /// is moved to destination archetype at once.
+    /// Entities that are not alive or don't have component are skipped.
+    ///
+    /// Returns number of entities components were removed from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for entities without the component.
+    pub fn remove_batch<T>(&mut self, entities: &[EntityId]) -> usize
+    where
+        T: Component,
+    {
+        match self.try_remove_batch::<T>(entities) {
+            Ok(removed) => removed,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Drops components of the specified type from the specified entities.
+    /// See [`World::remove_batch`].
+    ///
+    /// If `World` with fixed capacity has no space for entities without the component,
+    /// fails with `Err(CapacityExceeded)` and leaves all entities unchanged.
+    pub fn try_remove_batch<T>(&mut self, entities: &[EntityId]) -> Result<usize, CapacityExceeded>
+    where
+        T: Component,
+    {
+        let mut locations = Vec::with_capacity(entities.len());
+        for entity in entities {
+            if let Some((archetype, idx)) = self.entities.get(entity) {
+                if self.archetypes[archetype as usize].contains_id(TypeId::of::<T>()) {
+                    locations.push((archetype, idx, *entity));
+                }
+            }
+        }
+
+        if locations.is_empty() {
+            return Ok(0);
+        }
+
+        locations.sort_unstable_by_key(|&(archetype, idx, _)| (archetype, idx));
+        locations.dedup();
+
+        // Destination archetypes are resolved and checked
+        // before any entity is moved.
+        let mut groups = Vec::new();
+        for group in archetype_groups(&locations) {
+            let archetype = group[0].0;
+            let src = &self.archetypes[archetype as usize];
+
+            self.check_archetypes_capacity(|a| {
+                a.shared_values() == src.shared_values()
+                    && a.matches(src.ids().filter(|&id| id != TypeId::of::<T>()))
+            })?;
+
+            let dst_archetype =
+                cached_remove_info::<T>(&mut self.sub_one, &mut self.archetypes, archetype).dst;
+
+            debug_assert_ne!(archetype, dst_archetype);
+            groups.push((archetype, Some(dst_archetype), group.len()));
+        }
+        self.check_batch_space(&groups)?;
+
+        self.epoch += 1;
+
+        let mut indices = Vec::new();
+        for (group, &(archetype, dst_archetype, _)) in archetype_groups(&locations).zip(&groups) {
+            let dst_archetype = dst_archetype.unwrap();
+
+            indices.clear();
+            for &(_, idx, entity) in group {
+                self.delta_log
+                    .remove(&mut self.epoch, entity, TypeId::of::<T>());
+                self.indices.remove(entity, TypeId::of::<T>());
+                indices.push(idx);
+            }
+
+            let (before, after) = self
+                .archetypes
+                .split_at_mut(archetype.max(dst_archetype) as usize);
+
+            let (src, dst) = match archetype < dst_archetype {
+                true => (&mut before[archetype as usize], &mut after[0]),
+                false => (&mut after[0], &mut before[dst_archetype as usize]),
+            };
+
+            let entities = &mut self.entities;
+            let dst_start = unsafe {
+                src.remove_batch(dst, &indices, |id, idx| {
+                    entities.set_location(id, archetype, idx)
+                })
+            };
+            set_batch_locations(entities, dst, dst_archetype, dst_start);
+        }
+
+        self.canonicalize_archetypes();
+        Ok(locations.len())
+    }
+
+    /// Fails if any destination archetype of batch groups
+    /// has fixed capacity and no space for all entities moved to it.
+    fn check_batch_space(
+        &self,
+        groups: &[(u32, Option<u32>, usize)],
+    ) -> Result<(), CapacityExceeded> {
+        for &(_, dst_archetype, _) in groups {
+            if let Some(dst_archetype) = dst_archetype {
+                let required = groups
+                    .iter()
+                    .filter(|group| group.1 == Some(dst_archetype))
+                    .map(|group| group.2)
+                    .sum();
+
+                if !self.archetypes[dst_archetype as usize].has_space(required) {
+                    return Err(CapacityExceeded);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Enables or disables deterministic mode.
+    ///
+    /// In deterministic mode iteration order of `World` does not depend
+    /// on order in which archetypes were created
+    /// nor on which entities were removed from archetypes:
+    ///
+    /// * Archetypes are ordered by canonical key -
+    ///   sorted canonical names of their components, then of their shared components,
+    ///   then values of shared components.
+    ///   Canonical name of a component type is its id registered with
+    ///   [`World::register_canonical_id`] or its type name otherwise.
+    ///   Values of shared components are compared with `Ord` implementation
+    ///   registered with [`World::register_ord`].
+    ///   Archetypes with equal canonical keys are kept in order of creation.
+    ///   This happens if archetypes differ only in values of shared component
+    ///   which type is not registered with [`World::register_ord`],
+    ///   or if distinct component types have equal canonical names.
+    /// * Entities in archetype are kept in the order they were added to it,
+    ///   until reordered with [`World::sort_archetypes_by`].
+    ///   Removing an entity from archetype shifts following entities
+    ///   instead of moving the last entity into its place.
+    ///
+    /// Thus `World`s built with the same binary iterate identically
+    /// if entities were added to each archetype in the same order,
+    /// the same sorts were applied and archetypes with equal canonical keys
+    /// were created in the same order.
+    /// Type names may change between builds, `World`s built with different binaries
+    /// iterate identically if all component types have registered ids.
+    ///
+    /// Removing entities from archetypes costs linear time in this mode.
+    /// Creating archetype that is not last in canonical order reorders archetypes
+    /// and updates locations of all entities, which costs time linear
+    /// in number of entities.
+    /// Order of entities already in the `World` is kept when mode is enabled.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.storage.deterministic = deterministic;
+        for archetype in &mut self.archetypes {
+            archetype.set_deterministic(deterministic);
+        }
+        self.canonical_len = 0;
+        self.canonicalize_archetypes();
+    }
+
+    /// Returns `true` if `World` is in deterministic mode.
+    /// See [`World::set_deterministic`].
+    #[inline]
+    pub fn is_deterministic(&self) -> bool {
+        self.storage.deterministic
+    }
+
+    /// Reorders archetypes by canonical key in deterministic mode.
+    /// Does nothing if no archetypes were created since last call.
+    ///
+    /// Returns map from old archetype indices to new ones if archetypes were reordered.
+    fn canonicalize_archetypes(&mut self) -> Option<Vec<u32>> {
+        if !self.storage.deterministic || self.canonical_len == self.archetypes.len() {
+            return None;
+        }
+        self.canonical_len = self.archetypes.len();
+
+        let keys: Vec<_> = self
+            .archetypes
+            .iter()
+            .map(|archetype| CanonicalKey::new(archetype, &self.registry))
+            .collect();
+
+        // Archetypes are kept in canonical order and new ones are pushed last,
+        // so stable sort keeps archetypes with equal keys in order of creation.
+        let mut order: Vec<usize> = (0..self.archetypes.len()).collect();
+        order.sort_by(|&lhs, &rhs| keys[lhs].cmp(&keys[rhs]));
+
+        if order.iter().enumerate().all(|(idx, &old)| idx == old) {
+            return None;
+        }
+
+        // Keep capacity as `World` with fixed capacity relies on it.
+        let mut archetypes = Vec::with_capacity(self.archetypes.capacity());
+        let mut old: Vec<Option<Archetype>> = self.archetypes.drain(..).map(Some).collect();
+        let mut map = alloc::vec![0; order.len()];
+
+        for (new_idx, &old_idx) in order.iter().enumerate() {
+            archetypes.push(old[old_idx].take().unwrap());
+            map[old_idx] = new_idx as u32;
+        }
+        self.archetypes = archetypes;
+
+        for (archetype_idx, archetype) in self.archetypes.iter().enumerate() {
+            for (idx, entity) in archetype.entities().iter().enumerate() {
+                self.entities
+                    .set_location(entity.idx, archetype_idx as u32, idx as u32);
+            }
+        }
+
+        // Cached archetype indices are invalidated.
+        self.keys.clear();
+        self.ids.clear();
+        self.add_one.clear();
+        self.add_key.clear();
+        self.add_ids.clear();
+        self.sub_one.clear();
+        self.sub_key.clear();
+        self.sub_ids.clear();
+
+        Some(map)
+    }
+
+    /// Returns index of archetype after canonicalization of archetypes order.
+    fn canonical_idx(&mut self, archetype_idx: u32) -> u32 {
+        match self.canonicalize_archetypes() {
+            None => archetype_idx,
+            Some(map) => map[archetype_idx as usize],
+        }
+    }
+
+    /// Adds index of entities by value of component `T`.
+    /// Does nothing if index for `T` is already added.
+    ///
+    /// Index is kept up to date on spawns, insertions, removals and despawns.
+    /// Components modified through mutable access are reindexed
+    /// on next lookup using entity versions.
+    pub fn add_index<T>(&mut self)
+    where
+        T: Component + Hash + Eq + Clone,
+    {
+        self.indices.add(Index::<T>::new());
+    }
+
+    /// Returns iterator over entities with component `T` equal to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if index for `T` was not added with [`World::add_index`].
+    pub fn lookup<T>(&self, value: &T) -> impl Iterator<Item = EntityId>
+    where
+        T: Component + Hash + Eq + Clone,
+    {
+        let index = match self.indices.get::<Index<T>>(&self.archetypes, self.epoch) {
+            None => panic!("Index for `{}` is not added", type_name::<T>()),
+            Some(index) => index,
+        };
+
+        let entities: Vec<EntityId> = index.get(value).to_vec();
+        entities.into_iter()
+    }
+
+    /// Adds index of entities ordered by value of component `T`.
+    /// Does nothing if ordered index for `T` is already added.
+    ///
+    /// Index is updated the same way as one added with [`World::add_index`].
+    pub fn add_ordered_index<T>(&mut self)
+    where
+        T: Component + Ord + Clone,
+    {
+        self.indices.add(OrderedIndex::<T>::new());
+    }
+
+    /// Returns iterator over entities with component `T` in `range`
+    /// in order of component values.
+    /// Returns empty iterator if `range` is inverted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if ordered index for `T` was not added with [`World::add_ordered_index`].
+    pub fn lookup_range<T, R>(&self, range: R) -> impl Iterator<Item = EntityId>
+    where
+        T: Component + Ord + Clone,
+        R: RangeBounds<T>,
+    {
+        let index = match self
+            .indices
+            .get::<OrderedIndex<T>>(&self.archetypes, self.epoch)
+        {
+            None => panic!("Ordered index for `{}` is not added", type_name::<T>()),
+            Some(index) => index,
+        };
+
+        let entities: Vec<EntityId> = index.range(range).collect();
+        entities.into_iter()
+    }
+
+    /// Sorts entities in every archetype with component `T`
+    /// by key extracted from that component.
+    ///
+    /// Components, their versions and entity locations are reordered together,
+    /// so that following queries visit entities of each archetype in key order.
+    /// Sort is stable. Archetypes without component `T` are not affected.
+    ///
+    /// In deterministic mode sorted entities keep the new order
+    /// instead of the order they were added in, see [`World::set_deterministic`].
+    pub fn sort_archetypes_by<T, K>(&mut self, mut key_fn: impl FnMut(&T) -> K)
+    where
+        T: Component,
+        K: Ord,
+    {
+        for (archetype_idx, archetype) in self.archetypes.iter_mut().enumerate() {
+            if !archetype.contains_id(TypeId::of::<T>()) {
+                continue;
+            }
+
+            if archetype.sort_by_key(&mut key_fn) {
+                for (idx, entity) in archetype.entities().iter().enumerate() {
+                    self.entities
+                        .set_location(entity.idx, archetype_idx as u32, idx as u32);
+                }
+            }
+        }
+    }
+
+    /// Sets value of shared component for the specified entity.
+    ///
+    /// Value of shared component is stored once per archetype.
+    /// Entity is moved to archetype with the same components
+    /// and shared values, except for `T`, that holds `value`.
+    ///
+    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity with new value.
+    pub fn set_shared<T>(&mut self, entity: &EntityId, value: T) -> Result<(), NoSuchEntity>
+    where
+        T: SharedComponent,
+    {
+        match self.try_set_shared(entity, value) {
+            Ok(()) => Ok(()),
+            Err(InsertError::NoSuchEntity) => Err(NoSuchEntity),
+            Err(InsertError::CapacityExceeded) => panic!("{}", CapacityExceeded),
+        }
+    }
+
+    /// Sets value of shared component for the specified entity.
+    ///
+    /// Value of shared component is stored once per archetype.
+    /// Entity is moved to archetype with the same components
+    /// and shared values, except for `T`, that holds `value`.
+    ///
+    /// If entity is not alive, fails with `Err(InsertError::NoSuchEntity)`.
+    /// If `World` with fixed capacity has no space for the entity with new value,
+    /// fails with `Err(InsertError::CapacityExceeded)`.
+    pub fn try_set_shared<T>(&mut self, entity: &EntityId, value: T) -> Result<(), InsertError>
+    where
+        T: SharedComponent,
+    {
+        let (archetype, idx) = self.entities.get(entity).ok_or(NoSuchEntity)?;
+
+        let src = &self.archetypes[archetype as usize];
+        if src.shared::<T>() == Some(&value) {
+            return Ok(());
+        }
+
+        let shared = src.shared_values().with(value);
+        self.move_to_shared(entity, archetype, idx, shared)?;
+        Ok(())
+    }
+
+    /// Removes value of shared component from the specified entity.
+    /// Does nothing if entity has no shared component of type `T`.
+    ///
+    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity without the value.
+    pub fn remove_shared<T>(&mut self, entity: &EntityId) -> Result<(), NoSuchEntity>
+    where
+        T: SharedComponent,
+    {
+        match self.try_remove_shared::<T>(entity) {
+            Ok(()) => Ok(()),
+            Err(RemoveError::CapacityExceeded) => panic!("{}", CapacityExceeded),
+            Err(_) => Err(NoSuchEntity),
+        }
+    }
+
+    /// Removes value of shared component from the specified entity.
+    /// Does nothing if entity has no shared component of type `T`.
+    ///
+    /// If entity is not alive, fails with `Err(RemoveError::NoSuchEntity)`.
+    /// If `World` with fixed capacity has no space for the entity without the value,
+    /// fails with `Err(RemoveError::CapacityExceeded)`.
+    pub fn try_remove_shared<T>(&mut self, entity: &EntityId) -> Result<(), RemoveError>
+    where
+        T: SharedComponent,
+    {
+        let (archetype, idx) = self.entities.get(entity).ok_or(NoSuchEntity)?;
+
+        let src = &self.archetypes[archetype as usize];
+        if src.shared::<T>().is_none() {
+            return Ok(());
+        }
+
+        let shared = src.shared_values().without::<T>();
+        self.move_to_shared(entity, archetype, idx, shared)?;
+        Ok(())
+    }
+
+    /// Returns value of shared component of the specified entity.
+    ///
+    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    pub fn get_shared<T>(&self, entity: &EntityId) -> Result<Option<&T>, NoSuchEntity>
+    where
+        T: SharedComponent,
+    {
+        let (archetype, _) = self.entities.get(entity).ok_or(NoSuchEntity)?;
+        Ok(self.archetypes[archetype as usize].shared::<T>())
+    }
+
+    /// Moves entity with all its components
+    /// to archetype with specified shared values.
+    /// Fails if `World` with fixed capacity has no space for the entity in that archetype.
+    fn move_to_shared(
+        &mut self,
+        entity: &EntityId,
+        archetype: u32,
+        idx: u32,
+        shared: SharedValues,
+    ) -> Result<(), CapacityExceeded> {
+        let src = &self.archetypes[archetype as usize];
+        self.check_archetypes_capacity(|a| a.shared_values() == &shared && a.matches(src.ids()))?;
+
+        let infos: Vec<ComponentInfo> = src.infos().copied().collect();
+
+        let dst_archetype = make_shared_archetype_idx(
+            &self.registry,
+            &mut self.archetypes,
+            &self.storage,
+            &infos,
+            &shared,
+        );
+
+        debug_assert_ne!(archetype, dst_archetype);
+
+        if self.archetypes[dst_archetype as usize].is_full() {
+            return Err(CapacityExceeded);
+        }
+
+        let (before, after) = self
+            .archetypes
+            .split_at_mut(archetype.max(dst_archetype) as usize);
+
+        let (src, dst) = match archetype < dst_archetype {
+            true => (&mut before[archetype as usize], &mut after[0]),
+            false => (&mut after[0], &mut before[dst_archetype as usize]),
+        };
+
+        let (dst_idx, opt_src_id) = unsafe { src.drop_bundle(dst, idx) };
+
+        self.entities
+            .set_location(entity.idx, dst_archetype, dst_idx);
+
+        if let Some(src_id) = opt_src_id {
+            self.entities.set_location(src_id, archetype, idx);
+        }
+        restore_order(&mut self.entities, src, archetype, idx);
+
+        self.canonicalize_archetypes();
+        Ok(())
+    }
+
+    /// Checks that entity has components of all types from the bundle.
+    /// Pins those types to the entity.
+    ///
+    /// Pinning serves as API level contract.
+    ///
+    /// Pinned components are not enforced to stay at entity
+    /// and can be removed using `World::remove` or `World::remove_bundle`
+    /// with a clone of the `Entity` without component types pinned.
+    #[cfg(feature = "rc")]
+    #[inline]
+    pub fn pin_bundle<B>(&mut self, entity: Entity) -> Entity<B>
+    where
+        B: Bundle,
+    {
+        if !B::static_valid() {
+            panic!(
+                "Specified bundle `{}` is not valid. Check for duplicate component types",
+                type_name::<B>()
+            );
+        }
+
+        assert!(self.entities.is_owner_of(&entity));
+
+        let (archetype, _idx) = self.entities.get(&entity).unwrap();
+
+        let archetype = &self.archetypes[archetype as usize];
+
+        if B::static_with_ids(|ids| ids.iter().any(|&id| !archetype.contains_id(id))) {
+            panic!("Attampt to pin missing components");
+        }
+
+        entity.with_bundle()
+    }
+
+    /// Queries components from specified entity.
+    ///
+    /// Requires access to all components in query.
+    /// Components proved by entity reference must be queried or skipped in order
+    /// followed by list of optionals.
     ///
     /// # Panics
     ///
     /// If `Entity` was not created by this world, this function will panic.
     #[cfg(feature = "rc")]
     #[inline]
-    pub fn get_mut<'a, Q, A: 'a>(&'a mut self, entity: &Entity<A>) -> <Q::Fetch as Fetch<'a>>::Item
+    pub fn get<'a, Q, P: 'a>(&'a self, entity: &Entity<P>) -> <Q::Fetch as Fetch<'a>>::Item
     where
-        &'a mut A: Proof<Q>,
-        Q: Query + NonTrackingQuery,
+        &'a P: Proof<Q>,
+        Q: Query + ImmutableQuery + NonTrackingQuery,
     {
         assert!(self.entities.is_owner_of(entity));
 
+        assert!(
+            !Q::mutates(),
+            "Invalid impl of `ImmutableQuery` for `{}`",
+            type_name::<Q>()
+        );
+
         assert!(
             !Q::tracks(),
             "Invalid impl of `NonTrackingQuery` for `{}`",
             type_name::<Q>()
         );
 
-        if Q::mutates() {
-            self.epoch += 1;
+        let (archetype, idx) = self.entities.get(entity).unwrap();
+        let archetype = &self.archetypes[archetype as usize];
+        let mut fetch = unsafe { Q::fetch(archetype, 0, self.epoch) }.expect("Query is prooven");
+        let item = unsafe { fetch.get_item(idx as usize) };
+        item
+    }
+
+    /// Queries components from specified entity.
+    ///
+    /// Requires access to all components in query.
+    /// Components proved by entity reference must be queried or skipped in order
+    /// followed by list of optionals.
+    ///
+    /// Query must implement [`NoAlias`], see [`World::query_mut`].
+    ///
+    /// # Panics
+    ///
+    /// If `Entity` was not created by this world, this function will panic.
+    #[cfg(feature = "rc")]
+    #[inline]
+    pub fn get_mut<'a, Q, I, P: 'a>(
+        &'a mut self,
+        entity: &Entity<P>,
+    ) -> <Q::Fetch as Fetch<'a>>::Item
+    where
+        &'a mut P: Proof<Q>,
+        Q: Query + NonTrackingQuery + NoAlias<I>,
+    {
+        assert!(self.entities.is_owner_of(entity));
+        assert_valid::<Q>();
+
+        assert!(
+            !Q::tracks(),
+            "Invalid impl of `NonTrackingQuery` for `{}`",
+            type_name::<Q>()
+        );
+
+        if Q::mutates() {
+            self.epoch += 1;
+        }
+
+        let (archetype, idx) = self.entities.get(entity).unwrap();
+        let archetype = &self.archetypes[archetype as usize];
+        let mut fetch = unsafe { Q::fetch(archetype, 0, self.epoch) }.expect("Query is prooven");
+        unsafe {
+            fetch.visit_chunk(chunk_idx(idx as usize));
+        }
+        let item = unsafe { fetch.get_item(idx as usize) };
+        item
+    }
+
+    /// Queries components from specified entity.
+    ///
+    /// If query cannot be satisfied, returns `EntityError::MissingComponents`.
+    #[inline]
+    pub fn query_one<'a, Q>(
+        &'a self,
+        entity: &EntityId,
+    ) -> Result<<Q::Fetch as Fetch<'a>>::Item, EntityError>
+    where
+        Q: Query + ImmutableQuery + NonTrackingQuery,
+    {
+        debug_assert!(Q::is_valid(), "Immutable queries are always valid");
+
+        assert!(
+            !Q::mutates(),
+            "Invalid impl of `ImmutableQuery` for `{}`",
+            type_name::<Q>()
+        );
+
+        assert!(
+            !Q::tracks(),
+            "Invalid impl of `NonTrackingQuery` for `{}`",
+            type_name::<Q>()
+        );
+
+        let (archetype, idx) = self.entities.get(entity).ok_or(EntityError::NoSuchEntity)?;
+        let archetype = &self.archetypes[archetype as usize];
+        match unsafe { Q::fetch(archetype, 0, self.epoch) } {
+            None => Err(EntityError::MissingComponents),
+            Some(mut fetch) => {
+                let item = unsafe { fetch.get_item(idx as usize) };
+                Ok(item)
+            }
+        }
+    }
+
+    /// Queries components from specified entity.
+    ///
+    /// Query must implement [`NoAlias`], see [`World::query_mut`].
+    ///
+    /// If query cannot be satisfied, returns `EntityError::MissingComponents`.
+    #[inline]
+    pub fn query_one_mut<'a, Q, I>(
+        &'a mut self,
+        entity: &EntityId,
+    ) -> Result<<Q::Fetch as Fetch<'a>>::Item, EntityError>
+    where
+        Q: Query + NonTrackingQuery + NoAlias<I>,
+    {
+        self.query_one_mut_dynamic::<Q>(entity)
+    }
+
+    /// Queries components from specified entity.
+    ///
+    /// Works with any query, see [`World::query_mut_dynamic`].
+    ///
+    /// If query cannot be satisfied, returns `EntityError::MissingComponents`.
+    #[inline]
+    pub fn query_one_mut_dynamic<'a, Q>(
+        &'a mut self,
+        entity: &EntityId,
+    ) -> Result<<Q::Fetch as Fetch<'a>>::Item, EntityError>
+    where
+        Q: Query + NonTrackingQuery,
+    {
+        assert_valid::<Q>();
+
+        assert!(
+            !Q::tracks(),
+            "Invalid impl of `NonTrackingQuery` for `{}`",
+            type_name::<Q>()
+        );
+
+        if Q::mutates() {
+            self.epoch += 1;
+        }
+
+        let (archetype, idx) = self.entities.get(entity).ok_or(EntityError::NoSuchEntity)?;
+        let archetype = &self.archetypes[archetype as usize];
+        match unsafe { Q::fetch(archetype, 0, self.epoch) } {
+            None => Err(EntityError::MissingComponents),
+            Some(mut fetch) => {
+                unsafe {
+                    fetch.visit_chunk(chunk_idx(idx as usize));
+                }
+                let item = unsafe { fetch.get_item(idx as usize) };
+                Ok(item)
+            }
+        }
+    }
+
+    /// Returns new [`Tracks`] instance to use with tracking queries.
+    ///
+    /// Returnd [`Tracks`] instance considers all modifications
+    /// since creation of the world as "new" for the first tracking query.
+    #[inline]
+    pub fn tracks(&self) -> Tracks {
+        Tracks { epoch: 0 }
+    }
+
+    /// Returns new [`Tracks`] instance to use with tracking queries.
+    ///
+    /// Returnd [`Tracks`] instance considers only modifications
+    /// that happen after this function call as "new" for the first tracking query.
+    #[inline]
+    pub fn tracks_now(&self) -> Tracks {
+        Tracks { epoch: self.epoch }
+    }
+
+    /// Run world maintenance, completing all deferred operations on it.
+    ///
+    /// Currently deferred operations are:
+    /// * Despawn of entities with no strong references left
+    /// * Drop of events sent before previous maintenance
+    ///
+    /// Despawned entities drop their components, which may release
+    /// last strong references to other entities, and so on.
+    /// Such cascade is despawned level by level,
+    /// up to depth set with [`World::set_cascade_depth`].
+    /// Entities of deeper levels are despawned on next maintenance.
+    ///
+    /// Returns number of entities despawned in cascade,
+    /// that is excluding entities whose last strong reference
+    /// was dropped before this call.
+    #[inline]
+    pub fn maintain(&mut self) -> usize {
+        #[cfg(feature = "leak-detection")]
+        self.entities.next_cycle();
+
+        for events in self.events.values_mut() {
+            events.update();
+        }
+
+        #[cfg(feature = "rc")]
+        {
+            self.despawn_dropped(self.cascade_depth)
+        }
+
+        #[cfg(not(feature = "rc"))]
+        {
+            0
+        }
+    }
+
+    /// Sets maximum number of cascade levels despawned by one [`World::maintain`] call.
+    ///
+    /// With zero depth only entities whose last strong reference
+    /// was dropped before the call are despawned.
+    /// Unbounded by default.
+    #[cfg(feature = "rc")]
+    #[inline]
+    pub fn set_cascade_depth(&mut self, depth: usize) {
+        self.cascade_depth = depth;
+    }
+
+    /// Despawns an entity with specified id
+    /// together with entities owned by it through components holding [`Entity`],
+    /// recursively, regardless of configured cascade depth.
+    ///
+    /// Other entities waiting to be despawned are left to [`World::maintain`].
+    ///
+    /// Returns number of entities despawned in addition to specified one.
+    #[cfg(feature = "rc")]
+    pub fn despawn_recursive(&mut self, entity: &EntityId) -> Result<usize, OwnershipError> {
+        let queue = self.entities.drop_queue();
+
+        // Keep entities released before this call for `maintain`.
+        queue.drain(&mut self.drop_queue);
+
+        self.despawn(entity)?;
+
+        let mut despawned = 0;
+        let mut level = AllocVec::new(self.storage.alloc.clone());
+        loop {
+            queue.drain(&mut level);
+            if level.is_empty() {
+                return Ok(despawned);
+            }
+            despawned += level.len();
+            for &id in level.iter() {
+                self.despawn_dropped_one(id);
+            }
+            level.clear();
+        }
+    }
+
+    /// Despawns entities with no strong references left,
+    /// following cascade for up to `depth` levels.
+    /// Returns number of entities despawned in cascade.
+    #[cfg(feature = "rc")]
+    fn despawn_dropped(&mut self, depth: usize) -> usize {
+        let queue = self.entities.drop_queue();
+        queue.drain(&mut self.drop_queue);
+
+        let mut cascaded = 0;
+        let mut level = 0;
+        let empty = AllocVec::new(self.storage.alloc.clone());
+        let mut ids = core::mem::replace(&mut self.drop_queue, empty);
+
+        while !ids.is_empty() {
+            if level > 0 {
+                cascaded += ids.len();
+            }
+
+            for &id in ids.iter() {
+                self.despawn_dropped_one(id);
+            }
+            ids.clear();
+
+            if level == depth {
+                break;
+            }
+            level += 1;
+
+            queue.drain(&mut ids);
+        }
+
+        self.drop_queue = ids;
+        cascaded
+    }
+
+    /// Despawns entity with specified index whose last strong reference was dropped.
+    #[cfg(feature = "rc")]
+    fn despawn_dropped_one(&mut self, id: u32) {
+        let (archetype, idx) = self.entities.dropped(id);
+
+        let entity = self.archetypes[archetype as usize].entities()[idx as usize];
+        self.delta_log.despawn(&mut self.epoch, entity);
+        self.indices.despawn(entity);
+        self.observers
+            .despawned(entity, archetype, idx, &self.archetypes[archetype as usize]);
+
+        let opt_id = unsafe { self.archetypes[archetype as usize].despawn_unchecked(idx) };
+        if let Some(id) = opt_id {
+            self.entities.set_location(id, archetype, idx)
+        }
+        restore_order(
+            &mut self.entities,
+            &mut self.archetypes[archetype as usize],
+            archetype,
+            idx,
+        );
+    }
+
+    /// Registers callback fired after entity is spawned in this world,
+    /// including entities cloned or instantiated from prefabs.
+    ///
+    /// Entities moved from another world with [`World::transfer`] or [`World::merge`]
+    /// are not reported.
+    pub fn on_spawn<F>(&mut self, f: F)
+    where
+        F: FnMut(EntityId, &EntityRef<'_>) + 'static,
+    {
+        self.observers.spawn.push(Box::new(f));
+    }
+
+    /// Registers callback fired before entity is despawned.
+    /// Callback may read components of the entity before they are dropped.
+    ///
+    /// Fired on [`World::despawn`], in [`World::maintain`] for entities
+    /// with no strong references left, and for all entities when the `World` is dropped.
+    /// Entities moved to another world with [`World::transfer`] or [`World::merge`]
+    /// are not reported.
+    pub fn on_despawn<F>(&mut self, f: F)
+    where
+        F: FnMut(EntityId, &EntityRef<'_>) + 'static,
+    {
+        self.observers.despawn.push(Box::new(f));
+    }
+
+    /// Sends event to all readers of events of this type.
+    ///
+    /// Event is kept until [`World::maintain`] is called twice.
+    pub fn send_event<E>(&mut self, event: E)
+    where
+        E: 'static,
+    {
+        self.events
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Events::<E>::new()))
+            .as_any_mut()
+            .downcast_mut::<Events<E>>()
+            .unwrap()
+            .send(event);
+    }
+
+    /// Returns queue of events of specified type.
+    /// Returns `None` if no events of this type were sent.
+    pub fn events<E>(&self) -> Option<&Events<E>>
+    where
+        E: 'static,
+    {
+        let events = self.events.get(&TypeId::of::<E>())?;
+        Some(events.as_any().downcast_ref::<Events<E>>().unwrap())
+    }
+
+    /// Returns iterator over events of specified type not yet seen by the reader
+    /// and advances the reader past them.
+    pub fn read_events<E>(&self, reader: &mut EventReader<E>) -> impl Iterator<Item = &E>
+    where
+        E: 'static,
+    {
+        self.events::<E>()
+            .map(|events| events.read(reader))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Transfers ownership of the entity from the caller to the `World`.
+    /// After this call, entity won't be despawned until [`World::despawn`] is called with this entity id.
+    #[cfg(feature = "rc")]
+    pub fn keep<T>(&mut self, entity: Entity<T>) {
+        assert!(self.entities.is_owner_of(&entity));
+        self.entities.give_ownership(entity);
+    }
+
+    /// Transfers ownership of the entity from the `World` to the caller.
+    /// After this call, entity should be despawned by dropping returned entity reference,
+    /// or by returning ownership to the `World` and then called [`World::despawn`]
+    ///
+    /// Returns error if entity with specified id does not exists,
+    /// or if that entity is not owned by the `World`.
+    #[cfg(feature = "rc")]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
+    pub fn take(&mut self, entity: &EntityId) -> Result<Entity, OwnershipError> {
+        self.entities.take_ownership(entity)
+    }
+
+    /// Returns new strong reference to the entity with specified id.
+    ///
+    /// Returns `None` if entity is not alive or has no strong references.
+    /// Entity with last strong reference dropped is not upgraded
+    /// even before [`World::maintain`] despawns it.
+    /// Use [`World::take`] for entities owned by the `World`.
+    #[cfg(feature = "rc")]
+    #[inline]
+    #[cfg_attr(feature = "leak-detection", track_caller)]
+    pub fn upgrade(&self, entity: &EntityId) -> Option<Entity> {
+        self.entities.upgrade(entity)
+    }
+
+    /// Returns number of strong references to the entity with specified id.
+    ///
+    /// Entities owned by the `World` have no strong references.
+    /// Entity with last strong reference dropped has none as well
+    /// until [`World::maintain`] despawns it.
+    #[cfg(feature = "rc")]
+    #[inline]
+    pub fn strong_count(&self, entity: &EntityId) -> Result<usize, NoSuchEntity> {
+        self.entities.strong_count(entity)
+    }
+
+    /// Returns entities that hold strong references
+    /// for more than `cycles` calls to [`World::maintain`],
+    /// together with locations where live references were created.
+    ///
+    /// Locations are recorded for references created by [`World::spawn_owning`],
+    /// [`World::take`], [`World::upgrade`] and by cloning [`Entity`].
+    #[cfg(feature = "leak-detection")]
+    pub fn leak_report(&self, cycles: u64) -> Vec<LeakedEntity> {
+        self.entities.leak_report(cycles)
+    }
+
+    /// Checks if specified entity has componet of specified type.
+    #[cfg(feature = "rc")]
+    #[inline]
+    pub fn has_component_owning<T: 'static, U>(&self, entity: &Entity<U>) -> bool {
+        assert!(self.entities.is_owner_of(entity));
+
+        let (archetype, _idx) = self.entities.get(entity).unwrap();
+        self.archetypes[archetype as usize].contains_id(TypeId::of::<T>())
+    }
+
+    /// Attemtps to check if specified entity has componet of specified type.
+    ///
+    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    #[inline]
+    pub fn has_component<T: 'static>(&self, entity: &EntityId) -> Result<bool, NoSuchEntity> {
+        let (archetype, _idx) = self.entities.get(entity).ok_or(NoSuchEntity)?;
+        Ok(self.archetypes[archetype as usize].contains_id(TypeId::of::<T>()))
+    }
+
+    /// Checks if specified entity is still alive.
+    #[inline]
+    pub fn is_alive(&self, entity: &EntityId) -> bool {
+        self.entities.get(entity).is_some()
+    }
+
+    /// Registers `fmt::Debug` implementation of the component type.
+    ///
+    /// Components of registered types are printed by [`World::debug_entity`] and [`World::dump`].
+    /// Values of components that are not registered are omitted.
+    pub fn register_debug<T>(&mut self)
+    where
+        T: Component + fmt::Debug,
+    {
+        self.register::<T>(|info| {
+            info.debug_fmt = Some(|ptr, f| unsafe { fmt::Debug::fmt(&*ptr.cast::<T>(), f) });
+        });
+    }
+
+    /// Registers `Clone` implementation of the component type.
+    ///
+    /// Entities can be cloned with [`World::clone_entity`]
+    /// and whole world can be cloned with [`World::try_clone`]
+    /// only if all their components are registered.
+    pub fn register_clone<T>(&mut self)
+    where
+        T: Component + Clone,
+    {
+        self.register::<T>(|info| {
+            info.clone_one =
+                Some(|src, dst| unsafe { ptr::write(dst.cast::<T>(), (*src.cast::<T>()).clone()) });
+        });
+    }
+
+    /// Registers component type to rewrite entity ids in its values
+    /// when entities are moved with [`World::transfer`] and [`World::merge`]
+    /// or remapped with [`World::map_entities`].
+    pub fn register_map_entities<T>(&mut self)
+    where
+        T: Component + MapEntities,
+    {
+        self.register::<T>(|info| {
+            info.map_entities = Some(|ptr, map| unsafe { (*ptr.cast::<T>()).map_entities(map) });
+        });
+    }
+
+    /// Registers stable id of the component type.
+    ///
+    /// In deterministic mode archetypes are ordered by ids of registered component types
+    /// instead of their type names, which may differ between builds and are not guaranteed
+    /// to be unique. Registered types are ordered before types that are not registered.
+    /// See [`World::set_deterministic`].
+    pub fn register_canonical_id<T>(&mut self, id: u64)
+    where
+        T: Component,
+    {
+        self.register::<T>(|info| info.canonical_id = Some(id));
+        self.canonical_len = 0;
+        self.canonicalize_archetypes();
+    }
+
+    /// Registers `Ord` implementation of the component type.
+    ///
+    /// In deterministic mode archetypes that differ only in values of shared component
+    /// of registered type are ordered by those values.
+    /// See [`World::set_deterministic`].
+    pub fn register_ord<T>(&mut self)
+    where
+        T: Component + Ord,
+    {
+        self.register::<T>(|info| {
+            info.cmp = Some(|lhs, rhs| unsafe { (*lhs.cast::<T>()).cmp(&*rhs.cast::<T>()) });
+        });
+        self.canonical_len = 0;
+        self.canonicalize_archetypes();
+    }
+
+    /// Rewrites entity ids in all components registered with [`World::register_map_entities`]
+    /// using provided map.
+    /// Rewritten components are marked as modified.
+    pub fn map_entities(&mut self, map: &EntityMap) {
+        self.epoch += 1;
+        for archetype in &mut self.archetypes {
+            archetype.map_entities(0..archetype.len(), map, self.epoch);
+        }
+    }
+
+    /// Updates registered info of the component type
+    /// and infos of the component type in all archetypes.
+    fn register<T>(&mut self, f: impl FnOnce(&mut ComponentInfo))
+    where
+        T: Component,
+    {
+        let info = self
+            .registry
+            .entry(TypeId::of::<T>())
+            .or_insert_with(ComponentInfo::of::<T>);
+
+        f(info);
+
+        for archetype in &mut self.archetypes {
+            archetype.update_info(info);
+        }
+    }
+
+    /// Spawns new entity with clones of all components of the entity with specified id.
+    /// World keeps ownership of the spawned entity and entity id is returned.
+    ///
+    /// If entity is not alive, fails with `Err(CloneError::NoSuchEntity)`.
+    /// If any component of the entity is not registered with [`World::register_clone`],
+    /// fails with `Err(CloneError::NotCloneable)`.
+    /// If `World` with fixed capacity has no space for the clone,
+    /// fails with `Err(CloneError::CapacityExceeded)`.
+    pub fn clone_entity(&mut self, entity: &EntityId) -> Result<EntityId, CloneError> {
+        let (archetype, idx) = self.entities.get(entity).ok_or(CloneError::NoSuchEntity)?;
+        check_cloneable(&self.archetypes[archetype as usize])?;
+
+        if self.entities.is_full() || self.archetypes[archetype as usize].is_full() {
+            return Err(CapacityExceeded.into());
+        }
+
+        self.epoch += 1;
+        let entities = &mut self.entities;
+        let (entity, dst_idx) = unsafe {
+            self.archetypes[archetype as usize].clone_entity(idx, || entities.spawn(), self.epoch)
+        };
+        self.entities.set_location(entity.idx, archetype, dst_idx);
+        self.observers.spawned(
+            entity,
+            archetype,
+            dst_idx,
+            &self.archetypes[archetype as usize],
+        );
+        Ok(entity)
+    }
+
+    /// Creates new prefab with components from the entity builder.
+    ///
+    /// If any component in the builder is not registered with [`World::register_clone`],
+    /// fails with `Err(CloneError::NotCloneable)`.
+    pub fn make_prefab(&self, builder: EntityBuilder) -> Result<Prefab, CloneError> {
+        let infos = builder
+            .component_types()
+            .map(|info| match self.registry.get(&info.id) {
+                Some(info) if info.clone_one.is_some() => Ok(*info),
+                _ => Err(CloneError::NotCloneable {
+                    debug_name: info.debug_name,
+                }),
+            })
+            .collect::<Result<SmallVec<[ComponentInfo; 8]>, _>>()?;
+
+        let mut ptrs = SmallVec::<[NonNull<u8>; 8]>::new();
+        builder.put(|ptr, _, _| ptrs.push(ptr));
+
+        Ok(unsafe { Prefab::new(infos.iter().zip(ptrs), true) })
+    }
+
+    /// Creates new prefab with clones of all components of the entity with specified id.
+    ///
+    /// If entity is not alive, fails with `Err(CloneError::NoSuchEntity)`.
+    /// If any component of the entity is not registered with [`World::register_clone`],
+    /// fails with `Err(CloneError::NotCloneable)`.
+    pub fn prefab_of(&self, entity: &EntityId) -> Result<Prefab, CloneError> {
+        let (archetype, idx) = self.entities.get(entity).ok_or(CloneError::NoSuchEntity)?;
+        let archetype = &self.archetypes[archetype as usize];
+        check_cloneable(archetype)?;
+
+        let components = archetype
+            .infos()
+            .map(|info| (info, archetype.component_ptr(idx, info.id).unwrap()));
+
+        Ok(unsafe { Prefab::new(components, false) })
+    }
+
+    /// Spawns new entity with clones of all components of the prefab.
+    /// World keeps ownership of the spawned entity and entity id is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity.
+    #[inline]
+    pub fn instantiate(&mut self, prefab: &Prefab) -> EntityId {
+        self.instantiate_with(prefab, ())
+    }
+
+    /// Spawns new entity with clones of all components of the prefab.
+    /// World keeps ownership of the spawned entity and entity id is returned.
+    ///
+    /// If `World` with fixed capacity has no space for the entity,
+    /// fails with `Err(CapacityExceeded)`.
+    #[inline]
+    pub fn try_instantiate(&mut self, prefab: &Prefab) -> Result<EntityId, CapacityExceeded> {
+        self.try_instantiate_with(prefab, ())
+    }
+
+    /// Spawns new entity with clones of components of the prefab
+    /// and components from provided bundle.
+    /// Bundle components override prefab components of the same type,
+    /// those are not cloned.
+    /// World keeps ownership of the spawned entity and entity id is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for the entity.
+    #[inline]
+    pub fn instantiate_with<B>(&mut self, prefab: &Prefab, overrides: B) -> EntityId
+    where
+        B: DynamicBundle,
+    {
+        match self.try_instantiate_with(prefab, overrides) {
+            Ok(entity) => entity,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Spawns new entity with clones of components of the prefab
+    /// and components from provided bundle.
+    /// Bundle components override prefab components of the same type,
+    /// those are not cloned.
+    /// World keeps ownership of the spawned entity and entity id is returned.
+    ///
+    /// If `World` with fixed capacity has no space for the entity,
+    /// fails with `Err(CapacityExceeded)`.
+    #[inline]
+    pub fn try_instantiate_with<B>(
+        &mut self,
+        prefab: &Prefab,
+        overrides: B,
+    ) -> Result<EntityId, CapacityExceeded>
+    where
+        B: DynamicBundle,
+    {
+        if !overrides.valid() {
+            panic!(
+                "Specified bundle `{}` is not valid. Check for duplicate component types",
+                type_name::<B>()
+            );
+        }
+
+        let archetype_idx = self.prefab_archetype_idx(prefab, &overrides, 1)?;
+        let overridden: SmallVec<[TypeId; 8]> = overrides.with_ids(|ids| ids.into());
+
+        let entity = self.entities.spawn();
+
+        self.epoch += 1;
+        let idx = unsafe {
+            self.archetypes[archetype_idx as usize].spawn_cloned(
+                entity,
+                prefab
+                    .components()
+                    .filter(|(info, _)| !overridden.contains(&info.id)),
+                overrides,
+                self.epoch,
+            )
+        };
+        self.entities.set_location(entity.idx, archetype_idx, idx);
+        self.observers.spawned(
+            entity,
+            archetype_idx,
+            idx,
+            &self.archetypes[archetype_idx as usize],
+        );
+        Ok(entity)
+    }
+
+    /// Spawns `count` new entities with clones of all components of the prefab.
+    /// World keeps ownership of spawned entities and their ids are returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `World` with fixed capacity has no space for all entities.
+    pub fn instantiate_batch(&mut self, prefab: &Prefab, count: usize) -> Vec<EntityId> {
+        match self.try_instantiate_batch(prefab, count) {
+            Ok(entities) => entities,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Spawns `count` new entities with clones of all components of the prefab.
+    /// World keeps ownership of spawned entities and their ids are returned.
+    ///
+    /// If `World` with fixed capacity has no space for all entities,
+    /// fails with `Err(CapacityExceeded)` before any entity is spawned.
+    pub fn try_instantiate_batch(
+        &mut self,
+        prefab: &Prefab,
+        count: usize,
+    ) -> Result<Vec<EntityId>, CapacityExceeded> {
+        let archetype_idx = self.prefab_archetype_idx(prefab, &(), count)?;
+
+        self.epoch += 1;
+
+        let archetype = &mut self.archetypes[archetype_idx as usize];
+        archetype.reserve(count);
+
+        let entities = &mut self.entities;
+        let observers = &mut self.observers;
+        let epoch = self.epoch;
+
+        let batch = (0..count)
+            .map(|_| {
+                let entity = entities.spawn();
+                let idx = unsafe { archetype.spawn_cloned(entity, prefab.components(), (), epoch) };
+                entities.set_location(entity.idx, archetype_idx, idx);
+                observers.spawned(entity, archetype_idx, idx, archetype);
+                entity
+            })
+            .collect();
+        Ok(batch)
+    }
+
+    /// Returns index of the archetype for `count` prefab instances with overrides.
+    /// Fails if `World` with fixed capacity has no space for them.
+    fn prefab_archetype_idx<B>(
+        &mut self,
+        prefab: &Prefab,
+        overrides: &B,
+        count: usize,
+    ) -> Result<u32, CapacityExceeded>
+    where
+        B: DynamicBundle,
+    {
+        self.check_prefab_space(prefab, overrides, count)?;
+
+        let archetype_idx = cached_archetype_idx(
+            &mut self.keys,
+            &mut self.ids,
+            &self.registry,
+            &mut self.archetypes,
+            &self.storage,
+            prefab,
+        );
+
+        if overrides.with_ids(|ids| ids.is_empty()) {
+            return Ok(self.canonical_idx(archetype_idx));
+        }
+
+        let archetype_idx = cached_insert_bundle_info(
+            &mut self.add_key,
+            &mut self.add_ids,
+            &self.registry,
+            &mut self.archetypes,
+            archetype_idx,
+            overrides,
+        )
+        .dst;
+        Ok(self.canonical_idx(archetype_idx))
+    }
+
+    /// Fails if `World` with fixed capacity has no space
+    /// for `count` prefab instances with overrides.
+    fn check_prefab_space<B>(
+        &self,
+        prefab: &Prefab,
+        overrides: &B,
+        count: usize,
+    ) -> Result<(), CapacityExceeded>
+    where
+        B: DynamicBundle,
+    {
+        let archetype_capacity = match self.storage.fixed_capacity {
+            None => return Ok(()),
+            Some(cap) => cap,
+        };
+
+        if !self.entities.has_space(count) {
+            return Err(CapacityExceeded);
+        }
+
+        let find = |ids: &[TypeId]| {
+            self.archetypes.iter().find(|a| {
+                a.shared_values().is_empty()
+                    && a.matches(
+                        prefab
+                            .ids()
+                            .iter()
+                            .copied()
+                            .filter(|id| !ids.contains(id))
+                            .chain(ids.iter().copied()),
+                    )
+            })
+        };
+
+        // Archetype of the prefab itself is created first for overridden instances.
+        let mut missing = match overrides.with_ids(|ids| ids.is_empty()) || find(&[]).is_some() {
+            true => 0,
+            false => 1,
+        };
+
+        match overrides.with_ids(find) {
+            Some(archetype) if !archetype.has_space(count) => return Err(CapacityExceeded),
+            Some(_) => {}
+            None if count > archetype_capacity => return Err(CapacityExceeded),
+            None => missing += 1,
+        }
+
+        if self.archetypes.len() + missing > self.archetypes.capacity() {
+            return Err(CapacityExceeded);
+        }
+        Ok(())
+    }
+
+    /// Returns new `World` with clones of all entities and their components.
+    ///
+    /// Entities keep their ids and change detection state.
+    /// All entities in the cloned `World` are owned by it,
+    /// strong references to entities are not cloned.
+    /// Events, observers and indices are not cloned.
+    ///
+    /// If any component in the world is not registered with [`World::register_clone`],
+    /// fails with `Err(CloneError::NotCloneable)`.
+    pub fn try_clone(&self) -> Result<Self, CloneError> {
+        for archetype in &self.archetypes {
+            check_cloneable(archetype)?;
+        }
+
+        let archetypes = self
+            .archetypes
+            .iter()
+            .map(|archetype| unsafe { archetype.clone_all() })
+            .collect();
+
+        Ok(World {
+            epoch: self.epoch,
+            #[cfg(feature = "rc")]
+            entities: self.entities.fork(DROP_QUEUE_INLINE_CAP),
+            #[cfg(not(feature = "rc"))]
+            entities: self.entities.fork(),
+            archetypes,
+            keys: self.keys.clone(),
+            ids: self.ids.clone(),
+            add_one: self.add_one.clone(),
+            add_key: self.add_key.clone(),
+            add_ids: self.add_ids.clone(),
+            sub_one: self.sub_one.clone(),
+            sub_key: self.sub_key.clone(),
+            sub_ids: self.sub_ids.clone(),
+            registry: self.registry.clone(),
+            delta_log: DeltaLog::default(),
+            events: HashMap::with_hasher(NoOpHasherBuilder),
+            observers: Observers::default(),
+            indices: Indices::default(),
+            #[cfg(feature = "rc")]
+            drop_queue: AllocVec::new(self.storage.alloc.clone()),
+            #[cfg(feature = "rc")]
+            cascade_depth: self.cascade_depth,
+            storage: self.storage.clone(),
+            canonical_len: self.canonical_len,
+            marker: PhantomData,
+            not_send: PhantomData,
+        })
+    }
+
+    /// Moves entities with specified ids from `other` world into this world.
+    /// Entities get new ids in this world, map from old ids to new ones is returned.
+    ///
+    /// Components registered with [`World::register_map_entities`]
+    /// of moved entities are remapped with returned map.
+    ///
+    /// If any entity is not alive, fails with `Err(TransferError::NoSuchEntity)`.
+    /// If any entity has strong references, fails with `Err(TransferError::NotOwned)`.
+    /// If `World` with fixed capacity has no space for moved entities,
+    /// fails with `Err(TransferError::CapacityExceeded)`.
+    /// No entities are moved on error.
+    pub fn transfer<B: Allocator>(
+        &mut self,
+        other: &mut World<B>,
+        entities: impl IntoIterator<Item = EntityId>,
+    ) -> Result<EntityMap, TransferError> {
+        self.transfer_impl(other, entities)
+    }
+
+    /// Moves all entities from `other` world into this world.
+    /// Components are moved column by column between matching archetypes.
+    /// Entities get new ids in this world, map from old ids to new ones is returned.
+    ///
+    /// Components registered with [`World::register_map_entities`]
+    /// of moved entities are remapped with returned map.
+    ///
+    /// If any entity has strong references, fails with `Err(TransferError::NotOwned)`.
+    /// If `World` with fixed capacity has no space for moved entities,
+    /// fails with `Err(TransferError::CapacityExceeded)`.
+    /// No entities are moved on error.
+    pub fn merge<B: Allocator>(
+        &mut self,
+        other: &mut World<B>,
+    ) -> Result<EntityMap, TransferError> {
+        self.merge_impl(other)
+    }
+
+    fn transfer_impl<B: Allocator>(
+        &mut self,
+        other: &mut World<B>,
+        entities: impl IntoIterator<Item = EntityId>,
+    ) -> Result<EntityMap, TransferError> {
+        let entities: Vec<EntityId> = entities.into_iter().collect();
+        for entity in &entities {
+            other.entities.check_despawn(entity)?;
+        }
+
+        if self.storage.fixed_capacity.is_some() {
+            let mut unique = HashSet::with_capacity_and_hasher(entities.len(), MulHasherBuilder);
+            let mut counts = alloc::vec![0; other.archetypes.len()];
+            for entity in &entities {
+                if unique.insert(entity.idx) {
+                    if let Some((archetype, _)) = other.entities.get(entity) {
+                        counts[archetype as usize] += 1;
+                    }
+                }
+            }
+            self.check_transfer_space(&other.archetypes, &counts)?;
+        }
+
+        self.import_registry(&other.registry);
+        self.epoch += 1;
+
+        let mut map = EntityMap::new();
+        let mut moved = Vec::with_capacity(entities.len());
+
+        // Destination archetype is resolved once per source archetype.
+        let mut dst_archetypes = alloc::vec![None; other.archetypes.len()];
+
+        for old in entities {
+            let (src_archetype, src_idx) = match other.entities.despawn(&old) {
+                Ok(location) => location,
+                // Duplicate id, entity is already moved.
+                Err(_) => continue,
+            };
+            other.delta_log.despawn(&mut other.epoch, old);
+            other.indices.despawn(old);
+
+            let dst_archetype = *dst_archetypes[src_archetype as usize].get_or_insert_with(|| {
+                transfer_archetype_idx(
+                    &mut self.keys,
+                    &mut self.ids,
+                    &self.registry,
+                    &mut self.archetypes,
+                    &self.storage,
+                    &other.archetypes[src_archetype as usize],
+                )
+            });
+
+            let new = self.entities.spawn();
+
+            let (dst_idx, opt_id) = unsafe {
+                other.archetypes[src_archetype as usize].transfer(
+                    &mut self.archetypes[dst_archetype as usize],
+                    src_idx,
+                    new,
+                    self.epoch,
+                )
+            };
+
+            self.entities.set_location(new.idx, dst_archetype, dst_idx);
+            if let Some(id) = opt_id {
+                other.entities.set_location(id, src_archetype, src_idx);
+            }
+            restore_order(
+                &mut other.entities,
+                &mut other.archetypes[src_archetype as usize],
+                src_archetype,
+                src_idx,
+            );
+
+            map.insert(old, new);
+            moved.push((dst_archetype, dst_idx as usize));
+        }
+
+        for (archetype, idx) in moved {
+            self.archetypes[archetype as usize].map_entities(idx..idx + 1, &map, self.epoch);
+        }
+
+        self.canonicalize_archetypes();
+        Ok(map)
+    }
+
+    fn merge_impl<B: Allocator>(
+        &mut self,
+        other: &mut World<B>,
+    ) -> Result<EntityMap, TransferError> {
+        for archetype in &other.archetypes {
+            for entity in archetype.entities() {
+                other.entities.check_despawn(entity)?;
+            }
         }
 
-        let (archetype, idx) = self.entities.get(entity).unwrap();
-        let archetype = &self.archetypes[archetype as usize];
-        let mut fetch = unsafe { Q::fetch(archetype, 0, self.epoch) }.expect("Query is prooven");
-        unsafe {
-            fetch.visit_chunk(chunk_idx(idx as usize));
+        if self.storage.fixed_capacity.is_some() {
+            let counts: Vec<usize> = other.archetypes.iter().map(Archetype::len).collect();
+            self.check_transfer_space(&other.archetypes, &counts)?;
         }
-        let item = unsafe { fetch.get_item(idx as usize) };
-        item
-    }
 
-    /// Queries components from specified entity.
-    ///
-    /// If query cannot be satisfied, returns `EntityError::MissingComponents`.
-    #[inline]
-    pub fn query_one<'a, Q>(
-        &'a self,
-        entity: &EntityId,
-    ) -> Result<<Q::Fetch as Fetch<'a>>::Item, EntityError>
-    where
-        Q: Query + ImmutableQuery + NonTrackingQuery,
-    {
-        debug_assert!(Q::is_valid(), "Immutable queries are always valid");
+        self.import_registry(&other.registry);
+        self.epoch += 1;
 
-        assert!(
-            !Q::mutates(),
-            "Invalid impl of `ImmutableQuery` for `{}`",
-            type_name::<Q>()
-        );
+        let mut map = EntityMap::new();
+        let mut moved = Vec::new();
+        let mut new_ids = Vec::new();
 
-        assert!(
-            !Q::tracks(),
-            "Invalid impl of `NonTrackingQuery` for `{}`",
-            type_name::<Q>()
-        );
+        for src_archetype in &mut other.archetypes {
+            if src_archetype.is_empty() {
+                continue;
+            }
 
-        let (archetype, idx) = self.entities.get(entity).ok_or(EntityError::NoSuchEntity)?;
-        let archetype = &self.archetypes[archetype as usize];
-        match unsafe { Q::fetch(archetype, 0, self.epoch) } {
-            None => Err(EntityError::MissingComponents),
-            Some(mut fetch) => {
-                let item = unsafe { fetch.get_item(idx as usize) };
-                Ok(item)
+            let dst_archetype = transfer_archetype_idx(
+                &mut self.keys,
+                &mut self.ids,
+                &self.registry,
+                &mut self.archetypes,
+                &self.storage,
+                src_archetype,
+            );
+
+            new_ids.clear();
+            for &old in src_archetype.entities() {
+                other.entities.despawn(&old)?;
+                other.delta_log.despawn(&mut other.epoch, old);
+                other.indices.despawn(old);
+                let new = self.entities.spawn();
+                map.insert(old, new);
+                new_ids.push(new);
             }
-        }
-    }
 
-    /// Queries components from specified entity.
-    ///
-    /// If query cannot be satisfied, returns `EntityError::MissingComponents`.
-    #[inline]
-    pub fn query_one_mut<'a, Q>(
-        &'a mut self,
-        entity: &EntityId,
-    ) -> Result<<Q::Fetch as Fetch<'a>>::Item, EntityError>
-    where
-        Q: Query + NonTrackingQuery,
-    {
-        assert!(Q::is_valid(), "Invalid query specified");
+            let dst_start = unsafe {
+                src_archetype.transfer_all(
+                    &mut self.archetypes[dst_archetype as usize],
+                    new_ids.iter().copied(),
+                    self.epoch,
+                )
+            };
 
-        assert!(
-            !Q::tracks(),
-            "Invalid impl of `NonTrackingQuery` for `{}`",
-            type_name::<Q>()
-        );
+            for (idx, new) in new_ids.iter().enumerate() {
+                self.entities
+                    .set_location(new.idx, dst_archetype, dst_start + idx as u32);
+            }
 
-        if Q::mutates() {
-            self.epoch += 1;
+            let dst_start = dst_start as usize;
+            moved.push((dst_archetype, dst_start..dst_start + new_ids.len()));
         }
 
-        let (archetype, idx) = self.entities.get(entity).ok_or(EntityError::NoSuchEntity)?;
-        let archetype = &self.archetypes[archetype as usize];
-        match unsafe { Q::fetch(archetype, 0, self.epoch) } {
-            None => Err(EntityError::MissingComponents),
-            Some(mut fetch) => {
-                unsafe {
-                    fetch.visit_chunk(chunk_idx(idx as usize));
-                }
-                let item = unsafe { fetch.get_item(idx as usize) };
-                Ok(item)
-            }
+        for (archetype, range) in moved {
+            self.archetypes[archetype as usize].map_entities(range, &map, self.epoch);
         }
-    }
 
-    /// Returns new [`Tracks`] instance to use with tracking queries.
-    ///
-    /// Returnd [`Tracks`] instance considers all modifications
-    /// since creation of the world as "new" for the first tracking query.
-    #[inline]
-    pub fn tracks(&self) -> Tracks {
-        Tracks { epoch: 0 }
+        self.canonicalize_archetypes();
+        Ok(map)
     }
 
-    /// Returns new [`Tracks`] instance to use with tracking queries.
-    ///
-    /// Returnd [`Tracks`] instance considers only modifications
-    /// that happen after this function call as "new" for the first tracking query.
-    #[inline]
-    pub fn tracks_now(&self) -> Tracks {
-        Tracks { epoch: self.epoch }
-    }
+    /// Fails if `World` with fixed capacity has no space for entities
+    /// moved from archetypes of another `World`.
+    /// `counts` holds number of moved entities for each source archetype.
+    fn check_transfer_space(
+        &self,
+        src_archetypes: &[Archetype],
+        counts: &[usize],
+    ) -> Result<(), CapacityExceeded> {
+        let archetype_capacity = match self.storage.fixed_capacity {
+            None => return Ok(()),
+            Some(cap) => cap,
+        };
 
-    /// Run world maintenance, completing all deferred operations on it.
-    ///
-    /// Currently deferred operations are:
-    /// * Despawn of entities with no strong references left
-    #[inline]
-    pub fn maintain(&mut self) {
-        #[cfg(feature = "rc")]
-        {
-            let queue = self.entities.drop_queue();
+        if !self.entities.has_space(counts.iter().sum()) {
+            return Err(CapacityExceeded);
+        }
 
-            loop {
-                queue.drain(&mut self.drop_queue);
+        let mut added = alloc::vec![0; self.archetypes.len()];
+        let mut missing = 0;
 
-                if self.drop_queue.is_empty() {
-                    break;
-                }
+        for (src, &count) in src_archetypes.iter().zip(counts) {
+            if count == 0 {
+                continue;
+            }
 
-                for id in self.drop_queue.drain(..) {
-                    let (archetype, idx) = self.entities.dropped(id);
-                    let opt_id =
-                        unsafe { self.archetypes[archetype as usize].despawn_unchecked(idx) };
-                    if let Some(id) = opt_id {
-                        self.entities.set_location(id, archetype, idx)
+            let dst = self
+                .archetypes
+                .iter()
+                .position(|a| a.shared_values() == src.shared_values() && a.matches(src.ids()));
+
+            match dst {
+                Some(dst) => {
+                    added[dst] += count;
+                    if !self.archetypes[dst].has_space(added[dst]) {
+                        return Err(CapacityExceeded);
                     }
                 }
+                None if count > archetype_capacity => return Err(CapacityExceeded),
+                None => missing += 1,
             }
         }
+
+        if self.archetypes.len() + missing > self.archetypes.capacity() {
+            return Err(CapacityExceeded);
+        }
+        Ok(())
     }
 
-    /// Transfers ownership of the entity from the caller to the `World`.
-    /// After this call, entity won't be despawned until [`World::despawn`] is called with this entity id.
-    #[cfg(feature = "rc")]
-    pub fn keep<T>(&mut self, entity: Entity<T>) {
-        assert!(self.entities.is_owner_of(&entity));
-        self.entities.give_ownership(entity);
+    /// Registers glue functions of component types registered in another world
+    /// and not yet registered in this one.
+    fn import_registry(&mut self, registry: &ComponentRegistry) {
+        for (id, info) in registry {
+            let entry = self.registry.entry(*id).or_insert(*info);
+            entry.debug_fmt = entry.debug_fmt.or(info.debug_fmt);
+            entry.clone_one = entry.clone_one.or(info.clone_one);
+            entry.map_entities = entry.map_entities.or(info.map_entities);
+
+            for archetype in &mut self.archetypes {
+                archetype.update_info(entry);
+            }
+        }
     }
 
-    /// Transfers ownership of the entity from the `World` to the caller.
-    /// After this call, entity should be despawned by dropping returned entity reference,
-    /// or by returning ownership to the `World` and then called [`World::despawn`]
+    /// Returns value that formats all components of the entity with specified id.
     ///
-    /// Returns error if entity with specified id does not exists,
-    /// or if that entity is not owned by the `World`.
-    #[cfg(feature = "rc")]
-    pub fn take(&mut self, entity: &EntityId) -> Result<Entity, OwnershipError> {
-        self.entities.take_ownership(entity)
+    /// Only components registered with [`World::register_debug`] are printed with their values.
+    ///
+    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    #[inline]
+    pub fn debug_entity(&self, entity: &EntityId) -> Result<DebugEntity<'_>, NoSuchEntity> {
+        let entity = self.entity(entity)?;
+        Ok(DebugEntity { entity })
     }
 
-    /// Checks if specified entity has componet of specified type.
-    #[cfg(feature = "rc")]
+    /// Returns value that formats all entities in the world with their components.
+    ///
+    /// Only components registered with [`World::register_debug`] are printed with their values.
+    /// Useful to produce readable snapshot of the world for bug reports.
     #[inline]
-    pub fn has_component_owning<T: 'static, U>(&self, entity: &Entity<U>) -> bool {
-        assert!(self.entities.is_owner_of(entity));
+    pub fn dump(&self) -> WorldDump<'_> {
+        WorldDump {
+            archetypes: &self.archetypes,
+        }
+    }
 
-        let (archetype, _idx) = self.entities.get(entity).unwrap();
-        self.archetypes[archetype as usize].contains_id(TypeId::of::<T>())
+    /// Returns read-only view of the entity with specified id.
+    ///
+    /// If entity is not alive, fails with `Err(NoSuchEntity)`.
+    #[inline]
+    pub fn entity(&self, entity: &EntityId) -> Result<EntityRef<'_>, NoSuchEntity> {
+        let (archetype, idx) = self.entities.get(entity).ok_or(NoSuchEntity)?;
+        Ok(EntityRef {
+            id: *entity,
+            archetype_idx: archetype,
+            idx,
+            archetype: &self.archetypes[archetype as usize],
+        })
     }
 
-    /// Attemtps to check if specified entity has componet of specified type.
+    /// Returns iterator over infos of all components of the entity with specified id.
     ///
     /// If entity is not alive, fails with `Err(NoSuchEntity)`.
     #[inline]
-    pub fn has_component<T: 'static>(&self, entity: &EntityId) -> Result<bool, NoSuchEntity> {
+    pub fn entity_components(
+        &self,
+        entity: &EntityId,
+    ) -> Result<impl ExactSizeIterator<Item = &ComponentInfo> + Clone + '_, NoSuchEntity> {
         let (archetype, _idx) = self.entities.get(entity).ok_or(NoSuchEntity)?;
-        Ok(self.archetypes[archetype as usize].contains_id(TypeId::of::<T>()))
+        Ok(self.archetypes[archetype as usize].infos())
     }
 
-    /// Checks if specified entity is still alive.
+    /// Returns type-erased pointer to the component with specified type id
+    /// of the entity with specified id.
+    ///
+    /// Pointer is valid for reads until `World` is modified.
+    ///
+    /// If entity is not alive, fails with `Err(EntityError::NoSuchEntity)`.
+    /// If entity does not have component with that id, fails with `Err(EntityError::MissingComponents)`.
     #[inline]
-    pub fn is_alive(&self, entity: &EntityId) -> bool {
-        self.entities.get(entity).is_some()
+    pub fn component_ptr(
+        &self,
+        entity: &EntityId,
+        type_id: TypeId,
+    ) -> Result<NonNull<u8>, EntityError> {
+        let (archetype, idx) = self.entities.get(entity).ok_or(EntityError::NoSuchEntity)?;
+        self.archetypes[archetype as usize]
+            .component_ptr(idx, type_id)
+            .ok_or(EntityError::MissingComponents)
     }
 
     /// Queries the world to iterate over entities and components specified by the query type.
@@ -900,40 +2983,97 @@ impl World {
     /// Queries the world to iterate over entities and components specified by the query type.
     ///
     /// This method can be used for queries that mutate components.
+    ///
+    /// Queries that access a component mutably along with other access to it
+    /// are rejected at compile time.
+    /// Works with queries that implement [`NoAlias`] - `&T`, `&mut T`, `Alt<T>`,
+    /// `Option` and `Modified` wrappers of them, `Shared<T>` and tuples of those.
+    /// Type parameter `I` is inferred.
+    /// Other queries can be used with [`World::query_mut_dynamic`].
+    ///
+    /// Distinct generic component types are assumed to be different types.
+    /// If they turn out to be the same type, aliasing is reported at runtime.
+    ///
+    /// ```
+    /// # use edict::{query::Alt, world::World};
+    /// let mut world = World::new();
+    /// world.query_mut::<(&mut u32, &u16, Option<Alt<u8>>), _>();
+    /// ```
+    ///
+    /// ```compile_fail,E0283
+    /// # use edict::world::World;
+    /// let mut world = World::new();
+    /// world.query_mut::<(&mut u32, &u16, Option<&u32>), _>();
+    /// ```
+    #[inline]
+    pub fn query_mut<'a, Q, I>(&'a mut self) -> QueryMut<'a, Q, ()>
+    where
+        Q: Query + NoAlias<I>,
+    {
+        self.query_mut_dynamic::<Q>()
+    }
+
+    /// Queries the world to iterate over entities and components specified by the query type.
+    ///
+    /// This method can be used for queries that mutate components.
+    ///
+    /// Works with any query, including custom queries and nested tuples
+    /// that do not implement [`NoAlias`].
+    /// Queries that access a component mutably along with other access to it
+    /// are reported at runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if query accesses a component mutably along with other access to it.
     #[inline]
-    pub fn query_mut<'a, Q>(&'a mut self) -> QueryMut<'a, Q, ()>
+    pub fn query_mut_dynamic<'a, Q>(&'a mut self) -> QueryMut<'a, Q, ()>
     where
         Q: Query,
     {
-        assert!(Q::is_valid(), "Invalid query specified");
+        assert_valid::<Q>();
 
         QueryMut {
-            epoch: &mut self.epoch,
+            epoch: Cell::from_mut(&mut self.epoch),
             archetypes: &self.archetypes,
             query: PhantomData,
             filter: (),
         }
     }
 
+    /// Returns [`WorldCell`] view of the world
+    /// that allows to borrow multiple queries at once.
+    ///
+    /// Queries borrowed from [`WorldCell`] must not access same components,
+    /// unless all of them access components immutably.
+    #[inline]
+    pub fn cell(&mut self) -> WorldCell<'_> {
+        WorldCell {
+            epoch: Cell::from_mut(&mut self.epoch),
+            archetypes: &self.archetypes,
+            borrows: RefCell::new(HashMap::with_hasher(NoOpHasherBuilder)),
+        }
+    }
+
     /// Splits the world into entity-meta and mutable query.
     /// Queries the world to iterate over entities and components specified by the query type.
     /// `EntityMeta` can be used to fetch and control some meta-information about entities query is alive,
     /// including checking if entity is alive, checking components attached to entity and taking, giving entity ownership.
     ///
     /// This method can be used for queries that mutate components.
+    /// Query must implement [`NoAlias`], see [`World::query_mut`].
     #[inline]
-    pub fn meta_query_mut<'a, Q>(&'a mut self) -> (EntityMeta<'a>, QueryMut<'a, Q, ()>)
+    pub fn meta_query_mut<'a, Q, I>(&'a mut self) -> (EntityMeta<'a>, QueryMut<'a, Q, ()>)
     where
-        Q: Query,
+        Q: Query + NoAlias<I>,
     {
-        assert!(Q::is_valid(), "Invalid query specified");
+        assert_valid::<Q>();
 
         let meta = EntityMeta {
             entities: &mut self.entities,
             archetypes: &self.archetypes,
         };
         let query = QueryMut {
-            epoch: &mut self.epoch,
+            epoch: Cell::from_mut(&mut self.epoch),
             archetypes: &self.archetypes,
             query: PhantomData,
             filter: (),
@@ -980,29 +3120,27 @@ impl World {
 
         for archetype in &self.archetypes {
             if let Some(mut fetch) = unsafe { Q::fetch(archetype, tracks_epoch, self.epoch) } {
-                for chunk_idx in 0..archetype.len() / CHUNK_LEN_USIZE {
+                for chunk_idx in 0..archetype.len() / CHUNK_LEN {
                     if unsafe { fetch.skip_chunk(chunk_idx) } {
                         continue;
                     }
 
-                    for idx in
-                        chunk_idx * CHUNK_LEN_USIZE..chunk_idx * CHUNK_LEN_USIZE + CHUNK_LEN_USIZE
-                    {
+                    for idx in chunk_idx * CHUNK_LEN..chunk_idx * CHUNK_LEN + CHUNK_LEN {
                         if !unsafe { fetch.skip_item(idx) } {
                             f(unsafe { fetch.get_item(idx) });
                         }
                     }
                 }
 
-                let tail = archetype.len() % CHUNK_LEN_USIZE;
+                let tail = archetype.len() % CHUNK_LEN;
 
                 if tail > 0 {
-                    let chunk_idx = archetype.len() / CHUNK_LEN_USIZE;
+                    let chunk_idx = archetype.len() / CHUNK_LEN;
                     if unsafe { fetch.skip_chunk(chunk_idx) } {
                         continue;
                     }
 
-                    for idx in chunk_idx * CHUNK_LEN_USIZE..chunk_idx * CHUNK_LEN_USIZE + tail {
+                    for idx in chunk_idx * CHUNK_LEN..chunk_idx * CHUNK_LEN + tail {
                         if !unsafe { fetch.skip_item(idx) } {
                             f(unsafe { fetch.get_item(idx) });
                         }
@@ -1016,13 +3154,14 @@ impl World {
     ///
     /// This method can be used for queries that mutate components.
     /// This method only works queries that does not track for component changes.
+    /// Query must implement [`NoAlias`], see [`World::query_mut`].
     #[inline]
-    pub fn for_each_mut<Q, F>(&mut self, mut f: F)
+    pub fn for_each_mut<Q, I, F>(&mut self, mut f: F)
     where
-        Q: Query + NonTrackingQuery,
+        Q: Query + NonTrackingQuery + NoAlias<I>,
         F: FnMut(QueryItem<'_, Q>),
     {
-        assert!(Q::is_valid(), "Invalid query specified");
+        assert_valid::<Q>();
 
         if Q::mutates() {
             self.epoch += 1
@@ -1031,25 +3170,25 @@ impl World {
         for archetype in &self.archetypes {
             if let Some(mut fetch) = unsafe { Q::fetch(archetype, 0, self.epoch) } {
                 if Q::mutates() {
-                    for chunk_idx in 0..archetype.len() / CHUNK_LEN_USIZE {
+                    for chunk_idx in 0..archetype.len() / CHUNK_LEN {
                         debug_assert!(!unsafe { fetch.skip_chunk(chunk_idx) });
                         unsafe { fetch.visit_chunk(chunk_idx) }
 
-                        let idx_begin = chunk_idx * CHUNK_LEN_USIZE;
-                        for idx in idx_begin..idx_begin + CHUNK_LEN_USIZE {
+                        let idx_begin = chunk_idx * CHUNK_LEN;
+                        for idx in idx_begin..idx_begin + CHUNK_LEN {
                             debug_assert!(!unsafe { fetch.skip_item(idx) });
                             f(unsafe { fetch.get_item(idx) });
                         }
                     }
 
-                    let tail = archetype.len() % CHUNK_LEN_USIZE;
+                    let tail = archetype.len() % CHUNK_LEN;
 
                     if tail > 0 {
-                        let chunk_idx = archetype.len() / CHUNK_LEN_USIZE;
+                        let chunk_idx = archetype.len() / CHUNK_LEN;
                         debug_assert!(!unsafe { fetch.skip_chunk(chunk_idx) });
                         unsafe { fetch.visit_chunk(chunk_idx) }
 
-                        let idx_begin = chunk_idx * CHUNK_LEN_USIZE;
+                        let idx_begin = chunk_idx * CHUNK_LEN;
                         for idx in idx_begin..idx_begin + tail {
                             debug_assert!(!unsafe { fetch.skip_item(idx) });
                             f(unsafe { fetch.get_item(idx) });
@@ -1068,13 +3207,14 @@ impl World {
     /// Iterates through world using specified query.
     ///
     /// This method can be used for queries that mutates components and track for component changes.
+    /// Query must implement [`NoAlias`], see [`World::query_mut`].
     #[inline]
-    pub fn for_each_tracked_mut<Q, F>(&mut self, tracks: &mut Tracks, mut f: F)
+    pub fn for_each_tracked_mut<Q, I, F>(&mut self, tracks: &mut Tracks, mut f: F)
     where
-        Q: Query,
+        Q: Query + NoAlias<I>,
         F: FnMut(QueryItem<'_, Q>),
     {
-        assert!(Q::is_valid(), "Invalid query specified");
+        assert_valid::<Q>();
 
         if Q::mutates() {
             self.epoch += 1
@@ -1085,7 +3225,7 @@ impl World {
 
         for archetype in &self.archetypes {
             if let Some(mut fetch) = unsafe { Q::fetch(archetype, tracks_epoch, self.epoch) } {
-                for chunk_idx in 0..archetype.len() / CHUNK_LEN_USIZE {
+                for chunk_idx in 0..archetype.len() / CHUNK_LEN {
                     if unsafe { fetch.skip_chunk(chunk_idx) } {
                         continue;
                     }
@@ -1094,18 +3234,18 @@ impl World {
                         unsafe { fetch.visit_chunk(chunk_idx) }
                     }
 
-                    let idx_begin = chunk_idx * CHUNK_LEN_USIZE;
-                    for idx in idx_begin..idx_begin + CHUNK_LEN_USIZE {
+                    let idx_begin = chunk_idx * CHUNK_LEN;
+                    for idx in idx_begin..idx_begin + CHUNK_LEN {
                         if !unsafe { fetch.skip_item(idx) } {
                             f(unsafe { fetch.get_item(idx) });
                         }
                     }
                 }
 
-                let tail = archetype.len() % CHUNK_LEN_USIZE;
+                let tail = archetype.len() % CHUNK_LEN;
 
                 if tail > 0 {
-                    let chunk_idx = archetype.len() / CHUNK_LEN_USIZE;
+                    let chunk_idx = archetype.len() / CHUNK_LEN;
                     if unsafe { fetch.skip_chunk(chunk_idx) } {
                         continue;
                     }
@@ -1114,7 +3254,7 @@ impl World {
                         unsafe { fetch.visit_chunk(chunk_idx) }
                     }
 
-                    let idx_begin = chunk_idx * CHUNK_LEN_USIZE;
+                    let idx_begin = chunk_idx * CHUNK_LEN;
                     for idx in idx_begin..idx_begin + tail {
                         if !unsafe { fetch.skip_item(idx) } {
                             f(unsafe { fetch.get_item(idx) });
@@ -1134,6 +3274,7 @@ pub struct SpawnBatch<'a, I> {
     archetype_idx: u32,
     archetype: &'a mut Archetype,
     entities: &'a mut Entities,
+    observers: &'a mut Observers,
 }
 
 impl<B, I> SpawnBatch<'_, I>
@@ -1151,6 +3292,7 @@ where
 
         let entities = &mut self.entities;
         let archetype = &mut self.archetype;
+        let observers = &mut self.observers;
         let archetype_idx = self.archetype_idx;
         let epoch = self.epoch;
 
@@ -1158,6 +3300,7 @@ where
             let entity = entities.spawn();
             let idx = archetype.spawn(entity, bundle, epoch);
             entities.set_location(entity.idx, archetype_idx, idx);
+            observers.spawned(entity, archetype_idx, idx, archetype);
         })
     }
 }
@@ -1177,6 +3320,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1191,6 +3336,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1207,6 +3354,7 @@ where
 
         let entities = &mut self.entities;
         let archetype = &mut self.archetype;
+        let observers = &mut self.observers;
         let archetype_idx = self.archetype_idx;
         let epoch = self.epoch;
 
@@ -1214,6 +3362,7 @@ where
             let entity = entities.spawn();
             let idx = archetype.spawn(entity, bundle, epoch);
             entities.set_location(entity.idx, archetype_idx, idx);
+            observers.spawned(entity, archetype_idx, idx, archetype);
             f(acc, entity)
         })
     }
@@ -1255,6 +3404,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1269,6 +3420,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1282,6 +3435,7 @@ where
 
         let entities = &mut self.entities;
         let archetype = &mut self.archetype;
+        let observers = &mut self.observers;
         let archetype_idx = self.archetype_idx;
         let epoch = self.epoch;
 
@@ -1289,6 +3443,7 @@ where
             let entity = entities.spawn();
             let idx = archetype.spawn(entity, bundle, epoch);
             entities.set_location(entity.idx, archetype_idx, idx);
+            observers.spawned(entity, archetype_idx, idx, archetype);
             f(acc, entity)
         })
     }
@@ -1310,6 +3465,7 @@ pub struct SpawnBatchOwned<'a, I> {
     archetype_idx: u32,
     archetype: &'a mut Archetype,
     entities: &'a mut Entities,
+    observers: &'a mut Observers,
 }
 
 #[cfg(feature = "rc")]
@@ -1328,6 +3484,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(*entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1342,6 +3500,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(*entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1358,6 +3518,7 @@ where
 
         let entities = &mut self.entities;
         let archetype = &mut self.archetype;
+        let observers = &mut self.observers;
         let archetype_idx = self.archetype_idx;
         let epoch = self.epoch;
 
@@ -1365,6 +3526,7 @@ where
             let entity = entities.spawn_owning();
             let idx = archetype.spawn(*entity, bundle, epoch);
             entities.set_location(entity.idx, archetype_idx, idx);
+            observers.spawned(*entity, archetype_idx, idx, archetype);
             f(acc, entity)
         })
     }
@@ -1414,6 +3576,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(*entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1428,6 +3592,8 @@ where
 
         self.entities
             .set_location(entity.idx, self.archetype_idx, idx);
+        self.observers
+            .spawned(*entity, self.archetype_idx, idx, self.archetype);
 
         Some(entity)
     }
@@ -1441,6 +3607,7 @@ where
 
         let entities = &mut self.entities;
         let archetype = &mut self.archetype;
+        let observers = &mut self.observers;
         let archetype_idx = self.archetype_idx;
         let epoch = self.epoch;
 
@@ -1448,6 +3615,7 @@ where
             let entity = entities.spawn_owning();
             let idx = archetype.spawn(*entity, bundle, epoch);
             entities.set_location(entity.idx, archetype_idx, idx);
+            observers.spawned(*entity, archetype_idx, idx, archetype);
             f(acc, entity)
         })
     }
@@ -1464,7 +3632,7 @@ where
 /// Mutable query builder.
 #[derive(Debug)]
 pub struct QueryMut<'a, Q, F> {
-    epoch: &'a mut u64,
+    epoch: &'a Cell<u64>,
     archetypes: &'a [Archetype],
     query: PhantomData<Q>,
     filter: F,
@@ -1497,7 +3665,7 @@ where
     {
         debug_assert!(!Q::mutates());
 
-        QueryIter::new(*self.epoch, self.archetypes, self.filter.clone())
+        QueryIter::new(self.epoch.get(), self.archetypes, self.filter.clone())
     }
 
     /// Returns iterator over query results.
@@ -1508,10 +3676,10 @@ where
         F: Clone,
     {
         if Q::mutates() {
-            *self.epoch += 1
+            self.epoch.set(self.epoch.get() + 1)
         };
 
-        QueryIter::new(*self.epoch, self.archetypes, self.filter.clone())
+        QueryIter::new(self.epoch.get(), self.archetypes, self.filter.clone())
     }
 
     /// Returns iterator over query results.
@@ -1521,10 +3689,10 @@ where
         Q: NonTrackingQuery,
     {
         if Q::mutates() {
-            *self.epoch += 1
+            self.epoch.set(self.epoch.get() + 1)
         };
 
-        QueryIter::new(*self.epoch, self.archetypes, self.filter)
+        QueryIter::new(self.epoch.get(), self.archetypes, self.filter)
     }
 
     /// Returns iterator over immutable query results.
@@ -1538,11 +3706,11 @@ where
 
         let iter = QueryTrackedIter::new(
             tracks.epoch,
-            *self.epoch,
+            self.epoch.get(),
             self.archetypes,
             self.filter.clone(),
         );
-        tracks.epoch = *self.epoch;
+        tracks.epoch = self.epoch.get();
         iter
     }
 
@@ -1553,16 +3721,16 @@ where
         F: Clone,
     {
         if Q::mutates() {
-            *self.epoch += 1
+            self.epoch.set(self.epoch.get() + 1)
         };
 
         let iter = QueryTrackedIter::new(
             tracks.epoch,
-            *self.epoch,
+            self.epoch.get(),
             self.archetypes,
             self.filter.clone(),
         );
-        tracks.epoch = *self.epoch;
+        tracks.epoch = self.epoch.get();
         iter
     }
 
@@ -1570,11 +3738,12 @@ where
     /// This method is available with tracking queries.
     pub fn tracked_into_iter(self, tracks: &mut Tracks) -> QueryTrackedIter<'a, Q, F> {
         if Q::mutates() {
-            *self.epoch += 1
+            self.epoch.set(self.epoch.get() + 1)
         };
 
-        let iter = QueryTrackedIter::new(tracks.epoch, *self.epoch, self.archetypes, self.filter);
-        tracks.epoch = *self.epoch;
+        let iter =
+            QueryTrackedIter::new(tracks.epoch, self.epoch.get(), self.archetypes, self.filter);
+        tracks.epoch = self.epoch.get();
         iter
     }
 }
@@ -1656,44 +3825,168 @@ where
     {
         debug_assert!(!Q::mutates());
 
-        let iter = QueryTrackedIter::new(tracks.epoch, self.epoch, self.archetypes, self.filter);
-        tracks.epoch = self.epoch;
-        iter
+        let iter = QueryTrackedIter::new(tracks.epoch, self.epoch, self.archetypes, self.filter);
+        tracks.epoch = self.epoch;
+        iter
+    }
+}
+
+/// Error returned in case specified [`EntityId`]
+/// does not reference any live entity in the [`World`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NoSuchEntity;
+
+impl fmt::Display for NoSuchEntity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Speicified entity is not found")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NoSuchEntity {}
+
+/// Error returned in case specified entity does not contain
+/// component of required type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MissingComponents;
+
+impl fmt::Display for MissingComponents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Speicified component is not found in entity")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MissingComponents {}
+
+/// Error returned if either entity reference is invalid
+/// or component of required type is not found for an entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntityError {
+    /// Error returned in case specified [`EntityId`]
+    /// does not reference any live entity in the [`World`].
+    NoSuchEntity,
+
+    /// Error returned in case specified entity does not contain
+    /// component of required type.
+    MissingComponents,
+}
+
+impl fmt::Display for EntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchEntity => fmt::Display::fmt(&NoSuchEntity, f),
+            Self::MissingComponents => fmt::Display::fmt(&MissingComponents, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EntityError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoSuchEntity => Some(&NoSuchEntity),
+            Self::MissingComponents => Some(&MissingComponents),
+        }
+    }
+}
+
+impl From<NoSuchEntity> for EntityError {
+    fn from(_: NoSuchEntity) -> Self {
+        EntityError::NoSuchEntity
+    }
+}
+
+impl From<MissingComponents> for EntityError {
+    fn from(_: MissingComponents) -> Self {
+        EntityError::MissingComponents
+    }
+}
+
+impl PartialEq<NoSuchEntity> for EntityError {
+    fn eq(&self, _: &NoSuchEntity) -> bool {
+        matches!(self, EntityError::NoSuchEntity)
+    }
+}
+
+impl PartialEq<MissingComponents> for EntityError {
+    fn eq(&self, _: &MissingComponents) -> bool {
+        matches!(self, EntityError::MissingComponents)
     }
 }
 
-/// Error returned in case specified [`EntityId`]
-/// does not reference any live entity in the [`World`].
+/// Error returned in case `World` with fixed capacity
+/// has no space for entity or archetype.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct NoSuchEntity;
+pub struct CapacityExceeded;
 
-impl fmt::Display for NoSuchEntity {
+impl fmt::Display for CapacityExceeded {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("Speicified entity is not found")
+        f.write_str("World capacity exceeded")
     }
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for NoSuchEntity {}
+impl std::error::Error for CapacityExceeded {}
 
-/// Error returned in case specified entity does not contain
-/// component of required type.
+/// Error that may occur when components are inserted to an entity.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct MissingComponents;
+pub enum InsertError {
+    /// Error returned in case specified [`EntityId`]
+    /// does not reference any live entity in the [`World`].
+    NoSuchEntity,
 
-impl fmt::Display for MissingComponents {
+    /// Error returned in case `World` with fixed capacity
+    /// has no space for entity with inserted components.
+    CapacityExceeded,
+}
+
+impl fmt::Display for InsertError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("Speicified component is not found in entity")
+        match self {
+            Self::NoSuchEntity => fmt::Display::fmt(&NoSuchEntity, f),
+            Self::CapacityExceeded => fmt::Display::fmt(&CapacityExceeded, f),
+        }
     }
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for MissingComponents {}
+impl std::error::Error for InsertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoSuchEntity => Some(&NoSuchEntity),
+            Self::CapacityExceeded => Some(&CapacityExceeded),
+        }
+    }
+}
 
-/// Error returned if either entity reference is invalid
-/// or component of required type is not found for an entity.
+impl From<NoSuchEntity> for InsertError {
+    fn from(_: NoSuchEntity) -> Self {
+        InsertError::NoSuchEntity
+    }
+}
+
+impl From<CapacityExceeded> for InsertError {
+    fn from(_: CapacityExceeded) -> Self {
+        InsertError::CapacityExceeded
+    }
+}
+
+impl PartialEq<NoSuchEntity> for InsertError {
+    fn eq(&self, _: &NoSuchEntity) -> bool {
+        matches!(self, InsertError::NoSuchEntity)
+    }
+}
+
+impl PartialEq<CapacityExceeded> for InsertError {
+    fn eq(&self, _: &CapacityExceeded) -> bool {
+        matches!(self, InsertError::CapacityExceeded)
+    }
+}
+
+/// Error that may occur when components are removed from an entity.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum EntityError {
+pub enum RemoveError {
     /// Error returned in case specified [`EntityId`]
     /// does not reference any live entity in the [`World`].
     NoSuchEntity,
@@ -1701,48 +3994,66 @@ pub enum EntityError {
     /// Error returned in case specified entity does not contain
     /// component of required type.
     MissingComponents,
+
+    /// Error returned in case `World` with fixed capacity
+    /// has no space for entity without removed components.
+    CapacityExceeded,
 }
 
-impl fmt::Display for EntityError {
+impl fmt::Display for RemoveError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::NoSuchEntity => fmt::Display::fmt(&NoSuchEntity, f),
             Self::MissingComponents => fmt::Display::fmt(&MissingComponents, f),
+            Self::CapacityExceeded => fmt::Display::fmt(&CapacityExceeded, f),
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for EntityError {
+impl std::error::Error for RemoveError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::NoSuchEntity => Some(&NoSuchEntity),
             Self::MissingComponents => Some(&MissingComponents),
+            Self::CapacityExceeded => Some(&CapacityExceeded),
         }
     }
 }
 
-impl From<NoSuchEntity> for EntityError {
+impl From<NoSuchEntity> for RemoveError {
     fn from(_: NoSuchEntity) -> Self {
-        EntityError::NoSuchEntity
+        RemoveError::NoSuchEntity
     }
 }
 
-impl From<MissingComponents> for EntityError {
+impl From<MissingComponents> for RemoveError {
     fn from(_: MissingComponents) -> Self {
-        EntityError::MissingComponents
+        RemoveError::MissingComponents
     }
 }
 
-impl PartialEq<NoSuchEntity> for EntityError {
+impl From<CapacityExceeded> for RemoveError {
+    fn from(_: CapacityExceeded) -> Self {
+        RemoveError::CapacityExceeded
+    }
+}
+
+impl PartialEq<NoSuchEntity> for RemoveError {
     fn eq(&self, _: &NoSuchEntity) -> bool {
-        matches!(self, EntityError::NoSuchEntity)
+        matches!(self, RemoveError::NoSuchEntity)
     }
 }
 
-impl PartialEq<MissingComponents> for EntityError {
+impl PartialEq<MissingComponents> for RemoveError {
     fn eq(&self, _: &MissingComponents) -> bool {
-        matches!(self, EntityError::MissingComponents)
+        matches!(self, RemoveError::MissingComponents)
+    }
+}
+
+impl PartialEq<CapacityExceeded> for RemoveError {
+    fn eq(&self, _: &CapacityExceeded) -> bool {
+        matches!(self, RemoveError::CapacityExceeded)
     }
 }
 
@@ -1794,6 +4105,160 @@ impl std::error::Error for OwnershipError {
     }
 }
 
+/// Error that may occur when cloning entities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CloneError {
+    /// Error returned in case specified [`EntityId`]
+    /// does not reference any live entity in the [`World`].
+    NoSuchEntity,
+
+    /// Error returned in case component type
+    /// is not registered with [`World::register_clone`].
+    NotCloneable {
+        /// Name of the component type.
+        debug_name: &'static str,
+    },
+
+    /// Error returned in case `World` with fixed capacity
+    /// has no space for cloned entity.
+    CapacityExceeded,
+}
+
+impl From<NoSuchEntity> for CloneError {
+    fn from(_: NoSuchEntity) -> Self {
+        CloneError::NoSuchEntity
+    }
+}
+
+impl From<CapacityExceeded> for CloneError {
+    fn from(_: CapacityExceeded) -> Self {
+        CloneError::CapacityExceeded
+    }
+}
+
+impl PartialEq<NoSuchEntity> for CloneError {
+    fn eq(&self, _: &NoSuchEntity) -> bool {
+        matches!(self, CloneError::NoSuchEntity)
+    }
+}
+
+impl PartialEq<CapacityExceeded> for CloneError {
+    fn eq(&self, _: &CapacityExceeded) -> bool {
+        matches!(self, CloneError::CapacityExceeded)
+    }
+}
+
+impl fmt::Display for CloneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchEntity => fmt::Display::fmt(&NoSuchEntity, f),
+            Self::NotCloneable { debug_name } => {
+                write!(
+                    f,
+                    "Component `{}` is not registered as cloneable",
+                    debug_name
+                )
+            }
+            Self::CapacityExceeded => fmt::Display::fmt(&CapacityExceeded, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CloneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoSuchEntity => Some(&NoSuchEntity),
+            Self::CapacityExceeded => Some(&CapacityExceeded),
+            _ => None,
+        }
+    }
+}
+
+/// Error that may occur when entities are moved from another `World`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TransferError {
+    /// Error returned in case specified [`EntityId`]
+    /// does not reference any live entity in the other [`World`].
+    NoSuchEntity,
+
+    /// Error returned in case specified [`EntityId`]
+    /// does not reference an entity currently owned by the other [`World`].
+    #[cfg(feature = "rc")]
+    NotOwned,
+
+    /// Error returned in case `World` with fixed capacity
+    /// has no space for moved entities.
+    CapacityExceeded,
+}
+
+impl From<NoSuchEntity> for TransferError {
+    fn from(_: NoSuchEntity) -> Self {
+        TransferError::NoSuchEntity
+    }
+}
+
+#[cfg(feature = "rc")]
+impl From<OwnershipError> for TransferError {
+    fn from(err: OwnershipError) -> Self {
+        match err {
+            OwnershipError::NoSuchEntity => TransferError::NoSuchEntity,
+            OwnershipError::NotOwned => TransferError::NotOwned,
+        }
+    }
+}
+
+impl From<CapacityExceeded> for TransferError {
+    fn from(_: CapacityExceeded) -> Self {
+        TransferError::CapacityExceeded
+    }
+}
+
+impl PartialEq<NoSuchEntity> for TransferError {
+    fn eq(&self, _: &NoSuchEntity) -> bool {
+        matches!(self, TransferError::NoSuchEntity)
+    }
+}
+
+impl PartialEq<CapacityExceeded> for TransferError {
+    fn eq(&self, _: &CapacityExceeded) -> bool {
+        matches!(self, TransferError::CapacityExceeded)
+    }
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchEntity => fmt::Display::fmt(&NoSuchEntity, f),
+            #[cfg(feature = "rc")]
+            Self::NotOwned => f.write_str("Entity is not owned by World"),
+            Self::CapacityExceeded => fmt::Display::fmt(&CapacityExceeded, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransferError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoSuchEntity => Some(&NoSuchEntity),
+            Self::CapacityExceeded => Some(&CapacityExceeded),
+            #[cfg(feature = "rc")]
+            Self::NotOwned => None,
+        }
+    }
+}
+
+/// Checks that all components of the archetype are registered as cloneable.
+fn check_cloneable(archetype: &Archetype) -> Result<(), CloneError> {
+    match archetype.infos().find(|info| info.clone_one.is_none()) {
+        None => Ok(()),
+        Some(info) => Err(CloneError::NotCloneable {
+            debug_name: info.debug_name,
+        }),
+    }
+}
+
 /// Umbrella trait for [`DynamicBundle`] and [`Bundle`].
 trait AsBundle {
     /// Returns static key if the bundle type have one.
@@ -1840,18 +4305,229 @@ where
     }
 }
 
-fn make_archetype_idx<B>(archetypes: &mut Vec<Archetype>, bundle: &B) -> u32
+/// Maps component type id to registered component info.
+type ComponentRegistry = HashMap<TypeId, ComponentInfo, NoOpHasherBuilder>;
+
+/// Returns registered info for the component type if there is one.
+fn registered_info<'a>(
+    registry: &'a ComponentRegistry,
+    info: &'a ComponentInfo,
+) -> &'a ComponentInfo {
+    registry.get(&info.id).unwrap_or(info)
+}
+
+impl AsBundle for Prefab {
+    fn key() -> Option<TypeId> {
+        None
+    }
+
+    fn with_ids<R>(&self, f: impl FnOnce(&[TypeId]) -> R) -> R {
+        f(self.ids())
+    }
+
+    fn with_components<R>(&self, f: impl FnOnce(&[ComponentInfo]) -> R) -> R {
+        f(self.infos())
+    }
+}
+
+impl AsBundle for Archetype {
+    fn key() -> Option<TypeId> {
+        None
+    }
+
+    fn with_ids<R>(&self, f: impl FnOnce(&[TypeId]) -> R) -> R {
+        let ids: SmallVec<[TypeId; 8]> = self.ids().collect();
+        f(&ids)
+    }
+
+    fn with_components<R>(&self, f: impl FnOnce(&[ComponentInfo]) -> R) -> R {
+        let infos: SmallVec<[ComponentInfo; 8]> = self.infos().copied().collect();
+        f(&infos)
+    }
+}
+
+/// Sets locations of entities moved by batch to the end of archetype starting at `start`.
+fn set_batch_locations(
+    entities: &mut Entities,
+    archetype: &Archetype,
+    archetype_idx: u32,
+    start: u32,
+) {
+    for (offset, entity) in archetype.entities()[start as usize..].iter().enumerate() {
+        entities.set_location(entity.idx, archetype_idx, start + offset as u32);
+    }
+}
+
+/// Restores order of entities in archetype with deterministic order
+/// after entity at `idx` was swap-removed from it.
+fn restore_order(entities: &mut Entities, archetype: &mut Archetype, archetype_idx: u32, idx: u32) {
+    if archetype.config().deterministic && archetype.restore_order(idx) {
+        for (offset, entity) in archetype.entities()[idx as usize..].iter().enumerate() {
+            entities.set_location(entity.idx, archetype_idx, idx + offset as u32);
+        }
+    }
+}
+
+/// Name of component type that defines canonical order of archetypes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CanonicalName {
+    Id(u64),
+    Name(&'static str),
+}
+
+impl CanonicalName {
+    fn new(info: Option<&ComponentInfo>, name: &'static str) -> Self {
+        match info.and_then(|info| info.canonical_id) {
+            Some(id) => CanonicalName::Id(id),
+            None => CanonicalName::Name(name),
+        }
+    }
+}
+
+/// Shared component in key that defines canonical order of archetypes.
+struct CanonicalShared {
+    name: CanonicalName,
+    id: TypeId,
+    ptr: *const u8,
+    cmp: Option<unsafe fn(*const u8, *const u8) -> Ordering>,
+}
+
+/// Key that defines canonical order of archetypes.
+struct CanonicalKey {
+    components: Vec<(CanonicalName, TypeId)>,
+    shared: Vec<CanonicalShared>,
+}
+
+impl CanonicalKey {
+    fn new(archetype: &Archetype, registry: &ComponentRegistry) -> Self {
+        let mut components: Vec<_> = archetype
+            .infos()
+            .map(|info| (CanonicalName::new(Some(info), info.debug_name), info.id))
+            .collect();
+        components.sort_unstable_by_key(|&(name, _)| name);
+
+        let mut shared: Vec<_> = archetype
+            .shared_values()
+            .iter()
+            .map(|(id, name, ptr)| {
+                let info = registry.get(&id);
+                CanonicalShared {
+                    name: CanonicalName::new(info, name),
+                    id,
+                    ptr,
+                    cmp: info.and_then(|info| info.cmp),
+                }
+            })
+            .collect();
+        shared.sort_unstable_by_key(|shared| shared.name);
+
+        CanonicalKey { components, shared }
+    }
+
+    /// Compares keys by names of component types and then by values of shared components.
+    /// Distinct types with equal names and shared values which types
+    /// have no registered `Ord` implementation compare equal.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let order = self
+            .components
+            .iter()
+            .map(|&(name, _)| name)
+            .cmp(other.components.iter().map(|&(name, _)| name))
+            .then_with(|| {
+                self.shared
+                    .iter()
+                    .map(|shared| shared.name)
+                    .cmp(other.shared.iter().map(|shared| shared.name))
+            });
+        if order != Ordering::Equal {
+            return order;
+        }
+
+        let same_types = self
+            .components
+            .iter()
+            .zip(&other.components)
+            .all(|(lhs, rhs)| lhs.1 == rhs.1);
+        if !same_types {
+            return Ordering::Equal;
+        }
+
+        for (lhs, rhs) in self.shared.iter().zip(&other.shared) {
+            if lhs.id != rhs.id {
+                return Ordering::Equal;
+            }
+            if let Some(cmp) = lhs.cmp {
+                // Values have the same type, which is registered with this function.
+                let order = unsafe { cmp(lhs.ptr, rhs.ptr) };
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// Panics if archetypes list has fixed capacity and is full.
+fn assert_archetypes_capacity(archetypes: &Vec<Archetype>, storage: &StorageConfig) {
+    assert!(
+        storage.fixed_capacity.is_none() || archetypes.len() < archetypes.capacity(),
+        "Archetype capacity exceeded"
+    );
+}
+
+fn make_archetype_idx<B>(
+    registry: &ComponentRegistry,
+    archetypes: &mut Vec<Archetype>,
+    storage: &StorageConfig,
+    bundle: &B,
+) -> u32
 where
     B: AsBundle,
 {
+    match archetypes.iter().position(|a| {
+        a.shared_values().is_empty() && bundle.with_ids(|ids| a.matches(ids.iter().copied()))
+    }) {
+        None => {
+            assert!(archetypes.len() < MAX_IDX_USIZE, "Too many archetypes");
+            assert_archetypes_capacity(archetypes, storage);
+
+            let archetype = bundle.with_components(|infos| {
+                Archetype::new(
+                    infos.iter().map(|info| registered_info(registry, info)),
+                    storage.clone(),
+                )
+            });
+            archetypes.push(archetype);
+            let idx = archetypes.len() - 1;
+            idx as u32
+        }
+        Some(idx) => idx as u32,
+    }
+}
+
+/// Returns index of archetype with specified components and shared values.
+/// Creates new archetype if none found.
+fn make_shared_archetype_idx(
+    registry: &ComponentRegistry,
+    archetypes: &mut Vec<Archetype>,
+    storage: &StorageConfig,
+    infos: &[ComponentInfo],
+    shared: &SharedValues,
+) -> u32 {
     match archetypes
         .iter()
-        .position(|a| bundle.with_ids(|ids| a.matches(ids.iter().copied())))
+        .position(|a| a.shared_values() == shared && a.matches(infos.iter().map(|info| info.id)))
     {
         None => {
             assert!(archetypes.len() < MAX_IDX_USIZE, "Too many archetypes");
+            assert_archetypes_capacity(archetypes, storage);
 
-            let archetype = bundle.with_components(|infos| Archetype::new(infos.iter()));
+            let archetype = Archetype::new(
+                infos.iter().map(|info| registered_info(registry, info)),
+                storage.clone(),
+            )
+            .with_shared(shared.clone());
             archetypes.push(archetype);
             let idx = archetypes.len() - 1;
             idx as u32
@@ -1860,9 +4536,41 @@ where
     }
 }
 
+/// Splits batch sorted by archetype index into groups
+/// of consecutive items in the same archetype.
+fn archetype_groups<T>(mut items: &[(u32, u32, T)]) -> impl Iterator<Item = &[(u32, u32, T)]> {
+    core::iter::from_fn(move || {
+        let archetype = items.first()?.0;
+        let len = items.iter().take_while(|item| item.0 == archetype).count();
+        let (group, rest) = items.split_at(len);
+        items = rest;
+        Some(group)
+    })
+}
+
+/// Returns index of archetype in this `World` for entities
+/// moved from archetype of another `World`.
+fn transfer_archetype_idx(
+    keys: &mut HashMap<TypeId, u32, NoOpHasherBuilder>,
+    ids: &mut HashMap<Vec<TypeId>, u32, MulHasherBuilder>,
+    registry: &ComponentRegistry,
+    archetypes: &mut Vec<Archetype>,
+    storage: &StorageConfig,
+    src: &Archetype,
+) -> u32 {
+    if src.shared_values().is_empty() {
+        cached_archetype_idx(keys, ids, registry, archetypes, storage, src)
+    } else {
+        let infos: Vec<ComponentInfo> = src.infos().copied().collect();
+        make_shared_archetype_idx(registry, archetypes, storage, &infos, src.shared_values())
+    }
+}
+
 fn get_archetype_idx<B>(
     map: &mut HashMap<Vec<TypeId>, u32, MulHasherBuilder>,
+    registry: &ComponentRegistry,
     archetypes: &mut Vec<Archetype>,
+    storage: &StorageConfig,
     bundle: &B,
 ) -> u32
 where
@@ -1873,7 +4581,7 @@ where
     match raw_entry {
         RawEntryMut::Occupied(entry) => *entry.get(),
         RawEntryMut::Vacant(entry) => {
-            let idx = make_archetype_idx(archetypes, bundle);
+            let idx = make_archetype_idx(registry, archetypes, storage, bundle);
             entry.insert(bundle.with_ids(|ids| ids.into()), idx);
             idx
         }
@@ -1883,18 +4591,20 @@ where
 fn cached_archetype_idx<B>(
     keys: &mut HashMap<TypeId, u32, NoOpHasherBuilder>,
     ids: &mut HashMap<Vec<TypeId>, u32, MulHasherBuilder>,
+    registry: &ComponentRegistry,
     archetypes: &mut Vec<Archetype>,
+    storage: &StorageConfig,
     bundle: &B,
 ) -> u32
 where
     B: AsBundle,
 {
     match B::key() {
-        None => get_archetype_idx(ids, archetypes, bundle),
+        None => get_archetype_idx(ids, registry, archetypes, storage, bundle),
         Some(key) => match keys.entry(key) {
             Entry::Occupied(entry) => *entry.get(),
             Entry::Vacant(entry) => {
-                let idx = get_archetype_idx(ids, archetypes, bundle);
+                let idx = get_archetype_idx(ids, registry, archetypes, storage, bundle);
                 entry.insert(idx);
                 idx
             }
@@ -1902,7 +4612,11 @@ where
     }
 }
 
-fn make_insert_info<T>(archetypes: &mut Vec<Archetype>, src: u32) -> InsertInfo
+fn make_insert_info<T>(
+    registry: &ComponentRegistry,
+    archetypes: &mut Vec<Archetype>,
+    src: u32,
+) -> InsertInfo
 where
     T: Component,
 {
@@ -1910,16 +4624,19 @@ where
         let ids = archetypes[src as usize]
             .ids()
             .chain(Some(TypeId::of::<T>()));
-        a.matches(ids)
+        a.shared_values() == archetypes[src as usize].shared_values() && a.matches(ids)
     }) {
         None => {
             assert!(archetypes.len() < MAX_IDX_USIZE, "Too many archetypes");
+            assert_archetypes_capacity(archetypes, archetypes[src as usize].config());
 
             let archetype = Archetype::new(
                 archetypes[src as usize]
                     .infos()
-                    .chain(Some(&ComponentInfo::of::<T>())),
-            );
+                    .chain(Some(registered_info(registry, &ComponentInfo::of::<T>()))),
+                archetypes[src as usize].config().clone(),
+            )
+            .with_shared(archetypes[src as usize].shared_values().clone());
 
             // let meta = InsertMeta::new::<T>(&archetypes[src as usize], &archetype);
 
@@ -1943,6 +4660,7 @@ where
 
 fn cached_insert_info<'a, T>(
     keys: &'a mut HashMap<(u32, TypeId), InsertInfo, MulHasherBuilder>,
+    registry: &ComponentRegistry,
     archetypes: &mut Vec<Archetype>,
     src: u32,
 ) -> &'a InsertInfo
@@ -1952,13 +4670,14 @@ where
     match keys.entry((src, TypeId::of::<T>())) {
         Entry::Occupied(entry) => entry.into_mut(),
         Entry::Vacant(entry) => {
-            let info = make_insert_info::<T>(archetypes, src);
+            let info = make_insert_info::<T>(registry, archetypes, src);
             entry.insert(info.clone())
         }
     }
 }
 
 fn make_insert_bundle_info<B>(
+    registry: &ComponentRegistry,
     archetypes: &mut Vec<Archetype>,
     src: u32,
     bundle: &B,
@@ -1967,21 +4686,25 @@ where
     B: DynamicBundle,
 {
     match archetypes.iter().position(|a| {
-        bundle.with_ids(|ids| {
-            let ids = archetypes[src as usize].ids().chain(ids.iter().copied());
-            a.matches(ids)
-        })
+        a.shared_values() == archetypes[src as usize].shared_values()
+            && bundle.with_ids(|ids| {
+                let ids = archetypes[src as usize].ids().chain(ids.iter().copied());
+                a.matches(ids)
+            })
     }) {
         None => {
             assert!(archetypes.len() < MAX_IDX_USIZE, "Too many archetypes");
+            assert_archetypes_capacity(archetypes, archetypes[src as usize].config());
 
             let archetype = bundle.with_components(|infos| {
                 Archetype::new(
                     archetypes[src as usize]
                         .infos()
                         .filter(|info| !bundle.contains_id(info.id))
-                        .chain(infos),
+                        .chain(infos.iter().map(|info| registered_info(registry, info))),
+                    archetypes[src as usize].config().clone(),
                 )
+                .with_shared(archetypes[src as usize].shared_values().clone())
             });
 
             // let meta = InsertBundleMeta::new(&archetypes[src as usize], &archetype, bundle);
@@ -2006,6 +4729,7 @@ where
 
 fn get_insert_bundle_info<'a, B>(
     map: &'a mut HashMap<(u32, Vec<TypeId>), InsertBundleInfo, MulHasherBuilder>,
+    registry: &ComponentRegistry,
     archetypes: &mut Vec<Archetype>,
     src: u32,
     bundle: &B,
@@ -2025,7 +4749,7 @@ where
     match raw_entry {
         RawEntryMut::Occupied(entry) => entry.into_mut(),
         RawEntryMut::Vacant(entry) => {
-            let info = make_insert_bundle_info(archetypes, src, bundle);
+            let info = make_insert_bundle_info(registry, archetypes, src, bundle);
             let (_, info) = entry.insert(bundle.with_ids(|ids| (src, ids.into())), info);
             info
         }
@@ -2035,6 +4759,7 @@ where
 fn cached_insert_bundle_info<'a, B>(
     keys: &'a mut HashMap<(u32, TypeId), InsertBundleInfo, MulHasherBuilder>,
     ids: &'a mut HashMap<(u32, Vec<TypeId>), InsertBundleInfo, MulHasherBuilder>,
+    registry: &ComponentRegistry,
     archetypes: &mut Vec<Archetype>,
     src: u32,
     bundle: &B,
@@ -2043,11 +4768,11 @@ where
     B: DynamicBundle,
 {
     match B::key() {
-        None => get_insert_bundle_info(ids, archetypes, src, bundle),
+        None => get_insert_bundle_info(ids, registry, archetypes, src, bundle),
         Some(key) => match keys.entry((src, key)) {
             Entry::Occupied(entry) => entry.into_mut(),
             Entry::Vacant(entry) => {
-                let info = get_insert_bundle_info(ids, archetypes, src, bundle);
+                let info = get_insert_bundle_info(ids, registry, archetypes, src, bundle);
                 entry.insert(info.clone())
             }
         },
@@ -2062,16 +4787,19 @@ where
         let ids = archetypes[src as usize]
             .ids()
             .filter(|id| *id != TypeId::of::<T>());
-        a.matches(ids)
+        a.shared_values() == archetypes[src as usize].shared_values() && a.matches(ids)
     }) {
         None => {
             assert!(archetypes.len() < MAX_IDX_USIZE, "Too many archetypes");
+            assert_archetypes_capacity(archetypes, archetypes[src as usize].config());
 
             let archetype = Archetype::new(
                 archetypes[src as usize]
                     .infos()
                     .filter(|info| info.id != TypeId::of::<T>()),
-            );
+                archetypes[src as usize].config().clone(),
+            )
+            .with_shared(archetypes[src as usize].shared_values().clone());
 
             // let meta = RemoveMeta::new::<T>(&archetypes[src as usize], &archetype);
 
@@ -2118,17 +4846,22 @@ where
         .ids()
         .filter(|id| !B::static_contains_id(*id));
 
-    match archetypes.iter().position(|a| a.matches(ids.clone())) {
+    match archetypes.iter().position(|a| {
+        a.shared_values() == archetypes[src as usize].shared_values() && a.matches(ids.clone())
+    }) {
         None => {
             drop(ids);
 
             assert!(archetypes.len() < MAX_IDX_USIZE, "Too many archetypes");
+            assert_archetypes_capacity(archetypes, archetypes[src as usize].config());
 
             let archetype = Archetype::new(
                 archetypes[src as usize]
                     .infos()
                     .filter(|info| !B::static_contains_id(info.id)),
-            );
+                archetypes[src as usize].config().clone(),
+            )
+            .with_shared(archetypes[src as usize].shared_values().clone());
 
             // let meta = RemoveBundleMeta::new::<B>(&archetypes[src as usize], &archetype);
             archetypes.push(archetype);
@@ -0,0 +1,67 @@
+use core::fmt;
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{archetype::Archetype, entity::EntityId};
+
+use super::EntityRef;
+
+type Observer = dyn FnMut(EntityId, &EntityRef<'_>);
+
+/// Callbacks registered with [`World::on_spawn`] and [`World::on_despawn`].
+///
+/// [`World::on_spawn`]: `edict::world::World::on_spawn`
+/// [`World::on_despawn`]: `edict::world::World::on_despawn`
+#[derive(Default)]
+pub(super) struct Observers {
+    pub spawn: Vec<Box<Observer>>,
+    pub despawn: Vec<Box<Observer>>,
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observers")
+            .field("spawn", &self.spawn.len())
+            .field("despawn", &self.despawn.len())
+            .finish()
+    }
+}
+
+impl Observers {
+    /// Notifies spawn observers about entity spawned at specified location.
+    #[inline]
+    pub fn spawned(&mut self, id: EntityId, archetype_idx: u32, idx: u32, archetype: &Archetype) {
+        notify(&mut self.spawn, id, archetype_idx, idx, archetype);
+    }
+
+    /// Notifies despawn observers about entity at specified location
+    /// that is about to be despawned.
+    #[inline]
+    pub fn despawned(&mut self, id: EntityId, archetype_idx: u32, idx: u32, archetype: &Archetype) {
+        notify(&mut self.despawn, id, archetype_idx, idx, archetype);
+    }
+}
+
+#[inline]
+fn notify(
+    observers: &mut [Box<Observer>],
+    id: EntityId,
+    archetype_idx: u32,
+    idx: u32,
+    archetype: &Archetype,
+) {
+    if observers.is_empty() {
+        return;
+    }
+
+    let entity = EntityRef {
+        id,
+        archetype_idx,
+        idx,
+        archetype,
+    };
+
+    for observer in observers {
+        observer(id, &entity);
+    }
+}
@@ -0,0 +1,66 @@
+use core::{any::type_name, cell::Ref};
+
+use crate::{
+    allocator::Allocator,
+    entity::EntityId,
+    spatial::{Position, SpatialGrid},
+};
+
+use super::World;
+
+impl<A: Allocator> World<A> {
+    /// Adds spatial index of entities by position component `P`.
+    /// Does nothing if spatial index for `P` is already added.
+    ///
+    /// Entities are bucketed into grid of cells with `cell_size` side.
+    /// Cell size close to typical query radius works best.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell_size` is not positive.
+    pub fn add_spatial_index<P>(&mut self, cell_size: f32)
+    where
+        P: Position,
+    {
+        self.indices.add(SpatialGrid::<P>::new(cell_size));
+    }
+
+    /// Returns iterator over entities with position `P`
+    /// within `radius` from `center`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if spatial index for `P` was not added with [`World::add_spatial_index`].
+    pub fn lookup_radius<P>(&self, center: [f32; 3], radius: f32) -> impl Iterator<Item = EntityId>
+    where
+        P: Position,
+    {
+        self.spatial_index::<P>().radius(center, radius).into_iter()
+    }
+
+    /// Returns iterator over entities with position `P`
+    /// inside axis-aligned box between `min` and `max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if spatial index for `P` was not added with [`World::add_spatial_index`].
+    pub fn lookup_aabb<P>(&self, min: [f32; 3], max: [f32; 3]) -> impl Iterator<Item = EntityId>
+    where
+        P: Position,
+    {
+        self.spatial_index::<P>().aabb(min, max).into_iter()
+    }
+
+    fn spatial_index<P>(&self) -> Ref<'_, SpatialGrid<P>>
+    where
+        P: Position,
+    {
+        match self
+            .indices
+            .get::<SpatialGrid<P>>(&self.archetypes, self.epoch)
+        {
+            None => panic!("Spatial index for `{}` is not added", type_name::<P>()),
+            Some(index) => index,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Implements `Query` outside of the crate using public `Archetype` API.
+
+use core::{any::TypeId, marker::PhantomData, ptr::NonNull};
+
+use edict::{
+    archetype::{chunk_idx, Archetype, CHUNK_LEN},
+    component::Component,
+    query::{Access, Fetch, ImmutableQuery, Query},
+    world::World,
+};
+
+/// Query that yields components modified since tracks together with their versions.
+struct Versioned<T>(PhantomData<T>);
+
+struct FetchVersioned<T> {
+    tracks: u64,
+    ptr: NonNull<T>,
+    entity_versions: NonNull<u64>,
+    chunk_versions: NonNull<u64>,
+}
+
+impl<'a, T> Fetch<'a> for FetchVersioned<T>
+where
+    T: 'a,
+{
+    type Item = (&'a T, u64);
+
+    fn dangling() -> Self {
+        FetchVersioned {
+            tracks: 0,
+            ptr: NonNull::dangling(),
+            entity_versions: NonNull::dangling(),
+            chunk_versions: NonNull::dangling(),
+        }
+    }
+
+    unsafe fn skip_chunk(&self, chunk_idx: usize) -> bool {
+        *self.chunk_versions.as_ptr().add(chunk_idx) <= self.tracks
+    }
+
+    unsafe fn skip_item(&self, idx: usize) -> bool {
+        *self.entity_versions.as_ptr().add(idx) <= self.tracks
+    }
+
+    unsafe fn get_item(&mut self, idx: usize) -> (&'a T, u64) {
+        (
+            &*self.ptr.as_ptr().add(idx),
+            *self.entity_versions.as_ptr().add(idx),
+        )
+    }
+}
+
+unsafe impl<T> Query for Versioned<T>
+where
+    T: Component,
+{
+    type Fetch = FetchVersioned<T>;
+
+    fn tracks() -> bool {
+        true
+    }
+
+    fn is_valid() -> bool {
+        true
+    }
+
+    fn access(ty: TypeId) -> Access {
+        <&T as Query>::access(ty)
+    }
+
+    fn allowed_with<Q: Query>() -> bool {
+        <&T as Query>::allowed_with::<Q>()
+    }
+
+    fn skip_archetype(archetype: &Archetype, tracks: u64) -> bool {
+        match archetype.column::<T>() {
+            None => true,
+            Some(column) => column.version() <= tracks,
+        }
+    }
+
+    unsafe fn fetch(archetype: &Archetype, tracks: u64, _epoch: u64) -> Option<FetchVersioned<T>> {
+        let column = archetype.column::<T>()?;
+        debug_assert_eq!(column.len(), archetype.entities().len());
+
+        Some(FetchVersioned {
+            tracks,
+            ptr: column.ptr(),
+            entity_versions: column.entity_versions(),
+            chunk_versions: column.chunk_versions(),
+        })
+    }
+}
+
+unsafe impl<T> ImmutableQuery for Versioned<T> where T: Component {}
+
+#[test]
+fn external_query() {
+    let mut world = World::new();
+
+    let entities: Vec<_> = (0..CHUNK_LEN as u32 + 10)
+        .map(|i| world.spawn((i,)))
+        .collect();
+    world.spawn((1u8,));
+
+    let mut tracks = world.tracks();
+    let all = world
+        .query::<Versioned<u32>>()
+        .tracked_iter(&mut tracks)
+        .count();
+    assert_eq!(all, entities.len());
+
+    let first = entities[CHUNK_LEN];
+    let last = *entities.last().unwrap();
+    assert_eq!(chunk_idx(CHUNK_LEN), 1);
+    *world.query_one_mut::<&mut u32, _>(&first).unwrap() = 42;
+    *world.query_one_mut::<&mut u32, _>(&last).unwrap() = 43;
+
+    let mut modified = Vec::new();
+    world
+        .query::<Versioned<u32>>()
+        .tracked_iter(&mut tracks)
+        .for_each(|(e, (v, version))| modified.push((e, *v, version > 0)));
+    assert_eq!(modified, [(first, 42, true), (last, 43, true)]);
+
+    let none = world
+        .query::<Versioned<u32>>()
+        .tracked_iter(&mut tracks)
+        .count();
+    assert_eq!(none, 0);
+}